@@ -1,5 +1,6 @@
 use tauri::{AppHandle, Emitter, State};
-use crate::state::{AppState, OperationResult};
+use crate::error::DrodeError;
+use crate::state::AppState;
 use crate::db::activity::{self, ActivityEvent};
 
 #[tauri::command]
@@ -13,13 +14,13 @@ pub fn log_activity(
     detail_json: Option<String>,
     severity: Option<String>,
     source_id: Option<String>,
-) -> OperationResult {
-    let db = state.db.lock().unwrap();
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
     let event_id = uuid::Uuid::new_v4().to_string();
     let severity = severity.unwrap_or_else(|| "info".to_string());
     let created_at = chrono::Utc::now().timestamp_millis();
 
-    match activity::insert_event(
+    let id = activity::insert_event(
         &db,
         &event_id,
         &project_path,
@@ -30,35 +31,23 @@ pub fn log_activity(
         &severity,
         source_id.as_deref(),
         created_at,
-    ) {
-        Ok(id) => {
-            // Emit event to frontend
-            let event = ActivityEvent {
-                id,
-                event_id,
-                project_path,
-                category,
-                event_type,
-                title,
-                detail_json,
-                severity,
-                source_id,
-                created_at,
-            };
-            let _ = app_handle.emit("activity-event", &event);
+    )?;
 
-            OperationResult {
-                success: true,
-                error: None,
-                content: None,
-            }
-        }
-        Err(e) => OperationResult {
-            success: false,
-            error: Some(e.to_string()),
-            content: None,
-        },
-    }
+    // Emit event to frontend
+    let event = ActivityEvent {
+        id,
+        event_id,
+        project_path,
+        category,
+        event_type,
+        title,
+        detail_json,
+        severity,
+        source_id,
+        created_at,
+    };
+    let _ = app_handle.emit("activity-event", &event);
+    Ok(())
 }
 
 #[tauri::command]
@@ -68,30 +57,19 @@ pub fn get_activity_log(
     category: Option<String>,
     limit: Option<i32>,
     before_id: Option<i64>,
-) -> Vec<ActivityEvent> {
-    let db = state.db.lock().unwrap();
+) -> Result<Vec<ActivityEvent>, DrodeError> {
+    let db = state.db.get();
     let limit = limit.unwrap_or(100);
 
-    activity::query_events(&db, &project_path, category.as_deref(), limit, before_id)
-        .unwrap_or_default()
+    Ok(activity::query_events(&db, &project_path, category.as_deref(), limit, before_id)?)
 }
 
 #[tauri::command]
 pub fn clear_activity_log(
     state: State<AppState>,
     project_path: String,
-) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match activity::clear_events(&db, &project_path) {
-        Ok(_) => OperationResult {
-            success: true,
-            error: None,
-            content: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            error: Some(e.to_string()),
-            content: None,
-        },
-    }
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    activity::clear_events(&db, &project_path)?;
+    Ok(())
 }
@@ -0,0 +1,99 @@
+use crate::db::backup::{self, BackupInfo, IntegrityReport};
+use crate::error::DrodeError;
+use crate::state::AppState;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+// How often the scheduler wakes up to see whether a backup is due
+const SCHEDULER_TICK: Duration = Duration::from_secs(15 * 60);
+
+/// Report the startup integrity check and keep taking backups on the configured interval
+pub fn start_backup_scheduler(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        {
+            let state = app_handle.state::<AppState>();
+            let report = state.integrity.lock().unwrap().clone();
+            if let Some(report) = report.filter(|r| !r.ok) {
+                let _ = app_handle.emit("db-integrity-error", &report);
+            }
+        }
+
+        loop {
+            run_scheduled_backup(&app_handle);
+            std::thread::sleep(SCHEDULER_TICK);
+        }
+    });
+}
+
+fn run_scheduled_backup(app_handle: &AppHandle) {
+    let state = app_handle.state::<AppState>();
+    let db = state.db.get();
+
+    let interval = backup::interval_hours(&db);
+    // 0 disables scheduled backups; manual ones still work
+    if interval == 0 || !backup::is_due(&state.backups_dir, interval) {
+        return;
+    }
+
+    match backup::create(&db, &state.backups_dir) {
+        Ok(_) => {
+            backup::rotate(&state.backups_dir, backup::keep_count(&db));
+        }
+        Err(e) => eprintln!("Scheduled database backup failed: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn list_db_backups(state: State<AppState>) -> Vec<BackupInfo> {
+    backup::list(&state.backups_dir)
+}
+
+#[tauri::command]
+pub fn create_db_backup(state: State<AppState>) -> Result<BackupInfo, DrodeError> {
+    let db = state.db.get();
+    let info = backup::create(&db, &state.backups_dir)?;
+    backup::rotate(&state.backups_dir, backup::keep_count(&db));
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn restore_db_backup(
+    app_handle: AppHandle,
+    state: State<AppState>,
+    file_name: String,
+) -> Result<(), DrodeError> {
+    let path = backup::resolve(&state.backups_dir, &file_name)
+        .ok_or_else(|| DrodeError::not_found(format!("Backup {}", file_name)))?;
+
+    {
+        let mut db = state.db.get();
+        // Snapshot the current state first so a restore can itself be undone
+        backup::create(&db, &state.backups_dir)?;
+        backup::restore(&mut db, &path)?;
+    }
+
+    let _ = app_handle.emit("db-restored", &file_name);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn check_db_integrity(
+    app_handle: AppHandle,
+    state: State<AppState>,
+) -> Result<IntegrityReport, DrodeError> {
+    let report = {
+        let db = state.db.get();
+        backup::integrity_check(&db)?
+    };
+
+    *state.integrity.lock().unwrap() = Some(report.clone());
+    if !report.ok {
+        let _ = app_handle.emit("db-integrity-error", &report);
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn get_db_integrity(state: State<AppState>) -> Option<IntegrityReport> {
+    state.integrity.lock().unwrap().clone()
+}
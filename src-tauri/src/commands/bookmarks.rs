@@ -0,0 +1,54 @@
+use crate::db::bookmarks::{self, Bookmark, TextRange};
+use crate::error::DrodeError;
+use crate::state::AppState;
+use tauri::State;
+
+#[tauri::command]
+pub fn add_bookmark(
+    state: State<AppState>,
+    message_id: String,
+    note: Option<String>,
+    color: Option<String>,
+    range: Option<TextRange>,
+) -> Result<Bookmark, DrodeError> {
+    let db = state.db.get();
+    bookmarks::add(&db, &message_id, note.as_deref().unwrap_or(""), color.as_deref(), range)
+}
+
+#[tauri::command]
+pub fn update_bookmark(
+    state: State<AppState>,
+    bookmark_id: i64,
+    note: Option<String>,
+    color: Option<String>,
+) -> Result<Bookmark, DrodeError> {
+    let db = state.db.get();
+    bookmarks::update(&db, bookmark_id, note.as_deref(), color.as_deref())
+}
+
+#[tauri::command]
+pub fn remove_bookmark(state: State<AppState>, bookmark_id: i64) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    bookmarks::remove(&db, bookmark_id)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_project_bookmarks(
+    state: State<'_, AppState>,
+    project_path: String,
+) -> Result<Vec<Bookmark>, DrodeError> {
+    state
+        .db
+        .run(move |db| bookmarks::list_for_project(db, &project_path).map_err(DrodeError::from))
+        .await?
+}
+
+#[tauri::command]
+pub fn list_conversation_bookmarks(
+    state: State<AppState>,
+    conversation_id: String,
+) -> Result<Vec<Bookmark>, DrodeError> {
+    let db = state.db.get();
+    Ok(bookmarks::list_for_conversation(&db, &conversation_id)?)
+}
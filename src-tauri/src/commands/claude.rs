@@ -1,50 +1,38 @@
-use crate::state::{AppState, OperationResult};
+use crate::error::DrodeError;
+use crate::state::AppState;
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 const DANGEROUS_MODE_KEY: &str = "dangerous_mode";
 
 #[tauri::command]
 pub fn get_dangerous_mode(state: State<AppState>) -> bool {
-    let db = state.db.lock().unwrap();
+    let db = state.db.get();
     crate::db::settings::get(&db, DANGEROUS_MODE_KEY)
         .map(|v| v == "true")
         .unwrap_or(false) // Default to safe mode
 }
 
 #[tauri::command]
-pub fn set_dangerous_mode(state: State<AppState>, enabled: bool) -> OperationResult {
-    let db = state.db.lock().unwrap();
+pub fn set_dangerous_mode(state: State<AppState>, enabled: bool) -> Result<(), DrodeError> {
+    let db = state.db.get();
     let value = if enabled { "true" } else { "false" };
-    match crate::db::settings::set(&db, DANGEROUS_MODE_KEY, value) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
-    }
+    crate::db::settings::set(&db, DANGEROUS_MODE_KEY, value)?;
+    Ok(())
 }
 
 #[tauri::command]
 pub fn start_claude_cli(
     state: State<AppState>,
     project_path: String,
-) -> OperationResult {
-    // Store the project path in settings
-    let db = state.db.lock().unwrap();
-    let _ = crate::db::settings::set(&db, "current_project", &project_path);
-
-    OperationResult {
-        success: true,
-        content: None,
-        error: None,
-    }
+) -> Result<(), DrodeError> {
+    // Store the project path in settings; it becomes a path policy root, so only a
+    // registered project is accepted
+    let db = state.db.get();
+    crate::commands::projects::require_registered(&db, &project_path)?;
+    crate::db::settings::set(&db, "current_project", &project_path)?;
+    Ok(())
 }
 
 #[tauri::command]
@@ -53,9 +41,9 @@ pub fn send_to_claude(
     state: State<AppState>,
     message: String,
     session_id: Option<String>,
-) -> OperationResult {
+) -> Result<(), DrodeError> {
     let (project_path, dangerous_mode) = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get();
         let path = crate::db::settings::get(&db, "current_project");
         let dangerous = crate::db::settings::get(&db, DANGEROUS_MODE_KEY)
             .map(|v| v == "true")
@@ -64,11 +52,7 @@ pub fn send_to_claude(
     };
 
     let Some(project_path) = project_path else {
-        return OperationResult {
-            success: false,
-            content: None,
-            error: Some("No project path set".to_string()),
-        };
+        return Err(DrodeError::validation("No project path set"));
     };
 
     // Spawn Claude in --print mode for this message
@@ -95,7 +79,7 @@ pub fn send_to_claude(
         }
     }
 
-    let result = Command::new("claude")
+    let mut child = Command::new("claude")
         .args(&args)
         .arg(&message)
         .current_dir(&project_path)
@@ -103,68 +87,64 @@ pub fn send_to_claude(
         .env("NO_COLOR", "1")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .spawn();
+        .spawn()
+        .map_err(|e| DrodeError::spawn("claude", e))?;
+
+    // File changes seen by the project watcher until the process exits are attributed to Claude
+    state.claude_runs.lock().unwrap().start(&project_path);
 
-    match result {
-        Ok(mut child) => {
-            let stdout = child.stdout.take();
-            let stderr = child.stderr.take();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
 
-            // Handle stdout - stream output line by line
+    // Handle stdout - stream output line by line, then wait for the process to end the run
+    {
+        let handle = app_handle.clone();
+        std::thread::spawn(move || {
             if let Some(stdout) = stdout {
-                let handle = app_handle.clone();
-                std::thread::spawn(move || {
-                    let reader = BufReader::new(stdout);
-                    for line in reader.lines() {
-                        match line {
-                            Ok(text) => {
-                                let _ = handle.emit("claude-output", serde_json::json!({
-                                    "type": "stdout",
-                                    "data": text
-                                }));
-                            }
-                            Err(_) => break,
+                let reader = BufReader::new(stdout);
+                for line in reader.lines() {
+                    match line {
+                        Ok(text) => {
+                            let _ = handle.emit("claude-output", serde_json::json!({
+                                "type": "stdout",
+                                "data": text
+                            }));
                         }
+                        Err(_) => break,
                     }
-                    // Signal completion
-                    let _ = handle.emit("claude-output", serde_json::json!({
-                        "type": "done",
-                        "data": ""
-                    }));
-                });
+                }
             }
+            let _ = child.wait();
+            handle.state::<AppState>().claude_runs.lock().unwrap().finish(&project_path);
+
+            // Signal completion
+            let _ = handle.emit("claude-output", serde_json::json!({
+                "type": "done",
+                "data": ""
+            }));
+        });
+    }
 
-            // Handle stderr
-            if let Some(stderr) = stderr {
-                let handle = app_handle.clone();
-                std::thread::spawn(move || {
-                    let reader = BufReader::new(stderr);
-                    for line in reader.lines() {
-                        match line {
-                            Ok(text) => {
-                                let _ = handle.emit("claude-output", serde_json::json!({
-                                    "type": "stderr",
-                                    "data": text
-                                }));
-                            }
-                            Err(_) => break,
-                        }
+    // Handle stderr
+    if let Some(stderr) = stderr {
+        let handle = app_handle.clone();
+        std::thread::spawn(move || {
+            let reader = BufReader::new(stderr);
+            for line in reader.lines() {
+                match line {
+                    Ok(text) => {
+                        let _ = handle.emit("claude-output", serde_json::json!({
+                            "type": "stderr",
+                            "data": text
+                        }));
                     }
-                });
+                    Err(_) => break,
+                }
             }
-
-            OperationResult {
-                success: true,
-                content: None,
-                error: None,
-            }
-        }
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
+        });
     }
+
+    Ok(())
 }
 
 #[tauri::command]
@@ -173,28 +153,20 @@ pub fn respond_to_tool(
     _tool_use_id: String,
     _result: String,
     _is_error: bool,
-) -> OperationResult {
+) -> Result<(), DrodeError> {
     // In --print mode, tools execute automatically
     // This is a placeholder for future TTY-based implementation
-    OperationResult {
-        success: true,
-        content: None,
-        error: None,
-    }
+    Ok(())
 }
 
 #[tauri::command]
-pub fn stop_claude_cli(_state: State<AppState>) -> OperationResult {
+pub fn stop_claude_cli(_state: State<AppState>) -> Result<(), DrodeError> {
     // Nothing to stop in print mode
-    OperationResult {
-        success: true,
-        content: None,
-        error: None,
-    }
+    Ok(())
 }
 
 #[tauri::command]
 pub fn is_claude_running(state: State<AppState>) -> bool {
-    let db = state.db.lock().unwrap();
+    let db = state.db.get();
     crate::db::settings::get(&db, "current_project").is_some()
 }
@@ -1,52 +1,43 @@
-use crate::state::{
-    AppState, Conversation, ConversationMessage, ConversationSummary, OperationResult,
-};
-use tauri::State;
+use crate::db::conversations::ConversationFilter;
+use crate::db::import_cli::ImportSummary;
+use crate::db::search::SearchResult;
+use crate::db::tags::TagInfo;
+use crate::error::DrodeError;
+use crate::state::{AppState, Conversation, ConversationMessage, ConversationPage, ConversationSummary};
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
 
 // Legacy single-conversation commands
 
 #[tauri::command]
 pub fn save_conversation(
+    app_handle: AppHandle,
     state: State<AppState>,
     project_path: String,
     messages: Vec<ConversationMessage>,
-) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match crate::db::conversations::save_legacy(&db, &project_path, &messages) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(format!("Failed to save: {}", e)),
-        },
-    }
+) -> Result<(), DrodeError> {
+    let conversation_id = {
+        let db = state.db.get();
+        crate::db::conversations::save_legacy(&db, &project_path, &messages)?
+    };
+    crate::commands::summaries::schedule(&app_handle, &conversation_id);
+    Ok(())
 }
 
 #[tauri::command]
-pub fn load_conversation(state: State<AppState>, project_path: String) -> Vec<ConversationMessage> {
-    let db = state.db.lock().unwrap();
-    crate::db::conversations::load_legacy(&db, &project_path)
+pub fn load_conversation(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<Vec<ConversationMessage>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::conversations::load_legacy(&db, &project_path)?)
 }
 
 #[tauri::command]
-pub fn clear_conversation(state: State<AppState>, project_path: String) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match crate::db::conversations::clear_legacy(&db, &project_path) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(format!("Failed to clear: {}", e)),
-        },
-    }
+pub fn clear_conversation(state: State<AppState>, project_path: String) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::conversations::clear_legacy(&db, &project_path)?;
+    Ok(())
 }
 
 // Multi-conversation management
@@ -55,9 +46,10 @@ pub fn clear_conversation(state: State<AppState>, project_path: String) -> Opera
 pub fn list_conversations(
     state: State<AppState>,
     project_path: String,
-) -> Vec<ConversationSummary> {
-    let db = state.db.lock().unwrap();
-    crate::db::conversations::list(&db, &project_path)
+    filter: Option<ConversationFilter>,
+) -> Result<ConversationPage, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::conversations::list(&db, &project_path, &filter.unwrap_or_default())?)
 }
 
 #[tauri::command]
@@ -65,9 +57,9 @@ pub fn create_conversation(
     state: State<AppState>,
     project_path: String,
     name: String,
-) -> Option<ConversationSummary> {
-    let db = state.db.lock().unwrap();
-    crate::db::conversations::create(&db, &project_path, &name)
+) -> Result<ConversationSummary, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::conversations::create(&db, &project_path, &name)?)
 }
 
 #[tauri::command]
@@ -75,31 +67,25 @@ pub fn get_conversation(
     state: State<AppState>,
     project_path: String,
     conversation_id: String,
-) -> Option<Conversation> {
-    let db = state.db.lock().unwrap();
-    crate::db::conversations::get(&db, &project_path, &conversation_id)
+) -> Result<Option<Conversation>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::conversations::get(&db, &project_path, &conversation_id)?)
 }
 
 #[tauri::command]
 pub fn save_conversation_messages(
+    app_handle: AppHandle,
     state: State<AppState>,
     _project_path: String,
     conversation_id: String,
     messages: Vec<ConversationMessage>,
-) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match crate::db::conversations::save_messages(&db, &conversation_id, &messages) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(format!("Conversation not found: {}", e)),
-        },
+) -> Result<(), DrodeError> {
+    {
+        let db = state.db.get();
+        crate::db::conversations::save_messages(&db, &conversation_id, &messages)?;
     }
+    crate::commands::summaries::schedule(&app_handle, &conversation_id);
+    Ok(())
 }
 
 #[tauri::command]
@@ -107,20 +93,10 @@ pub fn delete_conversation(
     state: State<AppState>,
     project_path: String,
     conversation_id: String,
-) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match crate::db::conversations::delete(&db, &project_path, &conversation_id) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(format!("Failed to delete: {}", e)),
-        },
-    }
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::conversations::delete(&db, &project_path, &conversation_id)?;
+    Ok(())
 }
 
 #[tauri::command]
@@ -129,26 +105,16 @@ pub fn rename_conversation(
     _project_path: String,
     conversation_id: String,
     new_name: String,
-) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match crate::db::conversations::rename(&db, &conversation_id, &new_name) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(format!("Conversation not found: {}", e)),
-        },
-    }
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::conversations::rename(&db, &conversation_id, &new_name)?;
+    Ok(())
 }
 
 #[tauri::command]
-pub fn get_active_conversation(state: State<AppState>, project_path: String) -> Option<String> {
-    let db = state.db.lock().unwrap();
-    crate::db::conversations::get_active(&db, &project_path)
+pub fn get_active_conversation(state: State<AppState>, project_path: String) -> Result<Option<String>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::conversations::get_active(&db, &project_path)?)
 }
 
 #[tauri::command]
@@ -156,18 +122,172 @@ pub fn set_active_conversation(
     state: State<AppState>,
     project_path: String,
     conversation_id: String,
-) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match crate::db::conversations::set_active(&db, &project_path, &conversation_id) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(format!("Failed to set active conversation: {}", e)),
-        },
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::conversations::set_active(&db, &project_path, &conversation_id)?;
+    Ok(())
+}
+
+// Trash
+
+#[tauri::command]
+pub fn list_trashed_conversations(
+    state: State<AppState>,
+    project_path: String,
+) -> Result<Vec<ConversationSummary>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::conversations::list_trash(&db, &project_path)?)
+}
+
+#[tauri::command]
+pub fn restore_conversation(state: State<AppState>, conversation_id: String) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::conversations::restore(&db, &conversation_id)?;
+    Ok(())
+}
+
+/// Returns how many conversations were purged
+#[tauri::command]
+pub fn empty_trash(state: State<AppState>, project_path: Option<String>) -> Result<usize, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::conversations::purge_trash(&db, project_path.as_deref(), None)?)
+}
+
+#[tauri::command]
+pub fn get_trash_retention_days(state: State<AppState>) -> u32 {
+    let db = state.db.get();
+    crate::db::conversations::trash_retention_days(&db)
+}
+
+#[tauri::command]
+pub fn set_trash_retention_days(state: State<AppState>, days: u32) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::settings::set(&db, crate::db::conversations::TRASH_RETENTION_KEY, &days.to_string())?;
+    Ok(())
+}
+
+// Organisation: pinning, archiving and tags
+
+#[tauri::command]
+pub fn set_conversation_pinned(
+    state: State<AppState>,
+    conversation_id: String,
+    pinned: bool,
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::conversations::set_pinned(&db, &conversation_id, pinned)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_conversation_archived(
+    state: State<AppState>,
+    conversation_id: String,
+    archived: bool,
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::conversations::set_archived(&db, &conversation_id, archived)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn add_conversation_tag(
+    state: State<AppState>,
+    conversation_id: String,
+    tag: String,
+) -> Result<(), DrodeError> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(DrodeError::validation("Tag name cannot be empty"));
     }
+
+    let db = state.db.get();
+    crate::db::tags::add_to_conversation(&db, &conversation_id, tag)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_conversation_tag(
+    state: State<AppState>,
+    conversation_id: String,
+    tag: String,
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    crate::db::tags::remove_from_conversation(&db, &conversation_id, tag.trim())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_conversation_tags(
+    state: State<AppState>,
+    project_path: Option<String>,
+) -> Result<Vec<TagInfo>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::tags::list(&db, project_path.as_deref())?)
+}
+
+#[tauri::command]
+pub async fn search_conversations(
+    state: State<'_, AppState>,
+    project_path: String,
+    query: String,
+) -> Result<Vec<SearchResult>, DrodeError> {
+    state
+        .db
+        .run(move |db| crate::db::search::search_messages(db, &project_path, &query).map_err(DrodeError::from))
+        .await?
+}
+
+/// Save the conversation, with its bookmarks, as Markdown where the user picks; None when
+/// they cancel
+#[tauri::command]
+pub async fn export_conversation_markdown(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    conversation_id: String,
+) -> Result<Option<String>, DrodeError> {
+    let (name, markdown) = state
+        .db
+        .run(move |db| {
+            let markdown = crate::db::export::conversation_markdown(db, &conversation_id)?
+                .ok_or_else(|| DrodeError::not_found(format!("Conversation {}", conversation_id)))?;
+            let name: String = db.query_row(
+                "SELECT name FROM conversations WHERE id = ?1",
+                rusqlite::params![conversation_id],
+                |row| row.get(0),
+            )?;
+            Ok::<_, DrodeError>((name, markdown))
+        })
+        .await??;
+
+    let file_name = format!("{}.md", crate::dialogs::file_stem(&name));
+    crate::dialogs::save(app_handle, file_name, "Markdown", "md", markdown.into_bytes()).await
+}
+
+// Claude CLI transcript import
+
+fn claude_projects_dir() -> Option<PathBuf> {
+    // The CLI honours CLAUDE_CONFIG_DIR before falling back to ~/.claude
+    let config_dir = std::env::var_os("CLAUDE_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".claude")))?;
+    Some(config_dir.join("projects"))
+}
+
+#[tauri::command]
+pub async fn import_claude_sessions(
+    state: State<'_, AppState>,
+    project_path: Option<String>,
+) -> Result<ImportSummary, DrodeError> {
+    let projects_dir = claude_projects_dir()
+        .filter(|p| p.is_dir())
+        .ok_or_else(|| DrodeError::not_found("No Claude CLI session logs found"))?;
+
+    state
+        .db
+        .run(move |db| {
+            crate::db::import_cli::import_all(db, &projects_dir, project_path.as_deref())
+                .map_err(DrodeError::from)
+        })
+        .await?
 }
@@ -0,0 +1,68 @@
+use crate::db::file_edits::{self, EditedFile, FileEdit};
+use crate::error::DrodeError;
+use crate::path_policy::{self, PathPolicy};
+use crate::state::AppState;
+use tauri::{AppHandle, State};
+
+/// What Claude changed in the project, oldest first; narrow it with `file_path` or `conversation_id`
+#[tauri::command]
+pub async fn list_file_edits(
+    state: State<'_, AppState>,
+    project_path: String,
+    file_path: Option<String>,
+    conversation_id: Option<String>,
+) -> Result<Vec<FileEdit>, DrodeError> {
+    state
+        .db
+        .run(move |db| {
+            file_edits::list(db, &project_path, file_path.as_deref(), conversation_id.as_deref())
+                .map_err(DrodeError::from)
+        })
+        .await?
+}
+
+#[tauri::command]
+pub async fn list_edited_files(
+    state: State<'_, AppState>,
+    project_path: String,
+) -> Result<Vec<EditedFile>, DrodeError> {
+    state
+        .db
+        .run(move |db| file_edits::edited_files(db, &project_path).map_err(DrodeError::from))
+        .await?
+}
+
+#[tauri::command]
+pub fn get_file_edit_diff(state: State<AppState>, edit_id: i64) -> Result<String, DrodeError> {
+    let edit = {
+        let db = state.db.get();
+        file_edits::get(&db, edit_id)?
+            .ok_or_else(|| DrodeError::not_found(format!("File edit {}", edit_id)))?
+    };
+    // The hunk position is only looked up in files the policy allows
+    let policy = PathPolicy::load(&state.db.get());
+    let current = path_policy::read_to_string(&policy, &edit.file_path).ok();
+    Ok(file_edits::unified_diff(&edit, current.as_deref()))
+}
+
+/// Save what Claude changed in the project (or one file) as a .patch file where the user
+/// picks; see file_edits::to_patch for what it covers. None when they cancel.
+#[tauri::command]
+pub async fn export_file_edits_patch(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    project_path: String,
+    file_path: Option<String>,
+) -> Result<Option<String>, DrodeError> {
+    let edits = state
+        .db
+        .run(move |db| file_edits::list(db, &project_path, file_path.as_deref(), None).map_err(DrodeError::from))
+        .await??;
+    let policy = {
+        let db = state.db.get();
+        PathPolicy::load(&db)
+    };
+    let patch = file_edits::to_patch(&edits, |path| path_policy::read_to_string(&policy, path).ok());
+
+    crate::dialogs::save(app_handle, "claude-edits.patch".to_string(), "Patch", "patch", patch.into_bytes()).await
+}
@@ -1,7 +1,19 @@
-use crate::state::OperationResult;
+use crate::error::DrodeError;
+use crate::file_index::{self, FileMatch};
+use crate::file_ops::{self, ConflictPolicy, DeletedItem, Mode};
+use crate::file_read::{self, FileChunk, FileInfo, ReadRange};
+use crate::file_search::{SearchOptions, Searcher};
+use crate::file_visibility::{self, Visibility, VisibilityRules};
+use crate::local_history::{self, History, Source};
+use crate::path_policy::{self, Expected, FileVersion, PathPolicy};
+use crate::state::AppState;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, State};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileEntry {
@@ -11,43 +23,71 @@ pub struct FileEntry {
     is_directory: bool,
     size: u64,
     modified: String,
+    /// The name starts with a dot
+    #[serde(rename = "isHidden")]
+    is_hidden: bool,
+    /// Matched by a .gitignore; shown dimmed unless the project hides these
+    #[serde(rename = "isIgnored")]
+    is_ignored: bool,
+    /// A symbolic link; `isDirectory` and `size` describe its target when it resolves
+    #[serde(rename = "isSymlink")]
+    is_symlink: bool,
+}
+
+// Every command below only accepts paths inside the current project or the allowed
+// folders (see path_policy.rs); anything else fails with `path_denied`.
+fn policy(state: &AppState) -> PathPolicy {
+    let db = state.db.get();
+    PathPolicy::load(&db)
 }
 
 #[tauri::command]
-pub fn read_directory(dir_path: String) -> Vec<FileEntry> {
-    let path = PathBuf::from(&dir_path);
+pub fn read_directory(state: State<AppState>, dir_path: String) -> Result<Vec<FileEntry>, DrodeError> {
+    let real = policy(&state).resolve(&dir_path)?;
+    let dir = std::path::Path::new(&dir_path);
+    let mut visibility = {
+        let db = state.db.get();
+        Visibility::load(&db, &real)
+    };
     let mut entries = Vec::new();
 
-    if let Ok(read_dir) = fs::read_dir(&path) {
-        for entry in read_dir.flatten() {
-            let name = entry.file_name().to_string_lossy().to_string();
+    for entry in fs::read_dir(&real).map_err(|e| DrodeError::io(&dir_path, e))?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+        // Follow links so a linked folder can be expanded; a dangling one is listed as is
+        let metadata = match fs::metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            },
+        };
+        let Some(is_ignored) = visibility.check(&real.join(&name), metadata.is_dir()) else {
+            continue;
+        };
 
-            // Skip hidden files except .gitignore
-            if name.starts_with('.') && name != ".gitignore" {
-                continue;
-            }
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| {
+                chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
 
-            if let Ok(metadata) = entry.metadata() {
-                let modified = metadata
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map(|d| {
-                        chrono::DateTime::from_timestamp(d.as_secs() as i64, 0)
-                            .map(|dt| dt.to_rfc3339())
-                            .unwrap_or_default()
-                    })
-                    .unwrap_or_default();
-
-                entries.push(FileEntry {
-                    name,
-                    path: entry.path().to_string_lossy().to_string(),
-                    is_directory: metadata.is_dir(),
-                    size: metadata.len(),
-                    modified,
-                });
-            }
-        }
+        entries.push(FileEntry {
+            is_hidden: name.starts_with('.'),
+            name,
+            // Keep the caller's spelling of the folder so paths match the tree it came from
+            path: dir.join(entry.file_name()).to_string_lossy().to_string(),
+            is_directory: metadata.is_dir(),
+            size: metadata.len(),
+            modified,
+            is_ignored,
+            is_symlink,
+        });
     }
 
     // Sort: directories first, then alphabetically
@@ -61,114 +101,417 @@ pub fn read_directory(dir_path: String) -> Vec<FileEntry> {
         }
     });
 
-    entries
+    Ok(entries)
+}
+
+/// A file's text and the version it was read at
+#[derive(Debug, Serialize, Clone)]
+pub struct FileContent {
+    content: String,
+    version: FileVersion,
+}
+
+/// The whole file as text, with the version of the bytes decoded; pass its hash back to
+/// `write_file` so the save is refused if the file changed since
+#[tauri::command]
+pub fn read_file(state: State<AppState>, file_path: String) -> Result<FileContent, DrodeError> {
+    let mut file = path_policy::open(&policy(&state), &file_path)?;
+    let bytes = file_read::read_bytes(&mut file, &file_path)?;
+    let version = path_policy::version_of(&file, &file_path, &bytes)?;
+    let content = file_read::decode_text(&bytes, &file_path)?;
+    Ok(FileContent { content, version })
+}
+
+/// Part of a file (or all of it) with its encoding and line endings. Binary files come
+/// back as a hex dump; see `ReadRange` for reading huge files by lines or bytes.
+#[tauri::command]
+pub async fn read_file_range(
+    state: State<'_, AppState>,
+    file_path: String,
+    range: Option<ReadRange>,
+) -> Result<FileChunk, DrodeError> {
+    let mut file = path_policy::open(&policy(&state), &file_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        file_read::read_range(&mut file, &file_path, &range.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Reading the file failed: {}", e)))?
+}
+
+/// Size, encoding, line endings and line count; reads the whole file to count lines
+#[tauri::command]
+pub async fn get_file_info(state: State<'_, AppState>, file_path: String) -> Result<FileInfo, DrodeError> {
+    let mut file = path_policy::open(&policy(&state), &file_path)?;
+    tauri::async_runtime::spawn_blocking(move || file_read::info(&mut file, &file_path))
+        .await
+        .map_err(|e| DrodeError::Internal(format!("Reading the file failed: {}", e)))?
+}
+
+/// Mtime, hash and size of a file, to pass back to `write_file` when saving
+#[tauri::command]
+pub fn get_file_version(state: State<AppState>, file_path: String) -> Result<FileVersion, DrodeError> {
+    path_policy::version(&policy(&state), &file_path)
+}
+
+/// Save atomically, in the encoding the file already has (see file_read::encode_like).
+/// With `expected_mtime` or `expected_hash` the save only goes ahead if the file is still
+/// that version; otherwise it fails with a conflict carrying the current content. Returns
+/// the version just written.
+#[tauri::command]
+pub fn write_file(
+    state: State<AppState>,
+    file_path: String,
+    content: String,
+    expected_mtime: Option<i64>,
+    expected_hash: Option<String>,
+) -> Result<FileVersion, DrodeError> {
+    let policy = policy(&state);
+    let real = policy.resolve(&file_path)?;
+    let expected = Expected {
+        mtime: expected_mtime,
+        hash: expected_hash,
+    };
+    // Saved in the encoding the file has now, as read_file decoded it
+    let existing = match path_policy::open(&policy, &file_path) {
+        Ok(mut file) => {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes).map_err(|e| DrodeError::io(&file_path, e))?;
+            bytes
+        }
+        Err(DrodeError::NotFound(_)) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let bytes = file_read::encode_like(&existing, &content, &file_path)?;
+    // What was there before, in case it changed outside drode since the last snapshot
+    remember(&state, |db, history| history.snapshot_file(db, &real, None, Source::External).map(|_| ()));
+    let version = path_policy::write_checked(&policy, &file_path, &bytes, &expected)?;
+    remember(&state, |db, history| history.snapshot(db, &real, None, Source::Save, &bytes).map(|_| ()));
+    Ok(version)
+}
+
+#[tauri::command]
+pub fn create_file(state: State<AppState>, file_path: String) -> Result<(), DrodeError> {
+    path_policy::write(&policy(&state), &file_path, b"").map(|_| ())
+}
+
+#[tauri::command]
+pub fn create_directory(state: State<AppState>, dir_path: String) -> Result<(), DrodeError> {
+    let real = policy(&state).resolve(&dir_path)?;
+    fs::create_dir_all(&real).map_err(|e| DrodeError::io(&dir_path, e))
+}
+
+#[tauri::command]
+pub fn delete_file(state: State<AppState>, file_path: String) -> Result<(), DrodeError> {
+    let real = policy(&state).resolve_entry(&file_path)?;
+    remember(&state, |db, history| history.snapshot_tree(db, &real, None, Source::Delete).map(|_| ()));
+    file_ops::discard(&real)
+}
+
+/// Entries deleted from the project or the allowed folders that are still in the trash,
+/// newest first. Not available on macOS.
+#[tauri::command]
+pub fn list_deleted_files(state: State<AppState>) -> Result<Vec<DeletedItem>, DrodeError> {
+    file_ops::list_deleted(&policy(&state))
+}
+
+/// Undo a `delete_file`: move the newest trashed entry that was at `file_path` back
+#[tauri::command]
+pub fn restore_deleted(state: State<AppState>, file_path: String) -> Result<(), DrodeError> {
+    file_ops::restore_deleted(&policy(&state), &file_path).map(|_| ())
+}
+
+/// Copy files and folders into `target_dir`; `conflict` defaults to skip. Returns an
+/// operation id right away. Progress arrives as `file-op-progress` events
+/// ({ operationId, processed, total, path }) and the batch ends with one `file-op-done`
+/// event ({ operationId, done, skipped, failed, fileCount }).
+#[tauri::command]
+pub fn copy_paths(
+    app_handle: AppHandle,
+    sources: Vec<String>,
+    target_dir: String,
+    conflict: Option<ConflictPolicy>,
+) -> Result<String, DrodeError> {
+    start_transfer(app_handle, &sources, &target_dir, Mode::Copy, conflict.unwrap_or_default())
+}
+
+/// Like `copy_paths`, but moves; the moves are recorded in the local history as renames
+#[tauri::command]
+pub fn move_paths(
+    app_handle: AppHandle,
+    sources: Vec<String>,
+    target_dir: String,
+    conflict: Option<ConflictPolicy>,
+) -> Result<String, DrodeError> {
+    start_transfer(app_handle, &sources, &target_dir, Mode::Move, conflict.unwrap_or_default())
+}
+
+/// Copy an entry next to itself as "name copy", with the same events as `copy_paths`
+#[tauri::command]
+pub fn duplicate_path(app_handle: AppHandle, path: String) -> Result<String, DrodeError> {
+    let parent = std::path::Path::new(&path)
+        .parent()
+        .ok_or_else(|| DrodeError::validation(format!("{} has no parent folder", path)))?
+        .to_string_lossy()
+        .to_string();
+    start_transfer(app_handle, &[path], &parent, Mode::Copy, ConflictPolicy::Rename)
+}
+
+fn start_transfer(
+    app_handle: AppHandle,
+    sources: &[String],
+    target_dir: &str,
+    mode: Mode,
+    conflict: ConflictPolicy,
+) -> Result<String, DrodeError> {
+    let policy = policy(&app_handle.state::<AppState>());
+    let target = policy.resolve(target_dir)?;
+    if !target.is_dir() {
+        return Err(DrodeError::validation(format!("{} is not a folder", target_dir)));
+    }
+    let sources = sources
+        .iter()
+        .map(|source| policy.resolve_entry(source))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let operation_id = uuid::Uuid::new_v4().to_string();
+    let id = operation_id.clone();
+    std::thread::spawn(move || {
+        let summary = file_ops::transfer(&sources, &target, mode, conflict, &mut |progress| {
+            let mut event = serde_json::to_value(progress).unwrap_or_default();
+            event["operationId"] = serde_json::json!(id);
+            let _ = app_handle.emit("file-op-progress", event);
+        });
+        if mode == Mode::Move {
+            let state = app_handle.state::<AppState>();
+            for moved in &summary.done {
+                let (from, to) = (std::path::Path::new(&moved.from), std::path::Path::new(&moved.to));
+                remember(&state, |db, history| history.snapshot_tree(db, to, Some(from), Source::Rename).map(|_| ()));
+            }
+        }
+
+        let mut done = serde_json::to_value(&summary).unwrap_or_default();
+        done["operationId"] = serde_json::json!(id);
+        let _ = app_handle.emit("file-op-done", done);
+    });
+    Ok(operation_id)
+}
+
+#[tauri::command]
+pub fn rename_file(state: State<AppState>, old_path: String, new_path: String) -> Result<(), DrodeError> {
+    let policy = policy(&state);
+    let from = policy.resolve_entry(&old_path)?;
+    let to = policy.resolve_entry(&new_path)?;
+    fs::rename(&from, &to).map_err(|e| DrodeError::io(&old_path, e))?;
+    remember(&state, |db, history| history.snapshot_tree(db, &to, Some(&from), Source::Rename).map(|_| ()));
+    Ok(())
+}
+
+// Local history is best effort: a snapshot that fails is logged, never fails the operation
+pub(crate) fn remember(state: &AppState, record: impl FnOnce(&Connection, &History) -> Result<(), DrodeError>) {
+    let db = state.db.get();
+    if !local_history::settings(&db).enabled {
+        return;
+    }
+    if let Err(e) = record(&db, &state.history) {
+        eprintln!("Failed to record local history: {}", e);
+    }
 }
 
+/// False for paths outside the project as well as missing ones
 #[tauri::command]
-pub fn read_file(file_path: String) -> OperationResult {
-    match fs::read_to_string(&file_path) {
-        Ok(content) => OperationResult {
-            success: true,
-            content: Some(content),
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
+pub fn file_exists(state: State<AppState>, file_path: String) -> bool {
+    policy(&state).resolve(&file_path).is_ok_and(|real| real.exists())
+}
+
+#[tauri::command]
+pub fn get_allowed_paths(state: State<AppState>) -> Vec<String> {
+    let db = state.db.get();
+    path_policy::allowed_paths(&db)
+}
+
+/// Let the user pick another folder outside the project for the file commands to use.
+/// The folder comes from the native dialog so the webview can't widen the policy itself.
+/// Returns the updated list, or None when the dialog was cancelled.
+#[tauri::command]
+pub async fn add_allowed_path(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Option<Vec<String>>, DrodeError> {
+    let Some(folder) = crate::dialogs::pick_folder(app_handle, "Allow Folder").await? else {
+        return Ok(None);
+    };
+    let folder = path_policy::check_root(&folder)?.to_string_lossy().to_string();
+
+    let db = state.db.get();
+    let mut paths = path_policy::allowed_paths(&db);
+    if !paths.contains(&folder) {
+        paths.push(folder);
+        path_policy::set_allowed_paths(&db, &paths)?;
     }
+    Ok(Some(paths))
 }
 
+/// Stop allowing a folder; returns the updated list
 #[tauri::command]
-pub fn write_file(file_path: String, content: String) -> OperationResult {
-    match fs::write(&file_path, &content) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
+pub fn remove_allowed_path(state: State<AppState>, path: String) -> Result<Vec<String>, DrodeError> {
+    let db = state.db.get();
+    let mut paths = path_policy::allowed_paths(&db);
+    paths.retain(|p| *p != path);
+    // Stored without set_allowed_paths' checks: removing only narrows the policy, and an
+    // entry saved before those checks existed must still be removable
+    crate::db::settings::set(&db, path_policy::ALLOWED_PATHS_KEY, &serde_json::to_string(&paths)?)?;
+    Ok(paths)
+}
+
+// File watching (see watcher.rs). The open project is watched automatically;
+// these let the UI watch additional folders.
+
+/// Resume watching the project that was open when the app last quit
+pub fn watch_current_project(app_handle: &AppHandle) {
+    let current = {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.get();
+        crate::db::settings::get(&db, "current_project")
+    };
+    if let Some(path) = current {
+        if let Err(e) = crate::watcher::watch(app_handle, &path) {
+            eprintln!("Failed to watch {}: {}", path, e);
+        }
     }
 }
 
+/// Only the current project and the allowed folders can be watched. Watchers are keyed by
+/// the path as given, which is what the change events report.
 #[tauri::command]
-pub fn create_file(file_path: String) -> OperationResult {
-    match fs::write(&file_path, "") {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
+pub fn watch_project(app_handle: AppHandle, project_path: String) -> Result<(), DrodeError> {
+    let policy = {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.get();
+        PathPolicy::load(&db)
+    };
+    if !policy.resolve(&project_path)?.is_dir() {
+        return Err(DrodeError::validation(format!("{} is not a folder", project_path)));
     }
+    crate::watcher::watch(&app_handle, &project_path)
+}
+
+#[tauri::command]
+pub fn unwatch_project(app_handle: AppHandle, project_path: String) {
+    crate::watcher::unwatch(&app_handle, &project_path)
+}
+
+#[tauri::command]
+pub fn list_watched_projects(app_handle: AppHandle) -> Vec<String> {
+    crate::watcher::watched(&app_handle)
+}
+
+/// Fuzzy-find files in the current project by path. The index is built in the background
+/// when the project is opened; until then this returns nothing, and `file-index-updated`
+/// is emitted once it is ready.
+#[tauri::command]
+pub async fn find_files(
+    app_handle: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<FileMatch>, DrodeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let project = {
+            let state = app_handle.state::<AppState>();
+            let db = state.db.get();
+            crate::db::settings::get(&db, "current_project")
+        }
+        .ok_or_else(|| DrodeError::validation("No project is open"))?;
+
+        crate::watcher::watch(&app_handle, &project)?;
+        let index = crate::watcher::index(&app_handle, &project)
+            .ok_or_else(|| DrodeError::internal(format!("{} is not indexed", project)))?;
+        let limit = limit.unwrap_or(file_index::DEFAULT_LIMIT).min(file_index::MAX_LIMIT);
+        let matches = index.read().unwrap().find(&query, limit);
+        Ok(matches)
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("File search failed: {}", e)))?
+}
+
+#[tauri::command]
+pub fn get_file_index_excludes(state: State<AppState>) -> Vec<String> {
+    let db = state.db.get();
+    file_index::excludes(&db)
 }
 
+/// Gitignore-style patterns to leave out of quick-open, on top of .gitignore and .ignore
 #[tauri::command]
-pub fn create_directory(dir_path: String) -> OperationResult {
-    match fs::create_dir_all(&dir_path) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
+pub fn set_file_index_excludes(state: State<AppState>, patterns: Vec<String>) -> Result<(), DrodeError> {
+    {
+        let db = state.db.get();
+        file_index::set_excludes(&db, &patterns)?;
     }
+    for watcher in state.watchers.lock().unwrap().values() {
+        watcher.index().write().unwrap().mark_stale();
+    }
+    Ok(())
 }
 
 #[tauri::command]
-pub fn delete_file(file_path: String) -> OperationResult {
-    // Try to move to trash, fall back to delete
-    match trash::delete(&file_path) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(_) => match fs::remove_file(&file_path).or_else(|_| fs::remove_dir_all(&file_path)) {
-            Ok(_) => OperationResult {
-                success: true,
-                content: None,
-                error: None,
-            },
-            Err(e) => OperationResult {
-                success: false,
-                content: None,
-                error: Some(e.to_string()),
-            },
-        },
+pub fn get_visibility_rules(state: State<AppState>, project_id: i64) -> VisibilityRules {
+    let db = state.db.get();
+    file_visibility::rules(&db, project_id)
+}
+
+/// What `read_directory` lists inside the project: hidden files, excludes and gitignored
+/// entries
+#[tauri::command]
+pub fn set_visibility_rules(state: State<AppState>, project_id: i64, rules: VisibilityRules) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    if crate::db::projects::get(&db, project_id)?.is_none() {
+        return Err(DrodeError::not_found(format!("Project {}", project_id)));
     }
+    file_visibility::save_rules(&db, project_id, &rules)
 }
 
+/// Start a content search in the current project and return its id right away. Matches
+/// arrive as `search-results` events ({ searchId, matches }) and the run ends with one
+/// `search-done` event ({ searchId, ...summary }), also after `cancel_search`.
 #[tauri::command]
-pub fn rename_file(old_path: String, new_path: String) -> OperationResult {
-    match fs::rename(&old_path, &new_path) {
-        Ok(_) => OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
+pub fn search_in_files(app_handle: AppHandle, options: SearchOptions) -> Result<String, DrodeError> {
+    let state = app_handle.state::<AppState>();
+    let project = {
+        let db = state.db.get();
+        crate::db::settings::get(&db, "current_project")
     }
+    .ok_or_else(|| DrodeError::validation("No project is open"))?;
+    let searcher = Searcher::new(std::path::Path::new(&project), options)?;
+
+    let search_id = uuid::Uuid::new_v4().to_string();
+    let cancel = Arc::new(AtomicBool::new(false));
+    state.searches.lock().unwrap().insert(search_id.clone(), cancel.clone());
+
+    let handle = app_handle.clone();
+    let id = search_id.clone();
+    std::thread::spawn(move || {
+        let summary = searcher.run(&cancel, |matches| {
+            let _ = handle.emit(
+                "search-results",
+                serde_json::json!({ "searchId": id, "matches": matches }),
+            );
+        });
+        handle.state::<AppState>().searches.lock().unwrap().remove(&id);
+
+        let mut done = serde_json::to_value(&summary).unwrap_or_default();
+        done["searchId"] = serde_json::json!(id);
+        let _ = handle.emit("search-done", done);
+    });
+    Ok(search_id)
 }
 
+/// False if the search had already finished
 #[tauri::command]
-pub fn file_exists(file_path: String) -> bool {
-    PathBuf::from(&file_path).exists()
+pub fn cancel_search(state: State<AppState>, search_id: String) -> bool {
+    match state.searches.lock().unwrap().get(&search_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
 }
@@ -0,0 +1,38 @@
+use crate::error::DrodeError;
+use crate::git_status::{self, GitStatus};
+use crate::path_policy::PathPolicy;
+use crate::state::AppState;
+use tauri::{AppHandle, Manager};
+
+/// Branch, ahead/behind counts and changed files of the current project, or of
+/// `project_path`. `directories` aggregates the changes per folder, keyed like the paths
+/// from `read_directory`, so explorer entries can be decorated by lookup. Served from a
+/// cache the project's watcher keeps current (see `git-status-changed`); `refresh` forces
+/// a new read.
+#[tauri::command]
+pub async fn git_status(
+    app_handle: AppHandle,
+    project_path: Option<String>,
+    refresh: Option<bool>,
+) -> Result<GitStatus, DrodeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let project = {
+            let state = app_handle.state::<AppState>();
+            let db = state.db.get();
+            let project = project_path
+                .or_else(|| crate::db::settings::get(&db, "current_project"))
+                .ok_or_else(|| DrodeError::validation("No project is open"))?;
+            PathPolicy::load(&db).resolve(&project)?;
+            project
+        };
+
+        crate::watcher::watch(&app_handle, &project)?;
+        if refresh.unwrap_or(false) {
+            let state = app_handle.state::<AppState>();
+            state.git_statuses.lock().unwrap().remove(&project);
+        }
+        git_status::cached(&app_handle, &project)
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("git status failed: {}", e)))?
+}
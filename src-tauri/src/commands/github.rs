@@ -1,14 +1,11 @@
 use tauri::State;
+use crate::error::{check_response, DrodeError};
 use crate::state::AppState;
-use crate::db::oauth;
+use crate::commands::oauth;
 use serde::{Deserialize, Serialize};
 
-fn get_github_token(state: &State<AppState>) -> Result<String, String> {
-    let db = state.db.lock().unwrap();
-    oauth::get_token(&db, "github")
-        .map_err(|e| e.to_string())?
-        .map(|t| t.access_token)
-        .ok_or_else(|| "GitHub not connected. Please authenticate first.".to_string())
+async fn get_github_token(state: &State<'_, AppState>) -> Result<String, DrodeError> {
+    Ok(oauth::load_token(state, "github").await?.access_token)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -69,8 +66,8 @@ pub struct GithubPullRequest {
 }
 
 #[tauri::command]
-pub async fn github_list_repos(state: State<'_, AppState>, page: Option<i32>) -> Result<Vec<GithubRepo>, String> {
-    let token = get_github_token(&state)?;
+pub async fn github_list_repos(state: State<'_, AppState>, page: Option<i32>) -> Result<Vec<GithubRepo>, DrodeError> {
+    let token = get_github_token(&state).await?;
     let page = page.unwrap_or(1);
     let client = reqwest::Client::new();
 
@@ -80,10 +77,10 @@ pub async fn github_list_repos(state: State<'_, AppState>, page: Option<i32>) ->
         .header("User-Agent", "Drode-IDE")
         .header("Accept", "application/vnd.github+json")
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let repos: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let repos: Vec<serde_json::Value> = resp.json().await?;
 
     Ok(repos.into_iter().map(|r| GithubRepo {
         id: r["id"].as_i64().unwrap_or(0),
@@ -104,8 +101,8 @@ pub async fn github_get_repo_tree(
     repo: String,
     branch: String,
     path: Option<String>,
-) -> Result<Vec<GithubTreeEntry>, String> {
-    let token = get_github_token(&state)?;
+) -> Result<Vec<GithubTreeEntry>, DrodeError> {
+    let token = get_github_token(&state).await?;
     let client = reqwest::Client::new();
     let api_path = path.unwrap_or_default();
 
@@ -120,10 +117,10 @@ pub async fn github_get_repo_tree(
         .header("User-Agent", "Drode-IDE")
         .header("Accept", "application/vnd.github+json")
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let items: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let items: Vec<serde_json::Value> = resp.json().await?;
 
     Ok(items.into_iter().map(|item| GithubTreeEntry {
         name: item["name"].as_str().unwrap_or("").to_string(),
@@ -141,8 +138,8 @@ pub async fn github_read_file(
     repo: String,
     branch: String,
     path: String,
-) -> Result<GithubFileContent, String> {
-    let token = get_github_token(&state)?;
+) -> Result<GithubFileContent, DrodeError> {
+    let token = get_github_token(&state).await?;
     let client = reqwest::Client::new();
 
     let url = format!(
@@ -156,10 +153,10 @@ pub async fn github_read_file(
         .header("User-Agent", "Drode-IDE")
         .header("Accept", "application/vnd.github+json")
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let data: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let data: serde_json::Value = resp.json().await?;
 
     let encoding = data["encoding"].as_str().unwrap_or("base64").to_string();
     let raw_content = data["content"].as_str().unwrap_or("").to_string();
@@ -168,8 +165,8 @@ pub async fn github_read_file(
     let content = if encoding == "base64" {
         use base64::{Engine as _, engine::general_purpose::STANDARD};
         let cleaned = raw_content.replace('\n', "");
-        let bytes = STANDARD.decode(&cleaned).map_err(|e| e.to_string())?;
-        String::from_utf8(bytes).map_err(|e| e.to_string())?
+        let bytes = STANDARD.decode(&cleaned).map_err(DrodeError::internal)?;
+        String::from_utf8(bytes).map_err(|_| DrodeError::validation(format!("{} is not a UTF-8 text file", path)))?
     } else {
         raw_content
     };
@@ -193,8 +190,8 @@ pub async fn github_update_file(
     content: String,
     message: String,
     sha: String,
-) -> Result<GithubCommitResult, String> {
-    let token = get_github_token(&state)?;
+) -> Result<GithubCommitResult, DrodeError> {
+    let token = get_github_token(&state).await?;
     let client = reqwest::Client::new();
 
     use base64::{Engine as _, engine::general_purpose::STANDARD};
@@ -216,10 +213,10 @@ pub async fn github_update_file(
         .header("Accept", "application/vnd.github+json")
         .json(&body)
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let data: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let data: serde_json::Value = resp.json().await?;
 
     Ok(GithubCommitResult {
         sha: data["commit"]["sha"].as_str().unwrap_or("").to_string(),
@@ -233,8 +230,8 @@ pub async fn github_list_branches(
     state: State<'_, AppState>,
     owner: String,
     repo: String,
-) -> Result<Vec<GithubBranch>, String> {
-    let token = get_github_token(&state)?;
+) -> Result<Vec<GithubBranch>, DrodeError> {
+    let token = get_github_token(&state).await?;
     let client = reqwest::Client::new();
 
     let url = format!("https://api.github.com/repos/{}/{}/branches?per_page=100", owner, repo);
@@ -245,10 +242,10 @@ pub async fn github_list_branches(
         .header("User-Agent", "Drode-IDE")
         .header("Accept", "application/vnd.github+json")
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let branches: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let branches: Vec<serde_json::Value> = resp.json().await?;
 
     Ok(branches.into_iter().map(|b| GithubBranch {
         name: b["name"].as_str().unwrap_or("").to_string(),
@@ -264,8 +261,8 @@ pub async fn github_create_branch(
     repo: String,
     branch_name: String,
     from_branch: String,
-) -> Result<GithubBranch, String> {
-    let token = get_github_token(&state)?;
+) -> Result<GithubBranch, DrodeError> {
+    let token = get_github_token(&state).await?;
     let client = reqwest::Client::new();
 
     // First get the SHA of the source branch
@@ -276,10 +273,10 @@ pub async fn github_create_branch(
         .header("User-Agent", "Drode-IDE")
         .header("Accept", "application/vnd.github+json")
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let ref_resp = check_response("github", ref_resp).await?;
 
-    let ref_data: serde_json::Value = ref_resp.json().await.map_err(|e| e.to_string())?;
+    let ref_data: serde_json::Value = ref_resp.json().await?;
     let sha = ref_data["object"]["sha"].as_str().unwrap_or("").to_string();
 
     // Create new branch
@@ -296,10 +293,10 @@ pub async fn github_create_branch(
         .header("Accept", "application/vnd.github+json")
         .json(&body)
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let data: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let data: serde_json::Value = resp.json().await?;
 
     Ok(GithubBranch {
         name: branch_name,
@@ -314,8 +311,8 @@ pub async fn github_list_pull_requests(
     owner: String,
     repo: String,
     state_filter: Option<String>,
-) -> Result<Vec<GithubPullRequest>, String> {
-    let token = get_github_token(&state)?;
+) -> Result<Vec<GithubPullRequest>, DrodeError> {
+    let token = get_github_token(&state).await?;
     let client = reqwest::Client::new();
     let pr_state = state_filter.unwrap_or_else(|| "open".to_string());
 
@@ -330,10 +327,10 @@ pub async fn github_list_pull_requests(
         .header("User-Agent", "Drode-IDE")
         .header("Accept", "application/vnd.github+json")
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let prs: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let prs: Vec<serde_json::Value> = resp.json().await?;
 
     Ok(prs.into_iter().map(|pr| GithubPullRequest {
         number: pr["number"].as_i64().unwrap_or(0),
@@ -356,8 +353,8 @@ pub async fn github_create_pull_request(
     body: String,
     head: String,
     base: String,
-) -> Result<GithubPullRequest, String> {
-    let token = get_github_token(&state)?;
+) -> Result<GithubPullRequest, DrodeError> {
+    let token = get_github_token(&state).await?;
     let client = reqwest::Client::new();
 
     let url = format!("https://api.github.com/repos/{}/{}/pulls", owner, repo);
@@ -376,10 +373,10 @@ pub async fn github_create_pull_request(
         .header("Accept", "application/vnd.github+json")
         .json(&payload)
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("github", resp).await?;
 
-    let pr: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let pr: serde_json::Value = resp.json().await?;
 
     Ok(GithubPullRequest {
         number: pr["number"].as_i64().unwrap_or(0),
@@ -0,0 +1,67 @@
+use crate::db::file_history::{self, HistoryEntry};
+use crate::error::DrodeError;
+use crate::local_history::{self, HistorySettings};
+use crate::path_policy::{FileVersion, PathPolicy};
+use crate::state::AppState;
+use tauri::State;
+
+/// Snapshots of a file, newest first, including those from before it was renamed
+#[tauri::command]
+pub fn list_file_history(state: State<AppState>, file_path: String) -> Result<Vec<HistoryEntry>, DrodeError> {
+    let db = state.db.get();
+    let real = PathPolicy::load(&db).resolve(&file_path)?;
+    Ok(file_history::list(&db, &real.to_string_lossy())?)
+}
+
+/// The content of one snapshot, decoded lossily if it isn't UTF-8
+#[tauri::command]
+pub fn read_file_history_version(state: State<AppState>, entry_id: i64) -> Result<String, DrodeError> {
+    let db = state.db.get();
+    let entry = entry(&db, entry_id)?;
+    PathPolicy::load(&db).resolve(&entry.path)?;
+    let content = state.history.load(&entry.hash)?;
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Unified diff from one snapshot to another, or to the file as it is now when `to_id`
+/// is omitted
+#[tauri::command]
+pub fn diff_file_history(state: State<AppState>, from_id: i64, to_id: Option<i64>) -> Result<String, DrodeError> {
+    let db = state.db.get();
+    let from = entry(&db, from_id)?;
+    let to = to_id.map(|id| entry(&db, id)).transpose()?;
+    let policy = PathPolicy::load(&db);
+    policy.resolve(&from.path)?;
+    if let Some(to) = &to {
+        policy.resolve(&to.path)?;
+    }
+    local_history::diff(&db, &state.history, &policy, &from, to.as_ref())
+}
+
+/// Write a snapshot back to where its file is now. Returns the version now on disk.
+#[tauri::command]
+pub fn restore_file_version(state: State<AppState>, entry_id: i64) -> Result<FileVersion, DrodeError> {
+    let db = state.db.get();
+    let entry = entry(&db, entry_id)?;
+    let policy = PathPolicy::load(&db);
+    local_history::restore(&db, &state.history, &policy, &entry)
+}
+
+#[tauri::command]
+pub fn get_history_settings(state: State<AppState>) -> HistorySettings {
+    let db = state.db.get();
+    local_history::settings(&db)
+}
+
+/// Save the settings and apply the new retention limits right away
+#[tauri::command]
+pub fn set_history_settings(state: State<AppState>, settings: HistorySettings) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    local_history::save_settings(&db, &settings)?;
+    local_history::prune(&db, &state.history)?;
+    Ok(())
+}
+
+fn entry(db: &rusqlite::Connection, id: i64) -> Result<HistoryEntry, DrodeError> {
+    file_history::get(db, id)?.ok_or_else(|| DrodeError::not_found(format!("History entry {}", id)))
+}
@@ -1,10 +1,19 @@
 pub mod projects;
 pub mod files;
+pub mod replace;
+pub mod history;
+pub mod symbols;
+pub mod git;
 pub mod claude;
 pub mod conversations;
 pub mod terminal;
 pub mod ports;
 pub mod activity;
+pub mod usage;
+pub mod backup;
+pub mod summaries;
+pub mod bookmarks;
+pub mod file_edits;
 pub mod oauth;
 pub mod github;
 pub mod supabase;
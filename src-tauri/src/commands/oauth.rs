@@ -1,6 +1,8 @@
 use tauri::{AppHandle, Emitter, Manager, State};
-use crate::state::{AppState, OperationResult};
-use crate::db::oauth::{self, OAuthToken};
+use crate::error::DrodeError;
+use crate::state::AppState;
+use crate::db::oauth::{self, OAuthToken, TokenInfo};
+use crate::db::token_crypto::{self, TokenCipher};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -78,14 +80,14 @@ pub struct OAuthStatus {
 
 #[tauri::command]
 pub fn oauth_get_status(state: State<AppState>) -> Vec<OAuthStatus> {
-    let db = state.db.lock().unwrap();
+    let db = state.db.get();
     let providers = ["github", "supabase", "vercel"];
     let mut statuses = Vec::new();
 
     for provider in &providers {
-        match oauth::get_token(&db, provider) {
-            Ok(Some(token)) => {
-                let account_info = token.account_info_json
+        match oauth::get_account_info(&db, provider) {
+            Ok(Some(account_info_json)) => {
+                let account_info = account_info_json
                     .and_then(|s| serde_json::from_str(&s).ok());
                 statuses.push(OAuthStatus {
                     connected: true,
@@ -107,24 +109,16 @@ pub fn oauth_get_status(state: State<AppState>) -> Vec<OAuthStatus> {
 }
 
 #[tauri::command]
-pub async fn oauth_start(app_handle: AppHandle, provider: String) -> OperationResult {
-    let config = match get_provider_config(&provider) {
-        Some(c) => c,
-        None => return OperationResult {
-            success: false,
-            error: Some(format!("Unknown provider: {}", provider)),
-            content: None,
-        },
-    };
+pub async fn oauth_start(app_handle: AppHandle, provider: String) -> Result<(), DrodeError> {
+    let config = get_provider_config(&provider)
+        .ok_or_else(|| DrodeError::validation(format!("Unknown provider: {}", provider)))?;
 
-    let client_id = match std::env::var(config.client_id_env) {
-        Ok(id) => id,
-        Err(_) => return OperationResult {
-            success: false,
-            error: Some(format!("Set {} environment variable with your OAuth client ID", config.client_id_env)),
-            content: None,
-        },
-    };
+    let client_id = std::env::var(config.client_id_env).map_err(|_| {
+        DrodeError::validation(format!(
+            "Set {} environment variable with your OAuth client ID",
+            config.client_id_env
+        ))
+    })?;
 
     let state_param = generate_random_string(32);
     let (_code_verifier, code_challenge) = generate_pkce();
@@ -161,11 +155,7 @@ pub async fn oauth_start(app_handle: AppHandle, provider: String) -> OperationRe
     // Open browser
     let _ = open::that(&auth_url);
 
-    OperationResult {
-        success: true,
-        error: None,
-        content: None,
-    }
+    Ok(())
 }
 
 async fn start_callback_server(app_handle: AppHandle, provider: String) {
@@ -310,8 +300,15 @@ async fn exchange_code(app_handle: AppHandle, provider: &str, code: &str, code_v
 
             // Store token - access AppState directly from app_handle
             let app_state = app_handle.state::<AppState>();
-            let db = app_state.db.lock().unwrap();
-            if let Err(e) = oauth::store_token(&db, &token) {
+            let stored = match current_cipher(&app_state) {
+                Ok(cipher) => app_state
+                    .db
+                    .run(move |db| oauth::store_token(db, &cipher, &token).map_err(DrodeError::from))
+                    .await
+                    .and_then(|r| r),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = stored {
                 let _ = app_handle.emit("oauth-error", serde_json::json!({
                     "provider": provider, "error": format!("Failed to store token: {}", e)
                 }));
@@ -370,27 +367,129 @@ async fn fetch_account_info(provider: &str, access_token: &str) -> Option<serde_
 }
 
 #[tauri::command]
-pub fn oauth_revoke(state: State<AppState>, provider: String) -> OperationResult {
-    let db = state.db.lock().unwrap();
-    match oauth::remove_token(&db, &provider) {
-        Ok(_) => OperationResult {
-            success: true,
-            error: None,
-            content: None,
-        },
-        Err(e) => OperationResult {
-            success: false,
-            error: Some(e.to_string()),
-            content: None,
-        },
+pub fn oauth_revoke(state: State<AppState>, provider: String) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    oauth::remove_token(&db, &provider)?;
+    Ok(())
+}
+
+/// Decrypted token for backend callers (GitHub, Supabase, Vercel commands).
+/// Never return this from a command.
+pub(crate) async fn load_token(state: &AppState, provider: &str) -> Result<OAuthToken, DrodeError> {
+    let cipher = current_cipher(state)?;
+    let name = provider.to_string();
+    state
+        .db
+        .run(move |db| oauth::get_token(db, &cipher, &name).map_err(DrodeError::from))
+        .await??
+        .ok_or_else(|| DrodeError::AuthMissing {
+            provider: provider.to_string(),
+        })
+}
+
+fn current_cipher(state: &AppState) -> Result<TokenCipher, DrodeError> {
+    state
+        .token_cipher
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or(DrodeError::AuthLocked)
+}
+
+/// Token metadata only; the secrets stay in the backend
+#[tauri::command]
+pub fn oauth_get_token(state: State<AppState>, provider: String) -> Result<Option<TokenInfo>, DrodeError> {
+    let db = state.db.get();
+    Ok(oauth::get_token_info(&db, &provider)?)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenKeyStatus {
+    pub mode: String,
+    pub locked: bool,
+    #[serde(rename = "keyId")]
+    pub key_id: Option<String>,
+}
+
+#[tauri::command]
+pub fn oauth_get_key_status(state: State<AppState>) -> TokenKeyStatus {
+    let db = state.db.get();
+    let cipher = state.token_cipher.lock().unwrap();
+    TokenKeyStatus {
+        mode: if token_crypto::is_passphrase_mode(&db) { "passphrase" } else { "file" }.to_string(),
+        locked: cipher.is_none(),
+        key_id: cipher.as_ref().map(|c| c.key_id().to_string()),
+    }
+}
+
+/// Unlock passphrase-protected tokens for this session
+#[tauri::command]
+pub fn oauth_unlock(state: State<AppState>, passphrase: String) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    let cipher = token_crypto::unlock(&db, &passphrase).map_err(DrodeError::Validation)?;
+    // Rows stored before encryption was enabled get encrypted on first unlock
+    if oauth::encrypt_plaintext_rows(&db, &cipher)? > 0 {
+        let _ = db.execute_batch("VACUUM;");
+        oauth::scrub_backups(&db, &state.backups_dir, &cipher);
     }
+    *state.token_cipher.lock().unwrap() = Some(cipher);
+    Ok(())
 }
 
+/// Re-encrypt all tokens under a new key. With a passphrase the key is derived from
+/// it and the key file is removed; without one a fresh random key file is written.
+/// Returns the new key id.
 #[tauri::command]
-pub fn oauth_get_token(state: State<AppState>, provider: String) -> Option<String> {
-    let db = state.db.lock().unwrap();
-    oauth::get_token(&db, &provider)
-        .ok()
-        .flatten()
-        .map(|t| t.access_token)
+pub fn oauth_rotate_key(state: State<AppState>, passphrase: Option<String>) -> Result<String, DrodeError> {
+    let db = state.db.get();
+    let mut current = state.token_cipher.lock().unwrap();
+    let staged_key = token_crypto::staged_key_path(&state.token_key_path);
+    // Once the rows are re-encrypted the staged key is the only copy of the new key,
+    // so it must survive any failure after the commit; startup moves it into place
+    let mut committed = false;
+
+    let result = (|| -> Result<String, DrodeError> {
+        let old = current.as_ref().ok_or(DrodeError::AuthLocked)?;
+
+        let (new, salt) = match passphrase.as_deref().filter(|p| !p.is_empty()) {
+            Some(passphrase) => {
+                let (cipher, salt) =
+                    token_crypto::new_passphrase_cipher(passphrase).map_err(DrodeError::Internal)?;
+                (cipher, Some(salt))
+            }
+            None => (TokenCipher::generate(), None),
+        };
+
+        // Stage the new key file before touching rows so a crash can't leave
+        // tokens encrypted under a key that was never written down
+        if salt.is_none() {
+            token_crypto::write_key_file(&staged_key, &new).map_err(DrodeError::Internal)?;
+        }
+
+        let tx = db.unchecked_transaction()?;
+        oauth::rotate_key(&tx, old, &new)?;
+        let mode = if salt.is_some() { "passphrase" } else { "file" };
+        crate::db::settings::set(&tx, token_crypto::KEY_MODE_KEY, mode)?;
+        crate::db::settings::set(&tx, token_crypto::KEY_ID_KEY, new.key_id())?;
+        if let Some(salt) = &salt {
+            crate::db::settings::set(&tx, token_crypto::KEY_SALT_KEY, salt)?;
+        }
+        tx.commit()?;
+        committed = true;
+        *current = Some(new.clone());
+
+        if salt.is_some() {
+            let _ = std::fs::remove_file(&state.token_key_path);
+        } else {
+            std::fs::rename(&staged_key, &state.token_key_path)
+                .map_err(|e| DrodeError::io(&state.token_key_path, e))?;
+        }
+
+        Ok(new.key_id().to_string())
+    })();
+
+    if result.is_err() && !committed {
+        let _ = std::fs::remove_file(&staged_key);
+    }
+    result
 }
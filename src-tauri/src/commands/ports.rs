@@ -1,4 +1,4 @@
-use crate::state::OperationResult;
+use crate::error::DrodeError;
 use serde::{Deserialize, Serialize};
 use std::process::Command;
 
@@ -12,7 +12,7 @@ pub struct PortInfo {
 }
 
 #[tauri::command]
-pub fn list_ports() -> Vec<PortInfo> {
+pub fn list_ports() -> Result<Vec<PortInfo>, DrodeError> {
     let mut ports = Vec::new();
 
     // Use lsof to list network connections (macOS)
@@ -20,34 +20,33 @@ pub fn list_ports() -> Vec<PortInfo> {
     {
         let output = Command::new("lsof")
             .args(["-i", "-P", "-n", "-sTCP:LISTEN"])
-            .output();
+            .output()
+            .map_err(|e| DrodeError::spawn("lsof", e))?;
 
-        if let Ok(output) = output {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            for line in stdout.lines().skip(1) {
-                // Skip header
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 9 {
-                    let process_name = parts[0].to_string();
-                    let pid: u32 = parts[1].parse().unwrap_or(0);
-                    let addr = parts[8];
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for line in stdout.lines().skip(1) {
+            // Skip header
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 9 {
+                let process_name = parts[0].to_string();
+                let pid: u32 = parts[1].parse().unwrap_or(0);
+                let addr = parts[8];
 
-                    // Extract port from address (format: *:PORT or IP:PORT)
-                    if let Some(port_str) = addr.rsplit(':').next() {
-                        if let Ok(port) = port_str.parse::<u16>() {
-                            // Check if we already have this port
-                            if !ports
-                                .iter()
-                                .any(|p: &PortInfo| p.port == port && p.pid == pid)
-                            {
-                                ports.push(PortInfo {
-                                    port,
-                                    pid,
-                                    process_name,
-                                    state: "LISTEN".to_string(),
-                                    protocol: "TCP".to_string(),
-                                });
-                            }
+                // Extract port from address (format: *:PORT or IP:PORT)
+                if let Some(port_str) = addr.rsplit(':').next() {
+                    if let Ok(port) = port_str.parse::<u16>() {
+                        // Check if we already have this port
+                        if !ports
+                            .iter()
+                            .any(|p: &PortInfo| p.port == port && p.pid == pid)
+                        {
+                            ports.push(PortInfo {
+                                port,
+                                pid,
+                                process_name,
+                                state: "LISTEN".to_string(),
+                                protocol: "TCP".to_string(),
+                            });
                         }
                     }
                 }
@@ -57,47 +56,42 @@ pub fn list_ports() -> Vec<PortInfo> {
 
     // Sort by port number
     ports.sort_by_key(|p| p.port);
-    ports
+    Ok(ports)
 }
 
 #[tauri::command]
-pub fn kill_port(port: u16) -> OperationResult {
+pub fn kill_port(port: u16) -> Result<(), DrodeError> {
     // Find process using this port and kill it
     #[cfg(target_os = "macos")]
     {
         let output = Command::new("lsof")
             .args(["-i", &format!(":{}", port), "-t"])
-            .output();
+            .output()
+            .map_err(|e| DrodeError::spawn("lsof", e))?;
 
-        if let Ok(output) = output {
-            let pids = String::from_utf8_lossy(&output.stdout);
-            for pid in pids.lines() {
-                if let Ok(pid_num) = pid.trim().parse::<i32>() {
-                    // Try graceful kill first
-                    let _ = Command::new("kill")
-                        .args(["-TERM", &pid_num.to_string()])
-                        .output();
+        let pids = String::from_utf8_lossy(&output.stdout);
+        for pid in pids.lines() {
+            if let Ok(pid_num) = pid.trim().parse::<i32>() {
+                // Try graceful kill first
+                let _ = Command::new("kill")
+                    .args(["-TERM", &pid_num.to_string()])
+                    .output();
 
-                    // Wait a moment
-                    std::thread::sleep(std::time::Duration::from_millis(500));
+                // Wait a moment
+                std::thread::sleep(std::time::Duration::from_millis(500));
 
-                    // Force kill if still running
-                    let _ = Command::new("kill")
-                        .args(["-9", &pid_num.to_string()])
-                        .output();
-                }
+                // Force kill if still running
+                let _ = Command::new("kill")
+                    .args(["-9", &pid_num.to_string()])
+                    .output();
             }
-            return OperationResult {
-                success: true,
-                content: None,
-                error: None,
-            };
         }
+        Ok(())
     }
 
-    OperationResult {
-        success: false,
-        content: None,
-        error: Some("Failed to kill process on port".to_string()),
-    }
+    #[cfg(not(target_os = "macos"))]
+    Err(DrodeError::internal(format!(
+        "Killing the process on port {} is not supported on this platform",
+        port
+    )))
 }
@@ -1,30 +1,150 @@
+use crate::db::projects::{Project, ProjectUpdate};
+use crate::error::DrodeError;
+use crate::path_policy;
 use crate::state::AppState;
-use tauri::State;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::{AppHandle, State};
 
 #[tauri::command]
-pub fn get_recent_projects(state: State<AppState>) -> Vec<String> {
-    let db = state.db.lock().unwrap();
-    crate::db::projects::get_recent(&db)
+pub fn get_recent_projects(state: State<AppState>) -> Result<Vec<String>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::projects::get_recent(&db)?)
 }
 
 #[tauri::command]
 pub fn get_current_project(state: State<AppState>) -> Option<String> {
-    let db = state.db.lock().unwrap();
+    let db = state.db.get();
     crate::db::settings::get(&db, "current_project")
 }
 
+/// Switch to a registered project. The project is a root of the path policy, so a folder
+/// that isn't one yet has to be chosen in the native dialog (`open_project_folder`).
 #[tauri::command]
-pub fn set_current_project(state: State<AppState>, project_path: String) -> bool {
-    let db = state.db.lock().unwrap();
+pub fn set_current_project(
+    app_handle: AppHandle,
+    state: State<AppState>,
+    project_path: String,
+) -> Result<(), DrodeError> {
+    {
+        let db = state.db.get();
+        require_registered(&db, &project_path)?;
+    }
+    open_project(&app_handle, &state, &project_path)
+}
+
+/// Pick a folder in the native dialog and open it as the current project, registering it
+/// if it is new. Returns its path, or None when the dialog was cancelled.
+#[tauri::command]
+pub async fn open_project_folder(app_handle: AppHandle, state: State<'_, AppState>) -> Result<Option<String>, DrodeError> {
+    let Some(folder) = crate::dialogs::pick_folder(app_handle.clone(), "Open Project").await? else {
+        return Ok(None);
+    };
+    let project_path = folder.to_string_lossy().to_string();
+    open_project(&app_handle, &state, &project_path)?;
+    Ok(Some(project_path))
+}
+
+pub(crate) fn require_registered(conn: &rusqlite::Connection, project_path: &str) -> Result<(), DrodeError> {
+    if crate::db::projects::get_by_path(conn, project_path)?.is_none() {
+        return Err(DrodeError::validation(format!(
+            "{} isn't a known project; open it with Open Project first",
+            project_path
+        )));
+    }
+    Ok(())
+}
 
-    if crate::db::settings::set(&db, "current_project", &project_path).is_err() {
-        return false;
+fn open_project(app_handle: &AppHandle, state: &AppState, project_path: &str) -> Result<(), DrodeError> {
+    path_policy::check_root(Path::new(project_path))?;
+    {
+        let db = state.db.get();
+        crate::db::settings::set(&db, "current_project", project_path)?;
+        crate::db::projects::set_current_project(&db, project_path)?;
     }
-    crate::db::projects::set_current_project(&db, &project_path).is_ok()
+    // The explorer still works without live updates, so this doesn't fail the switch
+    if let Err(e) = crate::watcher::watch_only(app_handle, project_path) {
+        eprintln!("Failed to watch {}: {}", project_path, e);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_recent_project(state: State<AppState>, project_path: String) -> Result<Vec<String>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::projects::remove(&db, &project_path)?)
+}
+
+// Project registry
+
+#[tauri::command]
+pub fn list_projects(state: State<AppState>) -> Result<Vec<Project>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::projects::list(&db)?)
 }
 
 #[tauri::command]
-pub fn remove_recent_project(state: State<AppState>, project_path: String) -> Vec<String> {
-    let db = state.db.lock().unwrap();
-    crate::db::projects::remove(&db, &project_path).unwrap_or_default()
+pub fn update_project(
+    state: State<AppState>,
+    project_id: i64,
+    update: ProjectUpdate,
+) -> Result<Project, DrodeError> {
+    let db = state.db.get();
+    crate::db::projects::update(&db, project_id, &update)?
+        .ok_or_else(|| DrodeError::not_found(format!("Project {}", project_id)))
+}
+
+/// Manually point a project at its new location, e.g. when it isn't a git repo. The new
+/// folder is picked in the native dialog; None when it was cancelled.
+#[tauri::command]
+pub async fn relink_project(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    project_id: i64,
+) -> Result<Option<Project>, DrodeError> {
+    let Some(folder) = crate::dialogs::pick_folder(app_handle, "Locate Project").await? else {
+        return Ok(None);
+    };
+    path_policy::check_root(&folder)?;
+    let new_path = folder.to_string_lossy().to_string();
+
+    let db = state.db.get();
+    if crate::db::projects::get_by_path(&db, &new_path)?.is_some_and(|p| p.id != project_id) {
+        return Err(DrodeError::validation(format!(
+            "{} is already registered as another project",
+            new_path
+        )));
+    }
+    Ok(Some(crate::db::projects::relink(&db, project_id, &new_path)?))
+}
+
+#[tauri::command]
+pub fn get_project_settings(
+    state: State<AppState>,
+    project_id: i64,
+) -> Result<HashMap<String, String>, DrodeError> {
+    let db = state.db.get();
+    Ok(crate::db::projects::list_settings(&db, project_id)?)
+}
+
+#[tauri::command]
+pub fn get_project_setting(state: State<AppState>, project_id: i64, key: String) -> Option<String> {
+    let db = state.db.get();
+    crate::db::projects::get_setting(&db, project_id, &key)
+}
+
+/// A `None` value removes the key
+#[tauri::command]
+pub fn set_project_setting(
+    state: State<AppState>,
+    project_id: i64,
+    key: String,
+    value: Option<String>,
+) -> Result<(), DrodeError> {
+    let db = state.db.get();
+    match value {
+        Some(value) => crate::db::projects::set_setting(&db, project_id, &key, &value)?,
+        None => crate::db::projects::remove_setting(&db, project_id, &key)?,
+    }
+    Ok(())
 }
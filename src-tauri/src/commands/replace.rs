@@ -0,0 +1,157 @@
+use crate::db::replace_undo::{self, ReplaceUndo, UndoFile};
+use crate::error::DrodeError;
+use crate::commands::files::remember;
+use crate::file_replace::{self, PendingWrite, ReplaceOutcome, ReplacePlan};
+use crate::file_search::{SearchOptions, Searcher};
+use crate::local_history::Source;
+use crate::path_policy::PathPolicy;
+use crate::state::AppState;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use tauri::{AppHandle, Manager, State};
+
+// Previews waiting to be applied; the oldest is dropped past this many
+const MAX_PENDING_PLANS: usize = 8;
+
+/// Find every match in the current project and what replacing it would give. Nothing is
+/// written; pass the plan id (and the edits to keep) to `apply_replace`.
+#[tauri::command]
+pub async fn plan_replace(
+    app_handle: AppHandle,
+    options: SearchOptions,
+    replacement: String,
+) -> Result<ReplacePlan, DrodeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let project = {
+            let db = state.db.get();
+            crate::db::settings::get(&db, "current_project")
+        }
+        .ok_or_else(|| DrodeError::validation("No project is open"))?;
+
+        let searcher = Searcher::new(std::path::Path::new(&project), options)?;
+        let plan = file_replace::plan(&searcher, &replacement, &AtomicBool::new(false));
+
+        let mut plans = state.replace_plans.lock().unwrap();
+        if plans.len() >= MAX_PENDING_PLANS {
+            let oldest = plans.values().min_by_key(|p| p.created_at).map(|p| p.id.clone());
+            if let Some(id) = oldest {
+                plans.remove(&id);
+            }
+        }
+        plans.insert(plan.id.clone(), plan.clone());
+        Ok(plan)
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Replace preview failed: {}", e)))?
+}
+
+/// Apply the chosen edits of a plan (all of them when `edit_ids` is omitted). Nothing is
+/// written if any affected file changed since the preview.
+#[tauri::command]
+pub async fn apply_replace(
+    app_handle: AppHandle,
+    plan_id: String,
+    edit_ids: Option<Vec<usize>>,
+) -> Result<ReplaceOutcome, DrodeError> {
+    // Reading and writing every file takes a while on a large replace
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let plan = state
+            .replace_plans
+            .lock()
+            .unwrap()
+            .get(&plan_id)
+            .cloned()
+            .ok_or_else(|| DrodeError::not_found(format!("Replace preview {}", plan_id)))?;
+        let selected: Option<HashSet<usize>> = edit_ids.map(|ids| ids.into_iter().collect());
+
+        let policy = PathPolicy::load(&state.db.get());
+        let writes = file_replace::prepare(&plan, selected.as_ref(), &policy)?;
+        if writes.is_empty() {
+            return Err(DrodeError::validation("No edits selected"));
+        }
+
+        let edits_applied: usize = writes.iter().map(|w| w.edit_count).sum();
+        let undo_files: Vec<UndoFile> = writes
+            .iter()
+            .map(|w| UndoFile {
+                path: w.path.clone(),
+                original: w.original.clone(),
+                replaced_hash: file_replace::content_hash(&w.content),
+            })
+            .collect();
+        // Recorded first so a crash during the writes still leaves a way back
+        let undo_id = replace_undo::record(
+            &state.db.get(),
+            &plan.project_path,
+            &plan.query,
+            &plan.replacement,
+            edits_applied,
+            &undo_files,
+        )?;
+
+        if let Err(e) = file_replace::write_all(&policy, &writes) {
+            let _ = replace_undo::remove(&state.db.get(), &undo_id);
+            return Err(e);
+        }
+        state.replace_plans.lock().unwrap().remove(&plan_id);
+        remember_writes(&state, &policy, &writes);
+
+        Ok(ReplaceOutcome {
+            undo_id,
+            files_changed: writes.len(),
+            edits_applied,
+        })
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Replace failed: {}", e)))?
+}
+
+/// Put back what a replace overwrote. Refuses, without touching anything, if any of the
+/// files was edited after the replace. Returns the number of files restored.
+#[tauri::command]
+pub async fn undo_replace(app_handle: AppHandle, undo_id: String) -> Result<usize, DrodeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let (files, policy) = {
+            let db = state.db.get();
+            if replace_undo::get(&db, &undo_id)?.is_none() {
+                return Err(DrodeError::not_found(format!("Replace {}", undo_id)));
+            }
+            (replace_undo::files(&db, &undo_id)?, PathPolicy::load(&db))
+        };
+        let writes = file_replace::prepare_undo(&files, &policy)?;
+        file_replace::write_all(&policy, &writes)?;
+        replace_undo::remove(&state.db.get(), &undo_id)?;
+        remember_writes(&state, &policy, &writes);
+        Ok(writes.len())
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Undoing the replace failed: {}", e)))?
+}
+
+/// Record each rewritten file in the local history: what it held before, in case that was
+/// never snapshotted, and what the replace or undo made of it
+fn remember_writes(state: &AppState, policy: &PathPolicy, writes: &[PendingWrite]) {
+    for write in writes {
+        let Ok(real) = policy.resolve(&write.path) else {
+            continue;
+        };
+        remember(state, |db, history| {
+            history.snapshot(db, &real, None, Source::External, write.original.as_bytes())?;
+            history.snapshot(db, &real, None, Source::Save, write.content.as_bytes()).map(|_| ())
+        });
+    }
+}
+
+#[tauri::command]
+pub async fn list_replace_undos(
+    state: State<'_, AppState>,
+    project_path: String,
+) -> Result<Vec<ReplaceUndo>, DrodeError> {
+    state
+        .db
+        .run(move |db| replace_undo::list(db, &project_path).map_err(DrodeError::from))
+        .await?
+}
@@ -0,0 +1,158 @@
+use crate::db::summaries::{self, SummarySettings};
+use crate::error::DrodeError;
+use crate::state::AppState;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Generate a title/summary in the background if the feature is on and one is due.
+/// Called after every save; after a failed run, pending_job holds off the next automatic
+/// one until more messages arrive.
+pub fn schedule(app_handle: &AppHandle, conversation_id: &str) {
+    {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.get();
+        if !summaries::settings(&db).enabled {
+            return;
+        }
+    }
+
+    let handle = app_handle.clone();
+    let conversation_id = conversation_id.to_string();
+    std::thread::spawn(move || {
+        if let Err(e) = run_job(&handle, &conversation_id, false) {
+            eprintln!("Summary generation for {} failed: {}", conversation_id, e);
+        }
+    });
+}
+
+/// Returns false when there was nothing to generate or another run is already in flight
+fn run_job(app_handle: &AppHandle, conversation_id: &str, force: bool) -> Result<bool, DrodeError> {
+    let state = app_handle.state::<AppState>();
+    if !state.summary_jobs.lock().unwrap().insert(conversation_id.to_string()) {
+        return Ok(false);
+    }
+    let result = generate_and_store(app_handle, &state, conversation_id, force);
+    state.summary_jobs.lock().unwrap().remove(conversation_id);
+    result
+}
+
+fn generate_and_store(
+    app_handle: &AppHandle,
+    state: &AppState,
+    conversation_id: &str,
+    force: bool,
+) -> Result<bool, DrodeError> {
+    // Don't hold a connection while the model runs
+    let (settings, job) = {
+        let db = state.db.get();
+        let settings = summaries::settings(&db);
+        let job = summaries::pending_job(&db, conversation_id, settings.refresh_every, force)?;
+        (settings, job)
+    };
+    let Some(job) = job else {
+        return Ok(false);
+    };
+
+    let summary = match generate(state, &settings, &job) {
+        Ok(summary) => summary,
+        Err(e) => {
+            let _ = summaries::record_failure(&state.db.get(), &job);
+            return Err(e);
+        }
+    };
+
+    let db = state.db.get();
+    let name: String = db.query_row(
+        "SELECT name FROM conversations WHERE id = ?1",
+        rusqlite::params![conversation_id],
+        |row| row.get(0),
+    )?;
+    let _ = app_handle.emit(
+        "conversation-summary-updated",
+        serde_json::json!({
+            "conversationId": conversation_id,
+            "name": name,
+            "summary": summary,
+        }),
+    );
+    Ok(true)
+}
+
+/// Run the model for `job` and store the result; returns the new summary
+fn generate(state: &AppState, settings: &SummarySettings, job: &summaries::SummaryJob) -> Result<String, DrodeError> {
+    let output = run_cli(&settings.model, &summaries::build_prompt(job))?;
+    let (reply, usage) = summaries::parse_cli_output(&output, &settings.model).map_err(DrodeError::Internal)?;
+
+    let db = state.db.get();
+    // The call is paid for whether or not the reply turns out to be usable
+    let kind = if job.wants_title { "title" } else { "summary" };
+    summaries::record_usage(&db, &job.conversation_id, kind, &usage)?;
+
+    let (title, summary) = summaries::parse_reply(&reply)
+        .ok_or_else(|| DrodeError::internal("The model's reply contained no summary"))?;
+    summaries::apply(&db, job, title.as_deref(), &summary)?;
+    Ok(summary)
+}
+
+/// One-shot, tool-less call through the configured CLI. Runs in summaries::cli_workdir,
+/// outside the project, and asks the CLI not to keep the session.
+fn run_cli(model: &str, prompt: &str) -> Result<String, DrodeError> {
+    let workdir = summaries::cli_workdir();
+    std::fs::create_dir_all(&workdir).map_err(|e| DrodeError::io(&workdir, e))?;
+    let mut child = Command::new("claude")
+        .args(["--print", "--output-format", "json", "--model", model])
+        // No tools and a single turn: the transcript is data to summarise, not instructions
+        .args(["--tools", "", "--max-turns", "1", "--no-session-persistence"])
+        .current_dir(&workdir)
+        .env("FORCE_COLOR", "0")
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| DrodeError::spawn("claude", e))?;
+
+    // The transcript can be long, so it goes through stdin rather than argv
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(prompt.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    // Errors are still reported as a JSON result on stdout; let the parser surface them
+    if !output.status.success() && stdout.trim().is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DrodeError::internal(format!(
+            "claude exited with {}: {}",
+            output.status,
+            stderr.trim()
+        )));
+    }
+    Ok(stdout)
+}
+
+#[tauri::command]
+pub fn get_summary_settings(state: State<AppState>) -> SummarySettings {
+    let db = state.db.get();
+    summaries::settings(&db)
+}
+
+#[tauri::command]
+pub fn set_summary_settings(state: State<AppState>, settings: SummarySettings) -> Result<(), DrodeError> {
+    if settings.model.trim().is_empty() {
+        return Err(DrodeError::validation("A model is required"));
+    }
+    let db = state.db.get();
+    summaries::save_settings(&db, &settings)?;
+    Ok(())
+}
+
+/// Regenerate now, regardless of the refresh interval or whether automatic generation is on.
+/// Returns false when there was nothing new to summarize.
+#[tauri::command]
+pub async fn summarize_conversation(app_handle: AppHandle, conversation_id: String) -> Result<bool, DrodeError> {
+    tauri::async_runtime::spawn_blocking(move || run_job(&app_handle, &conversation_id, true))
+        .await
+        .map_err(|e| DrodeError::Internal(format!("Summary task failed: {}", e)))?
+}
@@ -1,14 +1,11 @@
 use tauri::State;
-use crate::state::{AppState, OperationResult};
-use crate::db::oauth;
+use crate::error::{check_response, DrodeError};
+use crate::state::AppState;
+use crate::commands::oauth;
 use serde::{Deserialize, Serialize};
 
-fn get_supabase_token(state: &State<'_, AppState>) -> Result<String, String> {
-    let db = state.db.lock().unwrap();
-    oauth::get_token(&db, "supabase")
-        .map_err(|e| e.to_string())?
-        .map(|t| t.access_token)
-        .ok_or_else(|| "Supabase not connected. Please authenticate first.".to_string())
+async fn get_supabase_token(state: &State<'_, AppState>) -> Result<String, DrodeError> {
+    Ok(oauth::load_token(state, "supabase").await?.access_token)
 }
 
 
@@ -56,18 +53,18 @@ pub struct SupabaseSqlResult {
 }
 
 #[tauri::command]
-pub async fn supabase_list_projects(state: State<'_, AppState>) -> Result<Vec<SupabaseProject>, String> {
-    let token = get_supabase_token(&state)?;
+pub async fn supabase_list_projects(state: State<'_, AppState>) -> Result<Vec<SupabaseProject>, DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
         .get("https://api.supabase.com/v1/projects")
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("supabase", resp).await?;
 
-    let projects: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let projects: Vec<serde_json::Value> = resp.json().await?;
 
     Ok(projects.into_iter().map(|p| SupabaseProject {
         id: p["id"].as_str().unwrap_or("").to_string(),
@@ -84,8 +81,8 @@ pub async fn supabase_list_projects(state: State<'_, AppState>) -> Result<Vec<Su
 pub async fn supabase_list_tables(
     state: State<'_, AppState>,
     project_ref: String,
-) -> Result<Vec<SupabaseTable>, String> {
-    let token = get_supabase_token(&state)?;
+) -> Result<Vec<SupabaseTable>, DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
 
     // Use the SQL endpoint to list tables and their columns
@@ -99,10 +96,10 @@ pub async fn supabase_list_tables(
         .header("Authorization", format!("Bearer {}", token))
         .json(&serde_json::json!({ "query": sql }))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("supabase", resp).await?;
 
-    let rows: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let rows: Vec<serde_json::Value> = resp.json().await?;
 
     // Group columns by table
     let mut tables: std::collections::HashMap<String, Vec<SupabaseColumn>> = std::collections::HashMap::new();
@@ -136,8 +133,8 @@ pub async fn supabase_get_table_data(
     page_size: Option<i32>,
     order_by: Option<String>,
     filters: Option<String>,
-) -> Result<SupabaseQueryResult, String> {
-    let token = get_supabase_token(&state)?;
+) -> Result<SupabaseQueryResult, DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
     let page = page.unwrap_or(0);
     let page_size = page_size.unwrap_or(50);
@@ -162,8 +159,8 @@ pub async fn supabase_get_table_data(
         .header("apikey", &token)
         .header("Prefer", "count=exact")
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("supabase", resp).await?;
 
     // Get total count from Content-Range header
     let total_count = resp.headers()
@@ -172,7 +169,7 @@ pub async fn supabase_get_table_data(
         .and_then(|s| s.split('/').last())
         .and_then(|s| s.parse::<i64>().ok());
 
-    let rows: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let rows: Vec<serde_json::Value> = resp.json().await?;
 
     let columns = if let Some(first_row) = rows.first() {
         if let Some(obj) = first_row.as_object() {
@@ -196,8 +193,8 @@ pub async fn supabase_run_sql(
     state: State<'_, AppState>,
     project_ref: String,
     sql: String,
-) -> Result<SupabaseSqlResult, String> {
-    let token = get_supabase_token(&state)?;
+) -> Result<SupabaseSqlResult, DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
@@ -205,10 +202,10 @@ pub async fn supabase_run_sql(
         .header("Authorization", format!("Bearer {}", token))
         .json(&serde_json::json!({ "query": sql }))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("supabase", resp).await?;
 
-    let rows: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let rows: Vec<serde_json::Value> = resp.json().await?;
     let row_count = rows.len() as i64;
 
     let columns = if let Some(first_row) = rows.first() {
@@ -234,11 +231,12 @@ pub async fn supabase_insert_row(
     project_ref: String,
     table_name: String,
     data_json: String,
-) -> Result<serde_json::Value, String> {
-    let token = get_supabase_token(&state)?;
+) -> Result<serde_json::Value, DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
 
-    let data: serde_json::Value = serde_json::from_str(&data_json).map_err(|e| e.to_string())?;
+    let data: serde_json::Value = serde_json::from_str(&data_json)
+        .map_err(|e| DrodeError::validation(format!("Invalid row data: {}", e)))?;
 
     let url = format!("https://{}.supabase.co/rest/v1/{}", project_ref, table_name);
 
@@ -249,10 +247,10 @@ pub async fn supabase_insert_row(
         .header("Prefer", "return=representation")
         .json(&data)
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("supabase", resp).await?;
 
-    resp.json().await.map_err(|e| e.to_string())
+    Ok(resp.json().await?)
 }
 
 #[tauri::command]
@@ -262,11 +260,12 @@ pub async fn supabase_update_row(
     table_name: String,
     row_id: String,
     data_json: String,
-) -> Result<serde_json::Value, String> {
-    let token = get_supabase_token(&state)?;
+) -> Result<serde_json::Value, DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
 
-    let data: serde_json::Value = serde_json::from_str(&data_json).map_err(|e| e.to_string())?;
+    let data: serde_json::Value = serde_json::from_str(&data_json)
+        .map_err(|e| DrodeError::validation(format!("Invalid row data: {}", e)))?;
 
     let url = format!("https://{}.supabase.co/rest/v1/{}?id=eq.{}", project_ref, table_name, row_id);
 
@@ -277,10 +276,10 @@ pub async fn supabase_update_row(
         .header("Prefer", "return=representation")
         .json(&data)
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("supabase", resp).await?;
 
-    resp.json().await.map_err(|e| e.to_string())
+    Ok(resp.json().await?)
 }
 
 #[tauri::command]
@@ -289,22 +288,20 @@ pub async fn supabase_delete_row(
     project_ref: String,
     table_name: String,
     row_id: String,
-) -> Result<OperationResult, String> {
-    let token = get_supabase_token(&state)?;
+) -> Result<(), DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
 
     let url = format!("https://{}.supabase.co/rest/v1/{}?id=eq.{}", project_ref, table_name, row_id);
 
-    match client
+    let resp = client
         .delete(&url)
         .header("Authorization", format!("Bearer {}", token))
         .header("apikey", &token)
         .send()
-        .await
-    {
-        Ok(_) => Ok(OperationResult { success: true, error: None, content: None }),
-        Err(e) => Ok(OperationResult { success: false, error: Some(e.to_string()), content: None }),
-    }
+        .await?;
+    check_response("supabase", resp).await?;
+    Ok(())
 }
 
 #[tauri::command]
@@ -313,8 +310,8 @@ pub async fn supabase_run_migration(
     project_ref: String,
     migration_sql: String,
     migration_name: String,
-) -> Result<OperationResult, String> {
-    let token = get_supabase_token(&state)?;
+) -> Result<String, DrodeError> {
+    let token = get_supabase_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
@@ -322,18 +319,8 @@ pub async fn supabase_run_migration(
         .header("Authorization", format!("Bearer {}", token))
         .json(&serde_json::json!({ "query": migration_sql }))
         .send()
-        .await;
+        .await?;
+    check_response("supabase", resp).await?;
 
-    match resp {
-        Ok(r) if r.status().is_success() => {
-            Ok(OperationResult { success: true, error: None, content: Some(migration_name) })
-        }
-        Ok(r) => {
-            let body = r.text().await.unwrap_or_default();
-            Ok(OperationResult { success: false, error: Some(format!("Migration failed: {}", body)), content: None })
-        }
-        Err(e) => {
-            Ok(OperationResult { success: false, error: Some(e.to_string()), content: None })
-        }
-    }
+    Ok(migration_name)
 }
@@ -0,0 +1,73 @@
+use crate::db::symbols::{self, Symbol};
+use crate::error::DrodeError;
+use crate::file_read;
+use crate::path_policy::{self, PathPolicy};
+use crate::state::AppState;
+use crate::symbol_index::{self, Lang, OutlineNode};
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// Symbols of the current project whose name contains `query`, for @symbol references.
+/// `kinds` narrows the result to e.g. ["function", "class"].
+#[tauri::command]
+pub async fn search_symbols(
+    app_handle: AppHandle,
+    query: String,
+    kinds: Option<Vec<String>>,
+    limit: Option<usize>,
+) -> Result<Vec<Symbol>, DrodeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let project = current_project(&app_handle)?;
+        // Indexing starts with the watcher; until it's done the result may be partial
+        crate::watcher::watch(&app_handle, &project)?;
+        let state = app_handle.state::<AppState>();
+        let db = state.db.get();
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        Ok(symbols::search(&db, &project, &query, &kinds.unwrap_or_default(), limit)?)
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Symbol search failed: {}", e)))?
+}
+
+/// Nested symbols of a file, parsed from what is on disk now. Empty for languages that
+/// aren't indexed.
+#[tauri::command]
+pub async fn document_outline(state: State<'_, AppState>, file_path: String) -> Result<Vec<OutlineNode>, DrodeError> {
+    let Some(lang) = Lang::of(Path::new(&file_path)) else {
+        return Ok(Vec::new());
+    };
+    let policy = {
+        let db = state.db.get();
+        PathPolicy::load(&db)
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut file = path_policy::open(&policy, &file_path)?;
+        let source = file_read::read_text(&mut file, &file_path)?;
+        Ok(symbol_index::outline(lang, &file_path, &source))
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Outline failed: {}", e)))?
+}
+
+/// Where `name` is declared in the current project. Declarations in `file_path`, the file
+/// the name was found in, come first.
+#[tauri::command]
+pub fn find_definition(
+    app_handle: AppHandle,
+    name: String,
+    file_path: Option<String>,
+) -> Result<Vec<Symbol>, DrodeError> {
+    let project = current_project(&app_handle)?;
+    let state = app_handle.state::<AppState>();
+    let db = state.db.get();
+    Ok(symbols::definitions(&db, &project, &name, file_path.as_deref())?)
+}
+
+fn current_project(app_handle: &AppHandle) -> Result<String, DrodeError> {
+    let state = app_handle.state::<AppState>();
+    let db = state.db.get();
+    crate::db::settings::get(&db, "current_project").ok_or_else(|| DrodeError::validation("No project is open"))
+}
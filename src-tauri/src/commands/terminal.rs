@@ -1,4 +1,5 @@
-use crate::state::{AppState, OperationResult};
+use crate::error::DrodeError;
+use crate::state::AppState;
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
 use tauri::{AppHandle, Emitter, State};
@@ -9,18 +10,14 @@ pub fn run_terminal_command(
     state: State<AppState>,
     command: String,
     terminal_id: String,
-) -> OperationResult {
+) -> Result<u32, DrodeError> {
     let project_path = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get();
         crate::db::settings::get(&db, "current_project")
     };
 
     let Some(project_path) = project_path else {
-        return OperationResult {
-            success: false,
-            content: None,
-            error: Some("No project path set".to_string()),
-        };
+        return Err(DrodeError::validation("No project path set"));
     };
 
     // Use sh -c to run the command
@@ -56,85 +53,73 @@ pub fn run_terminal_command(
         .stderr(Stdio::piped())
         .spawn();
 
-    match result {
-        Ok(mut child) => {
-            let stdout = child.stdout.take();
-            let stderr = child.stderr.take();
-            let tid = terminal_id.clone();
+    let mut child = result.map_err(|e| DrodeError::spawn("sh", e))?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let tid = terminal_id.clone();
 
-            // Get the process ID and store it for potential killing
-            let pid = child.id();
-            if let Ok(mut pids) = state.terminal_pids.lock() {
-                pids.insert(terminal_id.clone(), pid);
-            }
+    // Get the process ID and store it for potential killing
+    let pid = child.id();
+    if let Ok(mut pids) = state.terminal_pids.lock() {
+        pids.insert(terminal_id.clone(), pid);
+    }
 
-            // Handle stdout
-            if let Some(stdout) = stdout {
-                let handle = app_handle.clone();
-                let tid_clone = tid.clone();
-                std::thread::spawn(move || {
-                    let reader = BufReader::new(stdout);
-                    for line in reader.lines() {
-                        match line {
-                            Ok(text) => {
-                                let _ = handle.emit("terminal-output", serde_json::json!({
-                                    "terminalId": tid_clone,
-                                    "type": "stdout",
-                                    "data": text
-                                }));
-                            }
-                            Err(_) => break,
-                        }
+    // Handle stdout
+    if let Some(stdout) = stdout {
+        let handle = app_handle.clone();
+        let tid_clone = tid.clone();
+        std::thread::spawn(move || {
+            let reader = BufReader::new(stdout);
+            for line in reader.lines() {
+                match line {
+                    Ok(text) => {
+                        let _ = handle.emit("terminal-output", serde_json::json!({
+                            "terminalId": tid_clone,
+                            "type": "stdout",
+                            "data": text
+                        }));
                     }
-                });
+                    Err(_) => break,
+                }
             }
+        });
+    }
 
-            // Handle stderr
-            if let Some(stderr) = stderr {
-                let handle = app_handle.clone();
-                let tid_clone = tid.clone();
-                std::thread::spawn(move || {
-                    let reader = BufReader::new(stderr);
-                    for line in reader.lines() {
-                        match line {
-                            Ok(text) => {
-                                let _ = handle.emit("terminal-output", serde_json::json!({
-                                    "terminalId": tid_clone,
-                                    "type": "stderr",
-                                    "data": text
-                                }));
-                            }
-                            Err(_) => break,
-                        }
+    // Handle stderr
+    if let Some(stderr) = stderr {
+        let handle = app_handle.clone();
+        let tid_clone = tid.clone();
+        std::thread::spawn(move || {
+            let reader = BufReader::new(stderr);
+            for line in reader.lines() {
+                match line {
+                    Ok(text) => {
+                        let _ = handle.emit("terminal-output", serde_json::json!({
+                            "terminalId": tid_clone,
+                            "type": "stderr",
+                            "data": text
+                        }));
                     }
-                });
+                    Err(_) => break,
+                }
             }
+        });
+    }
 
-            // Wait for process and emit exit
-            let tid_for_wait = terminal_id.clone();
-            let handle = app_handle.clone();
-            std::thread::spawn(move || {
-                let status = child.wait();
-                let code = status.ok().and_then(|s| s.code()).unwrap_or(-1);
-                let _ = handle.emit("terminal-output", serde_json::json!({
-                    "terminalId": tid_for_wait,
-                    "type": "exit",
-                    "code": code
-                }));
-            });
+    // Wait for process and emit exit
+    let tid_for_wait = terminal_id.clone();
+    let handle = app_handle.clone();
+    std::thread::spawn(move || {
+        let status = child.wait();
+        let code = status.ok().and_then(|s| s.code()).unwrap_or(-1);
+        let _ = handle.emit("terminal-output", serde_json::json!({
+            "terminalId": tid_for_wait,
+            "type": "exit",
+            "code": code
+        }));
+    });
 
-            OperationResult {
-                success: true,
-                content: Some(pid.to_string()),
-                error: None,
-            }
-        }
-        Err(e) => OperationResult {
-            success: false,
-            content: None,
-            error: Some(e.to_string()),
-        },
-    }
+    Ok(pid)
 }
 
 #[tauri::command]
@@ -142,7 +127,7 @@ pub fn kill_terminal_process(
     app_handle: AppHandle,
     state: State<AppState>,
     terminal_id: String,
-) -> OperationResult {
+) -> Result<(), DrodeError> {
     let pid = if let Ok(mut pids) = state.terminal_pids.lock() {
         pids.remove(&terminal_id)
     } else {
@@ -182,16 +167,8 @@ pub fn kill_terminal_process(
             "code": -1
         }));
 
-        return OperationResult {
-            success: true,
-            content: None,
-            error: None,
-        };
+        return Ok(());
     }
 
-    OperationResult {
-        success: false,
-        content: None,
-        error: Some("Process not found".to_string()),
-    }
+    Err(DrodeError::not_found(format!("No running process for terminal {}", terminal_id)))
 }
@@ -0,0 +1,40 @@
+use crate::db::usage::{self, UsageGroup, UsageRow};
+use crate::error::DrodeError;
+use crate::state::AppState;
+use tauri::{AppHandle, State};
+
+#[tauri::command]
+pub async fn get_usage_stats(
+    state: State<'_, AppState>,
+    group_by: UsageGroup,
+    project_path: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<UsageRow>, DrodeError> {
+    state
+        .db
+        .run(move |db| {
+            usage::query_usage(db, group_by, project_path.as_deref(), since, until).map_err(DrodeError::from)
+        })
+        .await?
+}
+
+/// Save the usage table as CSV where the user picks; None when they cancel
+#[tauri::command]
+pub async fn export_usage_csv(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    group_by: UsageGroup,
+    project_path: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Option<String>, DrodeError> {
+    let rows = state
+        .db
+        .run(move |db| {
+            usage::query_usage(db, group_by, project_path.as_deref(), since, until).map_err(DrodeError::from)
+        })
+        .await??;
+
+    crate::dialogs::save(app_handle, "usage.csv".to_string(), "CSV", "csv", usage::to_csv(&rows).into_bytes()).await
+}
@@ -1,14 +1,11 @@
 use tauri::State;
-use crate::state::{AppState, OperationResult};
-use crate::db::oauth;
+use crate::error::{check_response, DrodeError};
+use crate::state::AppState;
+use crate::commands::oauth;
 use serde::{Deserialize, Serialize};
 
-fn get_vercel_token(state: &State<AppState>) -> Result<String, String> {
-    let db = state.db.lock().unwrap();
-    oauth::get_token(&db, "vercel")
-        .map_err(|e| e.to_string())?
-        .map(|t| t.access_token)
-        .ok_or_else(|| "Vercel not connected. Please authenticate first.".to_string())
+async fn get_vercel_token(state: &State<'_, AppState>) -> Result<String, DrodeError> {
+    Ok(oauth::load_token(state, "vercel").await?.access_token)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,18 +46,18 @@ pub struct VercelDomain {
 }
 
 #[tauri::command]
-pub async fn vercel_list_projects(state: State<'_, AppState>) -> Result<Vec<VercelProject>, String> {
-    let token = get_vercel_token(&state)?;
+pub async fn vercel_list_projects(state: State<'_, AppState>) -> Result<Vec<VercelProject>, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
         .get("https://api.vercel.com/v9/projects")
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let data: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let data: serde_json::Value = resp.json().await?;
     let projects = data["projects"].as_array().cloned().unwrap_or_default();
 
     Ok(projects.into_iter().map(|p| VercelProject {
@@ -80,8 +77,8 @@ pub async fn vercel_list_deployments(
     state: State<'_, AppState>,
     project_id: String,
     limit: Option<i32>,
-) -> Result<Vec<VercelDeployment>, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<Vec<VercelDeployment>, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
     let limit = limit.unwrap_or(20);
 
@@ -89,10 +86,10 @@ pub async fn vercel_list_deployments(
         .get(format!("https://api.vercel.com/v6/deployments?projectId={}&limit={}", project_id, limit))
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let data: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let data: serde_json::Value = resp.json().await?;
     let deployments = data["deployments"].as_array().cloned().unwrap_or_default();
 
     Ok(deployments.into_iter().map(|d| VercelDeployment {
@@ -113,18 +110,18 @@ pub async fn vercel_list_deployments(
 pub async fn vercel_get_deployment(
     state: State<'_, AppState>,
     deployment_id: String,
-) -> Result<VercelDeployment, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<VercelDeployment, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
         .get(format!("https://api.vercel.com/v13/deployments/{}", deployment_id))
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let d: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let d: serde_json::Value = resp.json().await?;
 
     Ok(VercelDeployment {
         uid: d["uid"].as_str().unwrap_or("").to_string(),
@@ -144,18 +141,18 @@ pub async fn vercel_get_deployment(
 pub async fn vercel_get_deployment_logs(
     state: State<'_, AppState>,
     deployment_id: String,
-) -> Result<Vec<VercelLogEntry>, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<Vec<VercelLogEntry>, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
         .get(format!("https://api.vercel.com/v2/deployments/{}/events", deployment_id))
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let events: Vec<serde_json::Value> = resp.json().await.map_err(|e| e.to_string())?;
+    let events: Vec<serde_json::Value> = resp.json().await?;
 
     Ok(events.into_iter().map(|e| VercelLogEntry {
         timestamp: e["created"].as_i64().unwrap_or(0),
@@ -169,8 +166,8 @@ pub async fn vercel_create_deployment(
     state: State<'_, AppState>,
     project_id: String,
     git_ref: Option<String>,
-) -> Result<VercelDeployment, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<VercelDeployment, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let mut body = serde_json::json!({
@@ -187,10 +184,10 @@ pub async fn vercel_create_deployment(
         .header("Authorization", format!("Bearer {}", token))
         .json(&body)
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let d: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let d: serde_json::Value = resp.json().await?;
 
     Ok(VercelDeployment {
         uid: d["uid"].as_str().unwrap_or("").to_string(),
@@ -210,18 +207,18 @@ pub async fn vercel_create_deployment(
 pub async fn vercel_list_domains(
     state: State<'_, AppState>,
     project_id: String,
-) -> Result<Vec<VercelDomain>, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<Vec<VercelDomain>, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
         .get(format!("https://api.vercel.com/v9/projects/{}/domains", project_id))
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let data: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let data: serde_json::Value = resp.json().await?;
     let domains = data["domains"].as_array().cloned().unwrap_or_default();
 
     Ok(domains.into_iter().map(|d| VercelDomain {
@@ -236,8 +233,8 @@ pub async fn vercel_add_domain(
     state: State<'_, AppState>,
     project_id: String,
     domain: String,
-) -> Result<VercelDomain, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<VercelDomain, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
@@ -245,10 +242,10 @@ pub async fn vercel_add_domain(
         .header("Authorization", format!("Bearer {}", token))
         .json(&serde_json::json!({ "name": domain }))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let d: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let d: serde_json::Value = resp.json().await?;
 
     Ok(VercelDomain {
         name: d["name"].as_str().unwrap_or(&domain).to_string(),
@@ -262,37 +259,35 @@ pub async fn vercel_remove_domain(
     state: State<'_, AppState>,
     project_id: String,
     domain: String,
-) -> Result<OperationResult, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<(), DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
-    match client
+    let resp = client
         .delete(format!("https://api.vercel.com/v9/projects/{}/domains/{}", project_id, domain))
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-    {
-        Ok(_) => Ok(OperationResult { success: true, error: None, content: None }),
-        Err(e) => Ok(OperationResult { success: false, error: Some(e.to_string()), content: None }),
-    }
+        .await?;
+    check_response("vercel", resp).await?;
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn vercel_promote_deployment(
     state: State<'_, AppState>,
     deployment_id: String,
-) -> Result<VercelDeployment, String> {
-    let token = get_vercel_token(&state)?;
+) -> Result<VercelDeployment, DrodeError> {
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
         .post(format!("https://api.vercel.com/v13/deployments/{}/promote", deployment_id))
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let d: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let d: serde_json::Value = resp.json().await?;
 
     Ok(VercelDeployment {
         uid: d["uid"].as_str().unwrap_or(&deployment_id).to_string(),
@@ -313,19 +308,19 @@ pub async fn vercel_rollback_deployment(
     state: State<'_, AppState>,
     _project_id: String,
     deployment_id: String,
-) -> Result<VercelDeployment, String> {
+) -> Result<VercelDeployment, DrodeError> {
     // Rollback is essentially promoting a previous deployment
-    let token = get_vercel_token(&state)?;
+    let token = get_vercel_token(&state).await?;
     let client = reqwest::Client::new();
 
     let resp = client
         .post(format!("https://api.vercel.com/v13/deployments/{}/promote", deployment_id))
         .header("Authorization", format!("Bearer {}", token))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    let resp = check_response("vercel", resp).await?;
 
-    let d: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    let d: serde_json::Value = resp.json().await?;
 
     Ok(VercelDeployment {
         uid: d["uid"].as_str().unwrap_or(&deployment_id).to_string(),
@@ -0,0 +1,190 @@
+// Online backups of drode.db through the SQLite backup API, plus integrity checks.
+// Backups are plain SQLite files in <config>/backups named drode-YYYYmmdd-HHMMSS.db.
+
+use crate::error::DrodeError;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const BACKUP_PREFIX: &str = "drode-";
+const BACKUP_EXTENSION: &str = "db";
+
+pub const BACKUP_INTERVAL_KEY: &str = "backup_interval_hours";
+pub const BACKUP_KEEP_KEY: &str = "backup_keep";
+const DEFAULT_INTERVAL_HOURS: u64 = 24;
+const DEFAULT_KEEP: usize = 7;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BackupInfo {
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub path: String,
+    pub size: u64,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub problems: Vec<String>,
+    #[serde(rename = "checkedAt")]
+    pub checked_at: i64,
+}
+
+pub fn interval_hours(conn: &Connection) -> u64 {
+    crate::db::settings::get(conn, BACKUP_INTERVAL_KEY)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_HOURS)
+}
+
+/// How many backups to keep; at least one, so a backup isn't deleted as soon as it is taken
+pub fn keep_count(conn: &Connection) -> usize {
+    crate::db::settings::get(conn, BACKUP_KEEP_KEY)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_KEEP)
+        .max(1)
+}
+
+/// Copy the live database into `backups_dir` without blocking other connections
+pub fn create(conn: &Connection, backups_dir: &Path) -> Result<BackupInfo, Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(backups_dir)?;
+
+    let now = chrono::Local::now();
+    let mut path = backups_dir.join(format!(
+        "{}{}.{}",
+        BACKUP_PREFIX,
+        now.format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    ));
+    // Two backups within the same second (manual + scheduled) must not overwrite each other
+    let mut n = 1;
+    while path.exists() {
+        path = backups_dir.join(format!(
+            "{}{}-{}.{}",
+            BACKUP_PREFIX,
+            now.format("%Y%m%d-%H%M%S"),
+            n,
+            BACKUP_EXTENSION
+        ));
+        n += 1;
+    }
+
+    conn.backup(DatabaseName::Main, &path, None)?;
+    Ok(info_for(&path)?)
+}
+
+/// Backups newest first
+pub fn list(backups_dir: &Path) -> Vec<BackupInfo> {
+    let Ok(entries) = std::fs::read_dir(backups_dir) else {
+        return vec![];
+    };
+
+    let mut backups: Vec<BackupInfo> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| is_backup_file(p))
+        .filter_map(|p| info_for(&p).ok())
+        .collect();
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.file_name.cmp(&a.file_name)));
+    backups
+}
+
+/// Delete all but the newest `keep` backups. Returns how many were removed.
+pub fn rotate(backups_dir: &Path, keep: usize) -> usize {
+    list(backups_dir)
+        .into_iter()
+        .skip(keep)
+        .filter(|b| std::fs::remove_file(&b.path).is_ok())
+        .count()
+}
+
+/// True when no backup exists yet or the newest one is older than the interval
+pub fn is_due(backups_dir: &Path, interval_hours: u64) -> bool {
+    let interval_ms = (interval_hours * 60 * 60 * 1000) as i64;
+    match list(backups_dir).first() {
+        Some(latest) => chrono::Utc::now().timestamp_millis() - latest.created_at >= interval_ms,
+        None => true,
+    }
+}
+
+/// Resolve a backup by file name, refusing anything outside the backups directory
+pub fn resolve(backups_dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let name = Path::new(file_name).file_name()?;
+    let path = backups_dir.join(name);
+    if is_backup_file(&path) && path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Replace the live database contents with a backup while the app keeps running.
+/// The backup is integrity-checked first, and the schema is migrated afterwards
+/// in case the backup predates the current version.
+pub fn restore(conn: &mut Connection, backup_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    // Not read-only: FTS5's integrity check needs a writable handle
+    let source = Connection::open_with_flags(backup_path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    let report = integrity_check(&source)?;
+    if !report.ok {
+        return Err(Box::new(DrodeError::Database(format!(
+            "Backup is corrupt: {}",
+            report.problems.join("; ")
+        ))));
+    }
+
+    {
+        let backup = Backup::new(&source, conn)?;
+        backup.run_to_completion(256, Duration::from_millis(0), None)?;
+    }
+
+    crate::db::schema::initialize(conn)?;
+    Ok(())
+}
+
+pub fn integrity_check(conn: &Connection) -> Result<IntegrityReport, rusqlite::Error> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+    let mut problems = Vec::new();
+    for row in rows {
+        let line = row?;
+        if line != "ok" {
+            problems.push(line);
+        }
+    }
+
+    Ok(IntegrityReport {
+        ok: problems.is_empty(),
+        problems,
+        checked_at: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+fn is_backup_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    name.starts_with(BACKUP_PREFIX)
+        && path.extension().and_then(|e| e.to_str()) == Some(BACKUP_EXTENSION)
+}
+
+fn info_for(path: &Path) -> std::io::Result<BackupInfo> {
+    let metadata = std::fs::metadata(path)?;
+    let created_at = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+
+    Ok(BackupInfo {
+        file_name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        created_at,
+    })
+}
@@ -0,0 +1,195 @@
+// Bookmarks and highlights on individual messages (schema version 9).
+// Notes and quotes are indexed in bookmarks_fts and searched alongside messages.
+
+use crate::error::DrodeError;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+pub const COLORS: &[&str] = &["yellow", "green", "blue", "red", "purple"];
+const DEFAULT_COLOR: &str = "yellow";
+
+/// Highlighted part of a message, in UTF-16 code units as reported by the webview's selection
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct TextRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Bookmark {
+    pub id: i64,
+    #[serde(rename = "conversationId")]
+    pub conversation_id: String,
+    #[serde(rename = "conversationName")]
+    pub conversation_name: String,
+    #[serde(rename = "messageId")]
+    pub message_id: String,
+    #[serde(rename = "messageRole")]
+    pub message_role: Option<String>,
+    #[serde(rename = "messageTimestamp")]
+    pub message_timestamp: Option<i64>,
+    pub note: String,
+    pub color: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<TextRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote: Option<String>,
+    /// The quote, or the first 200 characters of the message when all of it is bookmarked
+    pub excerpt: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+const BOOKMARK_SELECT: &str = "SELECT b.id, b.conversation_id, c.name, b.message_id, m.role, m.timestamp,
+        b.note, b.color, b.range_start, b.range_end, b.quote,
+        COALESCE(b.quote, substr(m.content, 1, 200), ''),
+        b.created_at, b.updated_at
+    FROM bookmarks b
+    JOIN conversations c ON c.id = b.conversation_id
+    LEFT JOIN messages m ON m.id = b.message_id";
+
+fn bookmark_from_row(row: &rusqlite::Row) -> Result<Bookmark, rusqlite::Error> {
+    let start: Option<u32> = row.get(8)?;
+    let end: Option<u32> = row.get(9)?;
+    Ok(Bookmark {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        conversation_name: row.get(2)?,
+        message_id: row.get(3)?,
+        message_role: row.get(4)?,
+        message_timestamp: row.get(5)?,
+        note: row.get(6)?,
+        color: row.get(7)?,
+        range: start.zip(end).map(|(start, end)| TextRange { start, end }),
+        quote: row.get(10)?,
+        excerpt: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+    })
+}
+
+fn query(conn: &Connection, filter: &str, key: &str) -> Result<Vec<Bookmark>, rusqlite::Error> {
+    let sql = format!(
+        "{} WHERE {} AND c.deleted_at IS NULL ORDER BY b.created_at DESC, b.id DESC",
+        BOOKMARK_SELECT, filter
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![key], bookmark_from_row)?;
+
+    let mut bookmarks = Vec::new();
+    for row in rows {
+        bookmarks.push(row?);
+    }
+    Ok(bookmarks)
+}
+
+/// Every bookmark in the project's conversations, newest first. Trashed conversations are left out.
+pub fn list_for_project(conn: &Connection, project_path: &str) -> Result<Vec<Bookmark>, rusqlite::Error> {
+    query(conn, "c.project_path = ?1", project_path)
+}
+
+pub fn list_for_conversation(conn: &Connection, conversation_id: &str) -> Result<Vec<Bookmark>, rusqlite::Error> {
+    query(conn, "b.conversation_id = ?1", conversation_id)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Option<Bookmark>, rusqlite::Error> {
+    let sql = format!("{} WHERE b.id = ?1", BOOKMARK_SELECT);
+    conn.query_row(&sql, params![id], bookmark_from_row).optional()
+}
+
+pub fn add(
+    conn: &Connection,
+    message_id: &str,
+    note: &str,
+    color: Option<&str>,
+    range: Option<TextRange>,
+) -> Result<Bookmark, DrodeError> {
+    let color = check_color(color)?;
+    let (conversation_id, content): (String, String) = conn
+        .query_row(
+            "SELECT conversation_id, content FROM messages WHERE id = ?1",
+            params![message_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| DrodeError::not_found(format!("Message {}", message_id)))?;
+
+    let quote = match range {
+        Some(r) => Some(
+            quote_range(&content, r)
+                .ok_or_else(|| DrodeError::validation("Highlight range is outside the message"))?,
+        ),
+        None => None,
+    };
+
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT INTO bookmarks (conversation_id, message_id, note, color, range_start, range_end,
+             quote, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+        params![
+            conversation_id,
+            message_id,
+            note.trim(),
+            color,
+            range.map(|r| r.start),
+            range.map(|r| r.end),
+            quote,
+            now,
+        ],
+    )?;
+
+    get(conn, conn.last_insert_rowid())?.ok_or_else(|| DrodeError::internal("Bookmark vanished after insert"))
+}
+
+/// Change the note and/or color; the highlighted range is fixed once created
+pub fn update(
+    conn: &Connection,
+    id: i64,
+    note: Option<&str>,
+    color: Option<&str>,
+) -> Result<Bookmark, DrodeError> {
+    let color = match color {
+        Some(c) => Some(check_color(Some(c))?),
+        None => None,
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    let changed = conn.execute(
+        "UPDATE bookmarks SET note = COALESCE(?1, note), color = COALESCE(?2, color), updated_at = ?3
+         WHERE id = ?4",
+        params![note.map(str::trim), color, now, id],
+    )?;
+    if changed == 0 {
+        return Err(DrodeError::not_found(format!("Bookmark {}", id)));
+    }
+    get(conn, id)?.ok_or_else(|| DrodeError::not_found(format!("Bookmark {}", id)))
+}
+
+pub fn remove(conn: &Connection, id: i64) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM bookmarks WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+fn check_color(color: Option<&str>) -> Result<&'static str, DrodeError> {
+    match color {
+        None => Ok(DEFAULT_COLOR),
+        Some(c) => COLORS
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(c))
+            .copied()
+            .ok_or_else(|| DrodeError::validation(format!("Unknown bookmark color: {}", c))),
+    }
+}
+
+/// The text covered by a UTF-16 range; None if the range is empty, reversed or too long
+pub fn quote_range(content: &str, range: TextRange) -> Option<String> {
+    let units: Vec<u16> = content.encode_utf16().collect();
+    let (start, end) = (range.start as usize, range.end as usize);
+    if start >= end || end > units.len() {
+        return None;
+    }
+    // An offset that splits a surrogate pair decodes to U+FFFD rather than failing
+    Some(String::from_utf16_lossy(&units[start..end]))
+}
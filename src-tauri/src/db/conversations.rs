@@ -1,39 +1,156 @@
-use crate::state::{Conversation, ConversationMessage, ConversationSummary};
-use rusqlite::{params, Connection};
+use crate::state::{Conversation, ConversationMessage, ConversationPage, ConversationSummary};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
 use std::collections::HashMap;
 
-pub fn list(conn: &Connection, project_path: &str) -> Vec<ConversationSummary> {
-    let mut stmt = match conn.prepare(
-        "SELECT c.id, c.name, c.created_at, c.updated_at,
-                (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id) as msg_count
-         FROM conversations c
-         WHERE c.project_path = ?1
-         ORDER BY c.updated_at DESC",
-    ) {
-        Ok(s) => s,
-        Err(_) => return vec![],
-    };
+/// Filters for `list`. Archived conversations are hidden unless `archived` is set.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct ConversationFilter {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub pinned: Option<bool>,
+    #[serde(default)]
+    pub archived: Option<bool>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
 
-    let result = match stmt.query_map(params![project_path], |row| {
-        Ok(ConversationSummary {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            created_at: row.get(2)?,
-            updated_at: row.get(3)?,
-            message_count: row.get::<_, i64>(4)? as usize,
-        })
-    }) {
-        Ok(rows) => rows.filter_map(|r| r.ok()).collect(),
-        Err(_) => vec![],
+// Tag names are joined with the ASCII unit separator so commas in names survive
+const TAG_SEPARATOR: char = '\u{1f}';
+
+const SUMMARY_SELECT: &str = "SELECT c.id, c.name, c.created_at, c.updated_at,
+        (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id) as msg_count,
+        c.session_id, c.pinned, c.archived,
+        (SELECT group_concat(t.name, char(31)) FROM conversation_tags ct
+         JOIN tags t ON t.id = ct.tag_id WHERE ct.conversation_id = c.id) as tag_names,
+        c.deleted_at, c.summary, c.title_source
+    FROM conversations c";
+
+fn summary_from_row(row: &rusqlite::Row) -> Result<ConversationSummary, rusqlite::Error> {
+    let tag_names: Option<String> = row.get(8)?;
+    Ok(ConversationSummary {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        message_count: row.get::<_, i64>(4)? as usize,
+        session_id: row.get(5)?,
+        pinned: row.get(6)?,
+        archived: row.get(7)?,
+        tags: tag_names
+            .map(|names| names.split(TAG_SEPARATOR).map(|t| t.to_string()).collect())
+            .unwrap_or_default(),
+        deleted_at: row.get(9)?,
+        summary: row.get(10)?,
+        title_source: row.get(11)?,
+    })
+}
+
+pub fn list(
+    conn: &Connection,
+    project_path: &str,
+    filter: &ConversationFilter,
+) -> Result<ConversationPage, rusqlite::Error> {
+    let mut sql = format!(
+        "{} WHERE c.project_path = ?1 AND c.archived = ?2 AND c.deleted_at IS NULL",
+        SUMMARY_SELECT
+    );
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = vec![
+        Box::new(project_path.to_string()),
+        Box::new(filter.archived.unwrap_or(false)),
+    ];
+
+    if let Some(pinned) = filter.pinned {
+        params_vec.push(Box::new(pinned));
+        sql.push_str(&format!(" AND c.pinned = ?{}", params_vec.len()));
+    }
+    if let Some(ref tag) = filter.tag {
+        params_vec.push(Box::new(tag.clone()));
+        sql.push_str(&format!(
+            " AND EXISTS (SELECT 1 FROM conversation_tags ct JOIN tags t ON t.id = ct.tag_id
+                          WHERE ct.conversation_id = c.id AND t.name = ?{})",
+            params_vec.len()
+        ));
+    }
+    if let Some((pinned, updated_at, id)) = filter.cursor.as_deref().and_then(decode_cursor) {
+        params_vec.push(Box::new(pinned));
+        params_vec.push(Box::new(updated_at));
+        params_vec.push(Box::new(id));
+        let n = params_vec.len();
+        sql.push_str(&format!(
+            " AND (c.pinned, c.updated_at, c.id) < (?{}, ?{}, ?{})",
+            n - 2,
+            n - 1,
+            n
+        ));
+    }
+
+    sql.push_str(" ORDER BY c.pinned DESC, c.updated_at DESC, c.id DESC");
+    // Fetch one extra row to know whether another page exists
+    if let Some(limit) = filter.limit {
+        sql.push_str(&format!(" LIMIT {}", limit as u64 + 1));
+    }
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+
+    let mut conversations: Vec<ConversationSummary> = stmt
+        .query_map(param_refs.as_slice(), summary_from_row)?
+        .collect::<Result<_, _>>()?;
+
+    let next_cursor = match filter.limit {
+        Some(limit) if conversations.len() > limit as usize => {
+            conversations.truncate(limit as usize);
+            conversations.last().map(encode_cursor)
+        }
+        _ => None,
     };
-    result
+
+    Ok(ConversationPage {
+        conversations,
+        next_cursor,
+    })
+}
+
+fn encode_cursor(c: &ConversationSummary) -> String {
+    format!("{}:{}:{}", c.pinned as i32, c.updated_at, c.id)
+}
+
+fn decode_cursor(cursor: &str) -> Option<(bool, i64, String)> {
+    let mut parts = cursor.splitn(3, ':');
+    let pinned = parts.next()? == "1";
+    let updated_at = parts.next()?.parse().ok()?;
+    let id = parts.next()?.to_string();
+    Some((pinned, updated_at, id))
+}
+
+pub fn set_pinned(conn: &Connection, conversation_id: &str, pinned: bool) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE conversations SET pinned = ?1 WHERE id = ?2",
+        params![pinned, conversation_id],
+    )?;
+    Ok(())
+}
+
+pub fn set_archived(conn: &Connection, conversation_id: &str, archived: bool) -> Result<(), rusqlite::Error> {
+    // An archived conversation can't stay the active one
+    conn.execute(
+        "UPDATE conversations SET archived = ?1,
+                is_active = CASE WHEN ?1 THEN 0 ELSE is_active END
+         WHERE id = ?2",
+        params![archived, conversation_id],
+    )?;
+    Ok(())
 }
 
 pub fn create(
     conn: &Connection,
     project_path: &str,
     name: &str,
-) -> Option<ConversationSummary> {
+) -> Result<ConversationSummary, rusqlite::Error> {
     let now = chrono::Utc::now().timestamp_millis();
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -41,18 +158,24 @@ pub fn create(
         "INSERT INTO conversations (id, project_path, name, created_at, updated_at, is_active)
          VALUES (?1, ?2, ?3, ?4, ?5, 0)",
         params![id, project_path, name, now, now],
-    )
-    .ok()?;
+    )?;
 
     // Set as active
-    let _ = set_active(conn, project_path, &id);
+    set_active(conn, project_path, &id)?;
 
-    Some(ConversationSummary {
+    Ok(ConversationSummary {
         id,
         name: name.to_string(),
         created_at: now,
         updated_at: now,
         message_count: 0,
+        session_id: None,
+        pinned: false,
+        archived: false,
+        tags: Vec::new(),
+        deleted_at: None,
+        summary: None,
+        title_source: None,
     })
 }
 
@@ -60,10 +183,11 @@ pub fn get(
     conn: &Connection,
     _project_path: &str,
     conversation_id: &str,
-) -> Option<Conversation> {
+) -> Result<Option<Conversation>, rusqlite::Error> {
     let conv = conn
         .query_row(
-            "SELECT id, name, created_at, updated_at FROM conversations WHERE id = ?1",
+            "SELECT id, name, created_at, updated_at, session_id FROM conversations
+             WHERE id = ?1 AND deleted_at IS NULL",
             params![conversation_id],
             |row| {
                 Ok(Conversation {
@@ -71,18 +195,31 @@ pub fn get(
                     name: row.get(1)?,
                     created_at: row.get(2)?,
                     updated_at: row.get(3)?,
+                    session_id: row.get(4)?,
                     messages: Vec::new(),
                 })
             },
         )
-        .ok()?;
+        .optional()?;
 
-    let messages = load_messages(conn, conversation_id);
+    let Some(conv) = conv else {
+        return Ok(None);
+    };
+    let messages = load_messages(conn, conversation_id)?;
 
-    Some(Conversation {
+    Ok(Some(Conversation {
         messages,
         ..conv
-    })
+    }))
+}
+
+/// role, content, timestamp and the metadata, tool use and tool result JSON of a message
+type StoredMessage = (String, String, i64, Option<String>, Option<String>, Option<String>);
+
+/// JSON for a message column. Goes through Value, whose maps are sorted, so an unchanged
+/// message serializes to the same text on every save.
+fn to_json<T: serde::Serialize>(value: &T) -> Option<String> {
+    serde_json::to_value(value).ok().map(|v| v.to_string())
 }
 
 pub fn save_messages(
@@ -92,12 +229,50 @@ pub fn save_messages(
 ) -> Result<(), rusqlite::Error> {
     let tx = conn.unchecked_transaction()?;
 
-    tx.execute(
-        "DELETE FROM messages WHERE conversation_id = ?1",
-        params![conversation_id],
-    )?;
+    // Only messages that changed are rewritten, so what triggers derive from the others
+    // (the search index, file_edits and the edit ids the UI holds) stays as it is
+    let mut stored: HashMap<String, (StoredMessage, i32)> = {
+        let mut stmt = tx.prepare(
+            "SELECT id, role, content, timestamp, metadata_json, tool_uses_json, tool_results_json,
+                    sort_order
+             FROM messages WHERE conversation_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![conversation_id], |row| {
+            let message = (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?);
+            Ok((row.get::<_, String>(0)?, (message, row.get::<_, i32>(7)?)))
+        })?;
+        rows.collect::<Result<_, _>>()?
+    };
 
     for (order, msg) in messages.iter().enumerate() {
+        let order = order as i32;
+        let message: StoredMessage = (
+            msg.role.clone(),
+            msg.content.clone(),
+            msg.timestamp,
+            msg.metadata.as_ref().and_then(to_json),
+            msg.tool_uses.as_ref().and_then(to_json),
+            msg.tool_results.as_ref().and_then(to_json),
+        );
+        match stored.remove(&msg.id) {
+            Some((old, old_order)) if old == message => {
+                if old_order != order {
+                    tx.execute(
+                        "UPDATE messages SET sort_order = ?1 WHERE id = ?2",
+                        params![order, msg.id],
+                    )?;
+                }
+                continue;
+            }
+            // Deleted and inserted again rather than updated: the file_edits triggers
+            // only follow inserts and deletes
+            Some(_) => {
+                tx.execute("DELETE FROM messages WHERE id = ?1", params![msg.id])?;
+            }
+            None => {}
+        }
+
+        let (role, content, timestamp, metadata_json, tool_uses_json, tool_results_json) = message;
         tx.execute(
             "INSERT INTO messages (id, conversation_id, role, content, timestamp,
              metadata_json, tool_uses_json, tool_results_json, sort_order)
@@ -105,23 +280,28 @@ pub fn save_messages(
             params![
                 msg.id,
                 conversation_id,
-                msg.role,
-                msg.content,
-                msg.timestamp,
-                msg.metadata
-                    .as_ref()
-                    .and_then(|m| serde_json::to_string(m).ok()),
-                msg.tool_uses
-                    .as_ref()
-                    .and_then(|t| serde_json::to_string(t).ok()),
-                msg.tool_results
-                    .as_ref()
-                    .and_then(|t| serde_json::to_string(t).ok()),
-                order as i32,
+                role,
+                content,
+                timestamp,
+                metadata_json,
+                tool_uses_json,
+                tool_results_json,
+                order,
             ],
         )?;
     }
 
+    for id in stored.keys() {
+        tx.execute("DELETE FROM messages WHERE id = ?1", params![id])?;
+    }
+
+    // Bookmarks outlive the rewrite as long as their message is still there
+    tx.execute(
+        "DELETE FROM bookmarks WHERE conversation_id = ?1
+         AND message_id NOT IN (SELECT id FROM messages WHERE conversation_id = ?1)",
+        params![conversation_id],
+    )?;
+
     let now = chrono::Utc::now().timestamp_millis();
     tx.execute(
         "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
@@ -131,39 +311,83 @@ pub fn save_messages(
     tx.commit()
 }
 
+/// Move a conversation to the trash. Messages stay in place until the trash is
+/// emptied or the retention period purges it.
 pub fn delete(
     conn: &Connection,
-    project_path: &str,
+    _project_path: &str,
     conversation_id: &str,
 ) -> Result<(), rusqlite::Error> {
-    // Check if this is the active conversation
-    let is_active: bool = conn
-        .query_row(
-            "SELECT is_active FROM conversations WHERE id = ?1",
-            params![conversation_id],
-            |row| row.get(0),
-        )
-        .unwrap_or(false);
-
-    // CASCADE will delete messages automatically
+    let now = chrono::Utc::now().timestamp_millis();
+    // A trashed conversation can't stay the active one
     conn.execute(
-        "DELETE FROM conversations WHERE id = ?1",
-        params![conversation_id],
+        "UPDATE conversations SET deleted_at = ?1, is_active = 0 WHERE id = ?2",
+        params![now, conversation_id],
     )?;
+    Ok(())
+}
 
-    // If deleted the active one, clear active for this project
-    if is_active {
-        // No need to do anything - the row is deleted
-        // Optionally set another conversation as active
-        let _ = conn.execute(
-            "UPDATE conversations SET is_active = 0 WHERE project_path = ?1",
-            params![project_path],
-        );
+pub fn list_trash(conn: &Connection, project_path: &str) -> Result<Vec<ConversationSummary>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE c.project_path = ?1 AND c.deleted_at IS NOT NULL ORDER BY c.deleted_at DESC",
+        SUMMARY_SELECT
+    ))?;
+    let rows = stmt.query_map(params![project_path], summary_from_row)?;
+
+    let mut conversations = Vec::new();
+    for row in rows {
+        conversations.push(row?);
     }
+    Ok(conversations)
+}
 
+pub fn restore(conn: &Connection, conversation_id: &str) -> Result<(), rusqlite::Error> {
+    let changed = conn.execute(
+        "UPDATE conversations SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+        params![conversation_id],
+    )?;
+    if changed == 0 {
+        return Err(rusqlite::Error::QueryReturnedNoRows);
+    }
     Ok(())
 }
 
+/// Permanently delete trashed conversations (CASCADE removes their messages).
+/// `project_path` limits the purge to one project; `deleted_before` to old entries.
+pub fn purge_trash(
+    conn: &Connection,
+    project_path: Option<&str>,
+    deleted_before: Option<i64>,
+) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM conversations
+         WHERE deleted_at IS NOT NULL
+           AND (?1 IS NULL OR project_path = ?1)
+           AND (?2 IS NULL OR deleted_at < ?2)",
+        params![project_path, deleted_before],
+    )
+}
+
+pub const TRASH_RETENTION_KEY: &str = "trash_retention_days";
+const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+/// Days a conversation stays in the trash before startup purges it (0 = keep forever)
+pub fn trash_retention_days(conn: &Connection) -> u32 {
+    crate::db::settings::get(conn, TRASH_RETENTION_KEY)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+}
+
+/// Startup housekeeping: drop trashed conversations older than the retention period
+pub fn purge_expired_trash(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    let days = trash_retention_days(conn);
+    if days == 0 {
+        return Ok(0);
+    }
+    let cutoff = chrono::Utc::now().timestamp_millis() - i64::from(days) * 24 * 60 * 60 * 1000;
+    purge_trash(conn, None, Some(cutoff))
+}
+
 pub fn rename(
     conn: &Connection,
     conversation_id: &str,
@@ -171,19 +395,20 @@ pub fn rename(
 ) -> Result<(), rusqlite::Error> {
     let now = chrono::Utc::now().timestamp_millis();
     conn.execute(
-        "UPDATE conversations SET name = ?1, updated_at = ?2 WHERE id = ?3",
+        "UPDATE conversations SET name = ?1, title_source = 'user', updated_at = ?2 WHERE id = ?3",
         params![new_name, now, conversation_id],
     )?;
     Ok(())
 }
 
-pub fn get_active(conn: &Connection, project_path: &str) -> Option<String> {
+pub fn get_active(conn: &Connection, project_path: &str) -> Result<Option<String>, rusqlite::Error> {
     conn.query_row(
-        "SELECT id FROM conversations WHERE project_path = ?1 AND is_active = 1",
+        "SELECT id FROM conversations
+         WHERE project_path = ?1 AND is_active = 1 AND deleted_at IS NULL",
         params![project_path],
         |row| row.get(0),
     )
-    .ok()
+    .optional()
 }
 
 pub fn set_active(
@@ -197,7 +422,7 @@ pub fn set_active(
         params![project_path],
     )?;
     tx.execute(
-        "UPDATE conversations SET is_active = 1 WHERE id = ?1",
+        "UPDATE conversations SET is_active = 1 WHERE id = ?1 AND deleted_at IS NULL",
         params![conversation_id],
     )?;
     tx.commit()
@@ -205,13 +430,14 @@ pub fn set_active(
 
 // Legacy single-conversation support
 
-const LEGACY_NAME: &str = "__legacy__";
+// Flagged with is_legacy rather than found by name so it can be renamed like any other
+const LEGACY_NAME: &str = "Conversation";
 
 fn get_or_create_legacy(conn: &Connection, project_path: &str) -> Option<String> {
     // Try to find existing legacy conversation
     if let Ok(id) = conn.query_row(
-        "SELECT id FROM conversations WHERE project_path = ?1 AND name = ?2",
-        params![project_path, LEGACY_NAME],
+        "SELECT id FROM conversations WHERE project_path = ?1 AND is_legacy = 1",
+        params![project_path],
         |row| row.get::<_, String>(0),
     ) {
         return Some(id);
@@ -221,8 +447,8 @@ fn get_or_create_legacy(conn: &Connection, project_path: &str) -> Option<String>
     let id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().timestamp_millis();
     conn.execute(
-        "INSERT INTO conversations (id, project_path, name, created_at, updated_at, is_active)
-         VALUES (?1, ?2, ?3, ?4, ?5, 0)",
+        "INSERT INTO conversations (id, project_path, name, created_at, updated_at, is_active, is_legacy)
+         VALUES (?1, ?2, ?3, ?4, ?5, 0, 1)",
         params![id, project_path, LEGACY_NAME, now, now],
     )
     .ok()?;
@@ -233,46 +459,46 @@ pub fn save_legacy(
     conn: &Connection,
     project_path: &str,
     messages: &[ConversationMessage],
-) -> Result<(), rusqlite::Error> {
+) -> Result<String, rusqlite::Error> {
     let conv_id = get_or_create_legacy(conn, project_path)
         .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
-    save_messages(conn, &conv_id, messages)
+    save_messages(conn, &conv_id, messages)?;
+    Ok(conv_id)
 }
 
-pub fn load_legacy(conn: &Connection, project_path: &str) -> Vec<ConversationMessage> {
-    let conv_id = match conn.query_row(
-        "SELECT id FROM conversations WHERE project_path = ?1 AND name = ?2",
-        params![project_path, LEGACY_NAME],
-        |row| row.get::<_, String>(0),
-    ) {
-        Ok(id) => id,
-        Err(_) => return vec![],
-    };
-    load_messages(conn, &conv_id)
+pub fn load_legacy(conn: &Connection, project_path: &str) -> Result<Vec<ConversationMessage>, rusqlite::Error> {
+    let conv_id = conn
+        .query_row(
+            "SELECT id FROM conversations WHERE project_path = ?1 AND is_legacy = 1",
+            params![project_path],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    match conv_id {
+        Some(id) => load_messages(conn, &id),
+        None => Ok(vec![]),
+    }
 }
 
 pub fn clear_legacy(conn: &Connection, project_path: &str) -> Result<(), rusqlite::Error> {
     conn.execute(
-        "DELETE FROM conversations WHERE project_path = ?1 AND name = ?2",
-        params![project_path, LEGACY_NAME],
+        "DELETE FROM conversations WHERE project_path = ?1 AND is_legacy = 1",
+        params![project_path],
     )?;
     Ok(())
 }
 
 // Shared helper
 
-fn load_messages(conn: &Connection, conversation_id: &str) -> Vec<ConversationMessage> {
-    let mut stmt = match conn.prepare(
+fn load_messages(conn: &Connection, conversation_id: &str) -> Result<Vec<ConversationMessage>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
         "SELECT id, role, content, timestamp, metadata_json, tool_uses_json, tool_results_json
          FROM messages
          WHERE conversation_id = ?1
          ORDER BY sort_order ASC",
-    ) {
-        Ok(s) => s,
-        Err(_) => return vec![],
-    };
+    )?;
 
-    let result = match stmt.query_map(params![conversation_id], |row| {
+    let result = stmt.query_map(params![conversation_id], |row| {
         let metadata_json: Option<String> = row.get(4)?;
         let tool_uses_json: Option<String> = row.get(5)?;
         let tool_results_json: Option<String> = row.get(6)?;
@@ -290,9 +516,7 @@ fn load_messages(conn: &Connection, conversation_id: &str) -> Vec<ConversationMe
                 serde_json::from_str::<HashMap<String, crate::state::ToolResult>>(&j).ok()
             }),
         })
-    }) {
-        Ok(rows) => rows.filter_map(|r| r.ok()).collect(),
-        Err(_) => vec![],
-    };
-    result
+    })?
+    .collect::<Result<_, _>>()?;
+    Ok(result)
 }
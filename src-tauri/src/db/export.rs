@@ -0,0 +1,89 @@
+// Markdown export of a single conversation. Bookmarks are written as quote blocks
+// after the message they belong to and listed again at the top as an index.
+
+use crate::db::bookmarks::{self, Bookmark};
+use crate::state::ConversationMessage;
+use std::collections::HashMap;
+
+/// None if the conversation doesn't exist or is in the trash
+pub fn conversation_markdown(
+    conn: &rusqlite::Connection,
+    conversation_id: &str,
+) -> Result<Option<String>, rusqlite::Error> {
+    let Some(conv) = crate::db::conversations::get(conn, "", conversation_id)? else {
+        return Ok(None);
+    };
+    let summary: Option<String> = conn.query_row(
+        "SELECT summary FROM conversations WHERE id = ?1",
+        rusqlite::params![conversation_id],
+        |row| row.get(0),
+    )?;
+    let marks = bookmarks::list_for_conversation(conn, conversation_id)?;
+
+    let mut out = format!("# {}\n\n", conv.name);
+    out.push_str(&format!("Exported {}\n\n", format_ts(chrono::Utc::now().timestamp_millis())));
+    if let Some(summary) = summary {
+        out.push_str(&format!("{}\n\n", summary.trim()));
+    }
+
+    if !marks.is_empty() {
+        out.push_str("## Bookmarks\n\n");
+        // Oldest first, in reading order
+        for b in marks.iter().rev() {
+            let label = if b.note.is_empty() { one_line(&b.excerpt) } else { one_line(&b.note) };
+            out.push_str(&format!("- [{}](#message-{}) ({})\n", label, b.message_id, b.color));
+        }
+        out.push('\n');
+    }
+
+    let mut by_message: HashMap<&str, Vec<&Bookmark>> = HashMap::new();
+    for b in marks.iter().rev() {
+        by_message.entry(b.message_id.as_str()).or_default().push(b);
+    }
+
+    out.push_str("---\n\n");
+    for msg in &conv.messages {
+        write_message(&mut out, msg, by_message.get(msg.id.as_str()).map(Vec::as_slice).unwrap_or(&[]));
+    }
+    Ok(Some(out))
+}
+
+fn write_message(out: &mut String, msg: &ConversationMessage, marks: &[&Bookmark]) {
+    let role = match msg.role.as_str() {
+        "user" => "User",
+        "assistant" => "Assistant",
+        _ => "System",
+    };
+    out.push_str(&format!(
+        "<a id=\"message-{}\"></a>\n### {} · {}\n\n{}\n\n",
+        msg.id,
+        role,
+        format_ts(msg.timestamp),
+        msg.content.trim_end()
+    ));
+
+    for b in marks {
+        out.push_str(&format!("> **Bookmark** ({})", b.color));
+        if !b.note.is_empty() {
+            out.push_str(&format!(": {}", one_line(&b.note)));
+        }
+        out.push('\n');
+        if let Some(ref quote) = b.quote {
+            out.push_str(">\n");
+            for line in quote.lines() {
+                out.push_str(&format!("> > {}\n", line));
+            }
+        }
+        out.push('\n');
+    }
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn format_ts(millis: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,383 @@
+// Per-file history of Claude's Edit/Write/MultiEdit tool uses (schema version 10).
+// The file_edits table is filled by triggers on messages; this module queries it and
+// renders the edits as unified diffs.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use std::ops::Range;
+
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileEdit {
+    pub id: i64,
+    #[serde(rename = "conversationId")]
+    pub conversation_id: String,
+    #[serde(rename = "conversationName")]
+    pub conversation_name: String,
+    #[serde(rename = "projectPath")]
+    pub project_path: String,
+    #[serde(rename = "messageId")]
+    pub message_id: String,
+    #[serde(rename = "toolUseId")]
+    pub tool_use_id: String,
+    /// "Edit", "Write" or "MultiEdit"
+    pub tool: String,
+    #[serde(rename = "filePath")]
+    pub file_path: String,
+    #[serde(rename = "oldString")]
+    pub old_string: Option<String>,
+    /// Replacement text, or the whole file for Write
+    #[serde(rename = "newString")]
+    pub new_string: Option<String>,
+    #[serde(rename = "replaceAll")]
+    pub replace_all: bool,
+    /// Tool use status at the time the message was saved (completed, denied, error ...)
+    pub status: Option<String>,
+    pub timestamp: i64,
+}
+
+impl FileEdit {
+    /// Denied or failed tool uses never touched the file
+    pub fn was_applied(&self) -> bool {
+        !matches!(self.status.as_deref(), Some("denied") | Some("error"))
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EditedFile {
+    #[serde(rename = "filePath")]
+    pub file_path: String,
+    #[serde(rename = "editCount")]
+    pub edit_count: i64,
+    #[serde(rename = "conversationCount")]
+    pub conversation_count: i64,
+    #[serde(rename = "lastEditedAt")]
+    pub last_edited_at: i64,
+}
+
+const EDIT_SELECT: &str = "SELECT e.id, e.conversation_id, c.name, c.project_path, e.message_id,
+        e.tool_use_id, e.tool, e.file_path, e.old_string, e.new_string, e.replace_all,
+        e.status, e.timestamp
+    FROM file_edits e
+    JOIN conversations c ON c.id = e.conversation_id";
+
+fn edit_from_row(row: &rusqlite::Row) -> Result<FileEdit, rusqlite::Error> {
+    Ok(FileEdit {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        conversation_name: row.get(2)?,
+        project_path: row.get(3)?,
+        message_id: row.get(4)?,
+        tool_use_id: row.get(5)?,
+        tool: row.get(6)?,
+        file_path: row.get(7)?,
+        old_string: row.get(8)?,
+        new_string: row.get(9)?,
+        replace_all: row.get(10)?,
+        status: row.get(11)?,
+        timestamp: row.get(12)?,
+    })
+}
+
+/// Edits in the project, oldest first, optionally narrowed to one file or conversation.
+/// Conversations in the trash are left out.
+pub fn list(
+    conn: &Connection,
+    project_path: &str,
+    file_path: Option<&str>,
+    conversation_id: Option<&str>,
+) -> Result<Vec<FileEdit>, rusqlite::Error> {
+    let mut sql = format!("{} WHERE c.project_path = ?1 AND c.deleted_at IS NULL", EDIT_SELECT);
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(project_path.to_string())];
+
+    if let Some(path) = file_path {
+        params_vec.push(Box::new(path.to_string()));
+        sql.push_str(&format!(" AND e.file_path = ?{}", params_vec.len()));
+    }
+    if let Some(id) = conversation_id {
+        params_vec.push(Box::new(id.to_string()));
+        sql.push_str(&format!(" AND e.conversation_id = ?{}", params_vec.len()));
+    }
+    sql.push_str(" ORDER BY e.timestamp ASC, e.id ASC");
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+    let rows = stmt.query_map(param_refs.as_slice(), edit_from_row)?;
+
+    let mut edits = Vec::new();
+    for row in rows {
+        edits.push(row?);
+    }
+    Ok(edits)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Option<FileEdit>, rusqlite::Error> {
+    let sql = format!("{} WHERE e.id = ?1", EDIT_SELECT);
+    conn.query_row(&sql, params![id], edit_from_row).optional()
+}
+
+/// Files Claude has changed in the project, most recently edited first
+pub fn edited_files(conn: &Connection, project_path: &str) -> Result<Vec<EditedFile>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT e.file_path, COUNT(*), COUNT(DISTINCT e.conversation_id), MAX(e.timestamp)
+         FROM file_edits e
+         JOIN conversations c ON c.id = e.conversation_id
+         WHERE c.project_path = ?1 AND c.deleted_at IS NULL
+         GROUP BY e.file_path
+         ORDER BY MAX(e.timestamp) DESC",
+    )?;
+
+    let rows = stmt.query_map(params![project_path], |row| {
+        Ok(EditedFile {
+            file_path: row.get(0)?,
+            edit_count: row.get(1)?,
+            conversation_count: row.get(2)?,
+            last_edited_at: row.get(3)?,
+        })
+    })?;
+
+    let mut files = Vec::new();
+    for row in rows {
+        files.push(row?);
+    }
+    Ok(files)
+}
+
+/// Unified diff for one edit. Write is shown as a new file, since the content it replaced
+/// isn't recorded. For Edit/MultiEdit the hunk line numbers are found in `current` (the file
+/// as it is now) when the new text is still there, otherwise they count from the snippet.
+pub fn unified_diff(edit: &FileEdit, current: Option<&str>) -> String {
+    let path = display_path(&edit.file_path, &edit.project_path);
+    let new = edit.new_string.as_deref().unwrap_or("");
+
+    let mut out = String::new();
+    if edit.tool == "Write" {
+        out.push_str(&format!("--- /dev/null\n+++ b/{}\n", path));
+        write_hunks(&mut out, "", new, 0);
+        return out;
+    }
+
+    // Snippets are cut mid-file, so a missing final newline means nothing here
+    let old = with_newline(edit.old_string.as_deref().unwrap_or(""));
+    let new = with_newline(new);
+    let offset = current.and_then(|text| line_of(text, new.trim_end_matches('\n'))).unwrap_or(0);
+
+    out.push_str(&format!("--- a/{}\n+++ b/{}\n", path, path));
+    write_hunks(&mut out, &old, &new, offset);
+    out
+}
+
+/// A patch taking each edited file from its first known content to its content now, for
+/// `git apply` or `patch -p1`. The first known content is found by undoing the applied
+/// edits on the current file, newest first. Undoing stops at an edit whose new text isn't
+/// in the file any more (changed since, or an edit that only deleted text), and at a
+/// Write, whose previous content isn't recorded; a file that starts with a Write is shown
+/// as created by it. Files that are gone or outside the project are listed in comments.
+/// `read_current` returns a file's current content, if it still exists.
+pub fn to_patch(edits: &[FileEdit], read_current: impl Fn(&str) -> Option<String>) -> String {
+    let mut files: Vec<(&str, Vec<&FileEdit>)> = Vec::new();
+    for edit in edits.iter().filter(|e| e.was_applied()) {
+        match files.iter_mut().find(|(path, _)| *path == edit.file_path) {
+            Some((_, file_edits)) => file_edits.push(edit),
+            None => files.push((&edit.file_path, vec![edit])),
+        }
+    }
+
+    let mut out = String::new();
+    for (file_path, file_edits) in files {
+        let Some(rel) = relative_path(file_path, &file_edits[0].project_path) else {
+            out.push_str(&format!("# {}: outside the project, left out\n", file_path));
+            continue;
+        };
+        let Some(current) = read_current(file_path) else {
+            out.push_str(&format!("# {}: no longer exists, left out\n", rel));
+            continue;
+        };
+
+        let (first, undone) = first_known(&current, &file_edits);
+        if undone < file_edits.len() {
+            out.push_str(&format!(
+                "# {}: {} of {} edits included, the rest predate the first known content\n",
+                rel,
+                undone,
+                file_edits.len()
+            ));
+        }
+        match first {
+            Some(first) if first == current => {}
+            Some(first) => out.push_str(&text_diff(&format!("a/{}", rel), &format!("b/{}", rel), &first, &current)),
+            None => out.push_str(&text_diff("/dev/null", &format!("b/{}", rel), "", &current)),
+        }
+    }
+    out
+}
+
+/// Undo `edits` (oldest first) on `current`, newest first, as far as they can be undone.
+/// Returns the content reached, None when the file was created by its first edit, and how
+/// many edits were undone.
+fn first_known(current: &str, edits: &[&FileEdit]) -> (Option<String>, usize) {
+    let mut text = current.to_string();
+    for (undone, edit) in edits.iter().rev().enumerate() {
+        if edit.tool == "Write" {
+            if undone + 1 == edits.len() {
+                return (None, edits.len());
+            }
+            return (Some(text), undone);
+        }
+
+        let old = edit.old_string.as_deref().unwrap_or("");
+        let new = edit.new_string.as_deref().unwrap_or("");
+        if new.is_empty() || !text.contains(new) {
+            return (Some(text), undone);
+        }
+        text = if edit.replace_all {
+            text.replace(new, old)
+        } else {
+            text.replacen(new, old, 1)
+        };
+    }
+    (Some(text), edits.len())
+}
+
+/// Plain unified diff of two texts, e.g. two versions of a file from the local history
+pub fn text_diff(old_label: &str, new_label: &str, old: &str, new: &str) -> String {
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    write_hunks(&mut out, old, new, 0);
+    out
+}
+
+fn write_hunks(out: &mut String, old: &str, new: &str, offset: usize) {
+    let diff = TextDiff::from_lines(old, new);
+    for group in diff.grouped_ops(CONTEXT_LINES) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else {
+            continue;
+        };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_range, offset),
+            hunk_range(new_range, offset)
+        ));
+
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let sign = match change.tag() {
+                    ChangeTag::Delete => '-',
+                    ChangeTag::Insert => '+',
+                    ChangeTag::Equal => ' ',
+                };
+                out.push(sign);
+                out.push_str(change.value());
+                if change.missing_newline() {
+                    out.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+    }
+}
+
+// "start,len" with 1-based lines; an empty range points at the line before it
+fn hunk_range(range: Range<usize>, offset: usize) -> String {
+    let len = range.len();
+    let start = if len == 0 { range.start + offset } else { range.start + offset + 1 };
+    if len == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, len)
+    }
+}
+
+fn line_of(text: &str, snippet: &str) -> Option<usize> {
+    if snippet.is_empty() {
+        return None;
+    }
+    let idx = text.find(snippet)?;
+    Some(text[..idx].matches('\n').count())
+}
+
+fn with_newline(text: &str) -> String {
+    if text.is_empty() || text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+/// Path relative to the project root when the file is inside it
+fn display_path(file_path: &str, project_path: &str) -> String {
+    relative_path(file_path, project_path).unwrap_or_else(|| file_path.trim_start_matches('/').to_string())
+}
+
+fn relative_path(file_path: &str, project_path: &str) -> Option<String> {
+    let root = project_path.trim_end_matches('/');
+    match file_path.strip_prefix(root).and_then(|rest| rest.strip_prefix('/')) {
+        Some(rel) if !root.is_empty() => Some(rel.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(tool: &str, file: &str, old: Option<&str>, new: &str) -> FileEdit {
+        FileEdit {
+            id: 0,
+            conversation_id: "c".to_string(),
+            conversation_name: "Chat".to_string(),
+            project_path: "/p".to_string(),
+            message_id: "m".to_string(),
+            tool_use_id: "t".to_string(),
+            tool: tool.to_string(),
+            file_path: format!("/p/{}", file),
+            old_string: old.map(str::to_string),
+            new_string: Some(new.to_string()),
+            replace_all: false,
+            status: Some("completed".to_string()),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn patch_goes_from_first_known_content_to_now() {
+        let mut denied = edit("Edit", "a.rs", Some("x"), "y");
+        denied.status = Some("denied".to_string());
+        let edits = vec![
+            edit("Edit", "a.rs", Some("fn a() {}"), "fn a() {\n    b();\n}"),
+            denied,
+            edit("Edit", "a.rs", Some("use x;"), "use x::y;"),
+            edit("Write", "new.txt", None, "hello\n"),
+            edit("Edit", "new.txt", Some("hello"), "hello world"),
+            edit("Edit", "gone.rs", Some("a"), "b"),
+        ];
+        let current = |path: &str| match path {
+            "/p/a.rs" => Some("use x::y;\n\nfn a() {\n    b();\n}\n".to_string()),
+            "/p/new.txt" => Some("hello world\n".to_string()),
+            _ => None,
+        };
+
+        let patch = to_patch(&edits, current);
+        assert_eq!(
+            patch,
+            "--- a/a.rs\n+++ b/a.rs\n@@ -1,3 +1,5 @@\n-use x;\n+use x::y;\n \n-fn a() {}\n+fn a() {\n+    b();\n+}\n\
+             --- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+hello world\n\
+             # gone.rs: no longer exists, left out\n"
+        );
+    }
+
+    #[test]
+    fn undoing_stops_where_the_history_breaks() {
+        let edits = [
+            edit("Edit", "a.rs", Some("one"), "two"),
+            edit("Write", "a.rs", None, "three\n"),
+            edit("Edit", "a.rs", Some("three"), "four"),
+        ];
+        let edits: Vec<&FileEdit> = edits.iter().collect();
+        assert_eq!(first_known("four\n", &edits), (Some("three\n".to_string()), 1));
+        // Changed by hand since the last edit
+        assert_eq!(first_known("five\n", &edits), (Some("five\n".to_string()), 0));
+    }
+}
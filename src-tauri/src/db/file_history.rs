@@ -0,0 +1,195 @@
+// Local history index (schema version 12). Rows only; the snapshot content itself is
+// stored and compressed by local_history.rs.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+// Renames are followed back this many times when listing a file's history
+const MAX_RENAME_HOPS: usize = 20;
+// Size-based retention drops the oldest entries this many at a time
+const PRUNE_BATCH: i64 = 20;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub path: String,
+    /// Where the file was before, for renames
+    #[serde(rename = "oldPath", skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    /// "save", "external", "rename", "delete" or "restore"
+    pub source: String,
+    pub hash: String,
+    pub size: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+const ENTRY_SELECT: &str = "SELECT h.id, h.path, h.old_path, h.source, h.hash, o.size, h.created_at
+    FROM file_history h
+    JOIN history_objects o ON o.hash = h.hash";
+
+fn entry_from_row(row: &rusqlite::Row) -> Result<HistoryEntry, rusqlite::Error> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        path: row.get(1)?,
+        old_path: row.get(2)?,
+        source: row.get(3)?,
+        hash: row.get(4)?,
+        size: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// Add an entry, and its object row if the content is new. Runs in the caller's
+/// transaction, which also checked and wrote the object (see local_history.rs).
+pub fn insert(
+    conn: &Connection,
+    path: &str,
+    old_path: Option<&str>,
+    source: &str,
+    hash: &str,
+    size: u64,
+    stored_size: u64,
+) -> Result<i64, rusqlite::Error> {
+    let now = chrono::Utc::now().timestamp_millis();
+    conn.execute(
+        "INSERT OR IGNORE INTO history_objects (hash, size, stored_size, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![hash, size as i64, stored_size as i64, now],
+    )?;
+    conn.execute(
+        "INSERT INTO file_history (path, old_path, source, hash, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![path, old_path, source, hash, now],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn has_object(conn: &Connection, hash: &str) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM history_objects WHERE hash = ?1",
+        params![hash],
+        |row| row.get(0),
+    )
+}
+
+/// Hash of the newest snapshot of `path`
+pub fn latest_hash(conn: &Connection, path: &str) -> Result<Option<String>, rusqlite::Error> {
+    conn.query_row(
+        "SELECT hash FROM file_history WHERE path = ?1 ORDER BY created_at DESC, id DESC LIMIT 1",
+        params![path],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Newest first. Renames are followed, so the entries a file had under its old name are
+/// included up to the point it was renamed.
+pub fn list(conn: &Connection, path: &str) -> Result<Vec<HistoryEntry>, rusqlite::Error> {
+    let sql = format!(
+        "{} WHERE h.path = ?1 AND h.id <= ?2 ORDER BY h.created_at DESC, h.id DESC",
+        ENTRY_SELECT
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    let mut current = path.to_string();
+    let mut before = i64::MAX;
+
+    for _ in 0..MAX_RENAME_HOPS {
+        let rows = stmt.query_map(params![current, before], entry_from_row)?;
+        let mut renamed_from = None;
+        for row in rows {
+            let entry = row?;
+            if entry.source == "rename" {
+                renamed_from = entry.old_path.clone().map(|old| (old, entry.id));
+            }
+            let stop = renamed_from.is_some();
+            entries.push(entry);
+            if stop {
+                break;
+            }
+        }
+        match renamed_from {
+            Some((old, id)) => {
+                current = old;
+                before = id - 1;
+            }
+            None => break,
+        }
+    }
+    Ok(entries)
+}
+
+/// Where the file of an entry is now, following the renames recorded after it
+pub fn current_path(conn: &Connection, entry: &HistoryEntry) -> Result<String, rusqlite::Error> {
+    let mut path = entry.path.clone();
+    let mut after = entry.id;
+    for _ in 0..MAX_RENAME_HOPS {
+        let next: Option<(String, i64)> = conn
+            .query_row(
+                "SELECT path, id FROM file_history
+                 WHERE source = 'rename' AND old_path = ?1 AND id > ?2 ORDER BY id LIMIT 1",
+                params![path, after],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match next {
+            Some((renamed, id)) => {
+                path = renamed;
+                after = id;
+            }
+            None => break,
+        }
+    }
+    Ok(path)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Option<HistoryEntry>, rusqlite::Error> {
+    let sql = format!("{} WHERE h.id = ?1", ENTRY_SELECT);
+    conn.query_row(&sql, params![id], entry_from_row).optional()
+}
+
+/// Compressed bytes of every stored object
+pub fn stored_size(conn: &Connection) -> Result<u64, rusqlite::Error> {
+    conn.query_row("SELECT COALESCE(SUM(stored_size), 0) FROM history_objects", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map(|n| n as u64)
+}
+
+/// Drop entries older than `cutoff` (millis), then the oldest ones until the objects fit in
+/// `max_bytes`. Returns the hashes of the objects no entry refers to any more; their rows
+/// are gone and the caller removes the files before committing its transaction.
+pub fn prune(conn: &Connection, cutoff: Option<i64>, max_bytes: Option<u64>) -> Result<Vec<String>, rusqlite::Error> {
+    if let Some(cutoff) = cutoff {
+        conn.execute("DELETE FROM file_history WHERE created_at < ?1", params![cutoff])?;
+    }
+    let mut orphans = delete_orphans(conn)?;
+
+    if let Some(max_bytes) = max_bytes {
+        while stored_size(conn)? > max_bytes {
+            let deleted = conn.execute(
+                "DELETE FROM file_history WHERE id IN (
+                     SELECT id FROM file_history ORDER BY created_at, id LIMIT ?1)",
+                params![PRUNE_BATCH],
+            )?;
+            orphans.extend(delete_orphans(conn)?);
+            if deleted == 0 {
+                break;
+            }
+        }
+    }
+    Ok(orphans)
+}
+
+fn delete_orphans(conn: &Connection) -> Result<Vec<String>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT hash FROM history_objects o
+         WHERE NOT EXISTS (SELECT 1 FROM file_history h WHERE h.hash = o.hash)",
+    )?;
+    let hashes = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    for hash in &hashes {
+        conn.execute("DELETE FROM history_objects WHERE hash = ?1", params![hash])?;
+    }
+    Ok(hashes)
+}
@@ -0,0 +1,451 @@
+// Import of Claude CLI session transcripts (~/.claude/projects/<project>/<session>.jsonl)
+// Each session file becomes one conversation, keyed by its session id so re-imports
+// update in place and imported conversations can be resumed with --resume.
+
+use crate::state::{ConversationMessage, MessageMetadata, ToolResult, ToolUseRequest};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+const MAX_TITLE_LEN: usize = 60;
+
+#[derive(Debug, Serialize, Default)]
+pub struct ImportSummary {
+    #[serde(rename = "sessionsScanned")]
+    pub sessions_scanned: usize,
+    pub imported: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+struct ParsedSession {
+    session_id: String,
+    project_path: String,
+    title: Option<String>,
+    messages: Vec<ConversationMessage>,
+}
+
+/// Scan every project directory under `projects_dir` and import its sessions.
+/// When `project_filter` is set, only sessions recorded in that working directory are imported.
+pub fn import_all(
+    conn: &Connection,
+    projects_dir: &Path,
+    project_filter: Option<&str>,
+) -> Result<ImportSummary, Box<dyn std::error::Error>> {
+    let mut summary = ImportSummary::default();
+
+    for project_entry in std::fs::read_dir(projects_dir)?.flatten() {
+        if !project_entry.path().is_dir() {
+            continue;
+        }
+        let Ok(files) = std::fs::read_dir(project_entry.path()) else {
+            continue;
+        };
+
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            summary.sessions_scanned += 1;
+
+            let session = match parse_session(&path) {
+                Ok(Some(s)) => s,
+                Ok(None) => {
+                    summary.skipped += 1;
+                    continue;
+                }
+                Err(e) => {
+                    summary.errors.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+
+            // Title and summary runs (commands/summaries.rs) aren't conversations
+            if crate::db::summaries::is_cli_workdir(&session.project_path) {
+                summary.skipped += 1;
+                continue;
+            }
+
+            if let Some(filter) = project_filter {
+                if session.project_path != filter {
+                    summary.skipped += 1;
+                    continue;
+                }
+            }
+
+            match store_session(conn, &session) {
+                Ok(StoreOutcome::Imported) => summary.imported += 1,
+                Ok(StoreOutcome::Updated) => summary.updated += 1,
+                Ok(StoreOutcome::Unchanged) => summary.skipped += 1,
+                Err(e) => summary.errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+enum StoreOutcome {
+    Imported,
+    Updated,
+    Unchanged,
+}
+
+fn store_session(conn: &Connection, session: &ParsedSession) -> Result<StoreOutcome, rusqlite::Error> {
+    let existing: Option<(String, i64)> = conn
+        .query_row(
+            "SELECT c.id, (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)
+             FROM conversations c WHERE c.session_id = ?1",
+            params![session.session_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    // Transcripts only grow, so a stored copy with as many messages is up to date
+    if let Some((_, count)) = &existing {
+        if *count as usize >= session.messages.len() {
+            return Ok(StoreOutcome::Unchanged);
+        }
+    }
+
+    let first_ts = session.messages.first().map(|m| m.timestamp).unwrap_or(0);
+    let last_ts = session.messages.last().map(|m| m.timestamp).unwrap_or(first_ts);
+
+    let tx = conn.unchecked_transaction()?;
+
+    let (conv_id, outcome) = match existing {
+        Some((id, _)) => {
+            tx.execute("DELETE FROM messages WHERE conversation_id = ?1", params![id])?;
+            tx.execute(
+                "UPDATE conversations SET updated_at = MAX(updated_at, ?1) WHERE id = ?2",
+                params![last_ts, id],
+            )?;
+            (id, StoreOutcome::Updated)
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            let name = session
+                .title
+                .clone()
+                .unwrap_or_else(|| "Imported CLI Session".to_string());
+            tx.execute(
+                "INSERT INTO conversations (id, project_path, name, created_at, updated_at, is_active, session_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6)",
+                params![id, session.project_path, name, first_ts, last_ts, session.session_id],
+            )?;
+            (id, StoreOutcome::Imported)
+        }
+    };
+
+    for (order, msg) in session.messages.iter().enumerate() {
+        // Message ids come from the CLI log; OR IGNORE guards against a uuid shared by two sessions
+        tx.execute(
+            "INSERT OR IGNORE INTO messages (id, conversation_id, role, content, timestamp,
+             metadata_json, tool_uses_json, tool_results_json, sort_order)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                msg.id,
+                conv_id,
+                msg.role,
+                msg.content,
+                msg.timestamp,
+                msg.metadata
+                    .as_ref()
+                    .and_then(|m| serde_json::to_string(m).ok()),
+                msg.tool_uses
+                    .as_ref()
+                    .and_then(|t| serde_json::to_string(t).ok()),
+                msg.tool_results
+                    .as_ref()
+                    .and_then(|t| serde_json::to_string(t).ok()),
+                order as i32,
+            ],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(outcome)
+}
+
+/// Parse one session log. Returns `Ok(None)` for logs with nothing worth importing
+/// (summary-only files, sidechain-only runs, empty sessions).
+fn parse_session(path: &Path) -> Result<Option<ParsedSession>, Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(path)?;
+
+    let mut session_id: Option<String> = None;
+    let mut project_path: Option<String> = None;
+    let mut title: Option<String> = None;
+    let mut messages: Vec<ConversationMessage> = Vec::new();
+    // Index of the assistant message collecting the current turn, if any
+    let mut turn: Option<usize> = None;
+    let mut turn_started_at: i64 = 0;
+    // API message the previous assistant line belonged to
+    let mut last_message_id: Option<String> = None;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Partially written trailing lines are expected while the CLI is running
+        let Ok(entry) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+
+        let entry_type = entry["type"].as_str().unwrap_or("");
+        if entry_type == "summary" {
+            if let Some(s) = entry["summary"].as_str() {
+                title = Some(truncate_title(s));
+            }
+            continue;
+        }
+        if entry_type != "user" && entry_type != "assistant" {
+            continue;
+        }
+        if entry["isSidechain"].as_bool().unwrap_or(false) || entry["isMeta"].as_bool().unwrap_or(false) {
+            continue;
+        }
+
+        if session_id.is_none() {
+            session_id = entry["sessionId"].as_str().map(|s| s.to_string());
+        }
+        if project_path.is_none() {
+            project_path = entry["cwd"].as_str().map(|s| s.to_string());
+        }
+
+        let timestamp = entry["timestamp"]
+            .as_str()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp_millis())
+            .unwrap_or(0);
+        let uuid = entry["uuid"]
+            .as_str()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let message = &entry["message"];
+
+        if entry_type == "user" {
+            let mut prompt = String::new();
+            match &message["content"] {
+                Value::String(s) => prompt.push_str(s),
+                Value::Array(blocks) => {
+                    for block in blocks {
+                        match block["type"].as_str() {
+                            Some("tool_result") => {
+                                if let Some(idx) = turn {
+                                    attach_tool_result(&mut messages[idx], block);
+                                }
+                            }
+                            Some("text") => {
+                                if let Some(t) = block["text"].as_str() {
+                                    append_text(&mut prompt, t);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            // Tool-result-only entries continue the current turn; a real prompt starts a new one
+            if !prompt.trim().is_empty() {
+                if title.is_none() {
+                    title = Some(truncate_title(&prompt));
+                }
+                messages.push(ConversationMessage {
+                    id: uuid,
+                    role: "user".to_string(),
+                    content: prompt,
+                    timestamp,
+                    metadata: None,
+                    tool_uses: None,
+                    tool_results: None,
+                });
+                turn = None;
+                turn_started_at = timestamp;
+            }
+            continue;
+        }
+
+        // Assistant entries: the CLI writes one line per content block, all belonging
+        // to the same turn, so they are folded into a single drode message.
+        let idx = match turn {
+            Some(idx) => idx,
+            None => {
+                messages.push(ConversationMessage {
+                    id: uuid,
+                    role: "assistant".to_string(),
+                    content: String::new(),
+                    timestamp,
+                    metadata: None,
+                    tool_uses: None,
+                    tool_results: None,
+                });
+                turn = Some(messages.len() - 1);
+                messages.len() - 1
+            }
+        };
+        let current = &mut messages[idx];
+
+        if let Some(blocks) = message["content"].as_array() {
+            for block in blocks {
+                match block["type"].as_str() {
+                    Some("text") => {
+                        if let Some(t) = block["text"].as_str() {
+                            append_text(&mut current.content, t);
+                        }
+                    }
+                    Some("tool_use") => {
+                        current.tool_uses.get_or_insert_with(Vec::new).push(ToolUseRequest {
+                            id: block["id"].as_str().unwrap_or("").to_string(),
+                            name: block["name"].as_str().unwrap_or("").to_string(),
+                            input: block["input"].clone(),
+                            status: "pending".to_string(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        } else if let Some(t) = message["content"].as_str() {
+            append_text(&mut current.content, t);
+        }
+
+        // Each line of an API message repeats its usage, so it is counted on the first only
+        let message_id = message["id"].as_str().map(|s| s.to_string());
+        let first_line = message_id.is_none() || message_id != last_message_id;
+        last_message_id = message_id;
+        accumulate_usage(current, &entry, first_line, timestamp - turn_started_at);
+    }
+
+    let (Some(session_id), Some(project_path)) = (session_id, project_path) else {
+        return Ok(None);
+    };
+    if messages.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(ParsedSession {
+        session_id,
+        project_path,
+        title,
+        messages,
+    }))
+}
+
+fn attach_tool_result(message: &mut ConversationMessage, block: &Value) {
+    let tool_use_id = block["tool_use_id"].as_str().unwrap_or("").to_string();
+    let is_error = block["is_error"].as_bool();
+    // Same flattening as the stream-json bridge in the frontend
+    let content = match &block["content"] {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(|c| c["text"].as_str().map(|s| s.to_string()).unwrap_or_else(|| c.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+
+    if let Some(tool) = message
+        .tool_uses
+        .as_mut()
+        .and_then(|uses| uses.iter_mut().find(|t| t.id == tool_use_id))
+    {
+        tool.status = if is_error.unwrap_or(false) { "error" } else { "completed" }.to_string();
+    }
+
+    message
+        .tool_results
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            tool_use_id.clone(),
+            ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            },
+        );
+}
+
+/// Add an assistant log line to its turn's metadata. Token counts and cost are only added
+/// when `count_usage`, i.e. once per API message.
+fn accumulate_usage(message: &mut ConversationMessage, entry: &Value, count_usage: bool, elapsed_ms: i64) {
+    let api_message = &entry["message"];
+    let metadata = message.metadata.get_or_insert_with(MessageMetadata::default);
+
+    if count_usage {
+        let usage = &api_message["usage"];
+        let add = |field: &mut Option<i64>, key: &str| {
+            if let Some(n) = usage[key].as_i64() {
+                *field = Some(field.unwrap_or(0) + n);
+            }
+        };
+        add(&mut metadata.input_tokens, "input_tokens");
+        add(&mut metadata.output_tokens, "output_tokens");
+        add(&mut metadata.cache_read_tokens, "cache_read_input_tokens");
+        add(&mut metadata.cache_creation_tokens, "cache_creation_input_tokens");
+        // Only some CLI versions log the cost
+        if let Some(cost) = entry["costUSD"].as_f64() {
+            metadata.total_cost_usd = Some(metadata.total_cost_usd.unwrap_or(0.0) + cost);
+        }
+    }
+
+    if let Some(model) = api_message["model"].as_str() {
+        metadata.model = Some(model.to_string());
+    }
+    if elapsed_ms > 0 {
+        metadata.duration_ms = Some(elapsed_ms);
+    }
+}
+
+fn append_text(target: &mut String, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if !target.is_empty() {
+        target.push_str("\n\n");
+    }
+    target.push_str(text);
+}
+
+fn truncate_title(text: &str) -> String {
+    let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("").trim();
+    if line.chars().count() <= MAX_TITLE_LEN {
+        line.to_string()
+    } else {
+        let cut: String = line.chars().take(MAX_TITLE_LEN).collect();
+        format!("{}...", cut.trim_end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_is_counted_once_per_api_message() {
+        let dir = std::env::temp_dir().join(format!("drode-import-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("s.jsonl");
+        let lines = [
+            r#"{"type":"user","uuid":"u1","sessionId":"S","cwd":"/p","timestamp":"2025-01-01T00:00:00Z","message":{"role":"user","content":"hi"}}"#,
+            r#"{"type":"assistant","uuid":"a1","sessionId":"S","cwd":"/p","timestamp":"2025-01-01T00:00:01Z","costUSD":0.5,"message":{"id":"m1","content":[{"type":"text","text":"a"}],"usage":{"input_tokens":10,"output_tokens":4}}}"#,
+            r#"{"type":"assistant","uuid":"a2","sessionId":"S","cwd":"/p","timestamp":"2025-01-01T00:00:01Z","costUSD":0.5,"message":{"id":"m1","content":[{"type":"tool_use","id":"t","name":"Read","input":{}}],"usage":{"input_tokens":10,"output_tokens":4}}}"#,
+            r#"{"type":"assistant","uuid":"a3","sessionId":"S","cwd":"/p","timestamp":"2025-01-01T00:00:02Z","costUSD":0.25,"message":{"id":"m2","content":[{"type":"text","text":"b"}],"usage":{"input_tokens":3,"output_tokens":1}}}"#,
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let session = parse_session(&path).unwrap().unwrap();
+        let metadata = session.messages[1].metadata.as_ref().unwrap();
+        assert_eq!(metadata.input_tokens, Some(13));
+        assert_eq!(metadata.output_tokens, Some(5));
+        assert_eq!(metadata.total_cost_usd, Some(0.75));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
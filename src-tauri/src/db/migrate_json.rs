@@ -40,12 +40,10 @@ pub fn migrate(
         )?;
     }
 
-    // 2. Recent projects
+    // 2. Recent projects (first = most recent)
+    let now = chrono::Utc::now().timestamp_millis();
     for (i, path) in store.recent_projects.iter().enumerate() {
-        tx.execute(
-            "INSERT OR IGNORE INTO recent_projects (path, position) VALUES (?1, ?2)",
-            params![path, i as i32],
-        )?;
+        crate::db::projects::register(&tx, path, now - i as i64)?;
     }
 
     // 3. Legacy single conversations -> "Imported Conversation" entries
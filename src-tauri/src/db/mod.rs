@@ -1,8 +1,21 @@
 pub mod schema;
+pub mod pool;
 pub mod settings;
 pub mod projects;
 pub mod conversations;
+pub mod tags;
 pub mod search;
 pub mod migrate_json;
+pub mod import_cli;
 pub mod activity;
 pub mod oauth;
+pub mod token_crypto;
+pub mod usage;
+pub mod backup;
+pub mod summaries;
+pub mod bookmarks;
+pub mod export;
+pub mod file_edits;
+pub mod replace_undo;
+pub mod file_history;
+pub mod symbols;
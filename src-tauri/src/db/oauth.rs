@@ -1,6 +1,10 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, Transaction, params};
 use serde::{Deserialize, Serialize};
+use crate::db::token_crypto::{self, TokenCipher};
+use std::path::{Path, PathBuf};
 
+// access_token / refresh_token are stored encrypted (see token_crypto.rs);
+// this struct always carries them in plaintext and must stay inside the backend.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OAuthToken {
     pub provider: String,
@@ -12,14 +16,47 @@ pub struct OAuthToken {
     pub updated_at: i64,
 }
 
-pub fn store_token(conn: &Connection, token: &OAuthToken) -> Result<(), rusqlite::Error> {
+/// Everything about a stored token except the secrets themselves
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenInfo {
+    pub provider: String,
+    #[serde(rename = "hasRefreshToken")]
+    pub has_refresh_token: bool,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<i64>,
+    pub scope: Option<String>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+// Associated data for each secret column, so ciphertexts can't be moved between rows
+fn access_aad(provider: &str) -> String {
+    format!("{}:access_token", provider)
+}
+
+fn refresh_aad(provider: &str) -> String {
+    format!("{}:refresh_token", provider)
+}
+
+pub fn store_token(
+    conn: &Connection,
+    cipher: &TokenCipher,
+    token: &OAuthToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let access_token = cipher.encrypt(&token.access_token, &access_aad(&token.provider))?;
+    let refresh_token = token
+        .refresh_token
+        .as_deref()
+        .map(|t| cipher.encrypt(t, &refresh_aad(&token.provider)))
+        .transpose()?;
+
     conn.execute(
         "INSERT OR REPLACE INTO oauth_tokens (provider, access_token, refresh_token, expires_at, scope, account_info_json, updated_at)
          VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
         params![
             token.provider,
-            token.access_token,
-            token.refresh_token,
+            access_token,
+            refresh_token,
             token.expires_at,
             token.scope,
             token.account_info_json,
@@ -29,7 +66,11 @@ pub fn store_token(conn: &Connection, token: &OAuthToken) -> Result<(), rusqlite
     Ok(())
 }
 
-pub fn get_token(conn: &Connection, provider: &str) -> Result<Option<OAuthToken>, rusqlite::Error> {
+pub fn get_token(
+    conn: &Connection,
+    cipher: &TokenCipher,
+    provider: &str,
+) -> Result<Option<OAuthToken>, Box<dyn std::error::Error>> {
     let mut stmt = conn.prepare(
         "SELECT provider, access_token, refresh_token, expires_at, scope, account_info_json, updated_at
          FROM oauth_tokens WHERE provider = ?1"
@@ -47,8 +88,49 @@ pub fn get_token(conn: &Connection, provider: &str) -> Result<Option<OAuthToken>
         })
     })?;
 
+    let Some(row) = rows.next() else {
+        return Ok(None);
+    };
+    let mut token = row?;
+    token.access_token = reveal(cipher, &token.access_token, &access_aad(provider))?;
+    token.refresh_token = token
+        .refresh_token
+        .as_deref()
+        .map(|t| reveal(cipher, t, &refresh_aad(provider)))
+        .transpose()?;
+    Ok(Some(token))
+}
+
+pub fn get_token_info(conn: &Connection, provider: &str) -> Result<Option<TokenInfo>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT provider, refresh_token IS NOT NULL, expires_at, scope, updated_at
+         FROM oauth_tokens WHERE provider = ?1"
+    )?;
+
+    let mut rows = stmt.query_map(params![provider], |row| {
+        Ok(TokenInfo {
+            provider: row.get(0)?,
+            has_refresh_token: row.get(1)?,
+            expires_at: row.get(2)?,
+            scope: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    })?;
+
     match rows.next() {
-        Some(Ok(token)) => Ok(Some(token)),
+        Some(Ok(info)) => Ok(Some(info)),
+        Some(Err(e)) => Err(e),
+        None => Ok(None),
+    }
+}
+
+/// Account details shown in the UI; not secret, so readable without the key
+pub fn get_account_info(conn: &Connection, provider: &str) -> Result<Option<Option<String>>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT account_info_json FROM oauth_tokens WHERE provider = ?1")?;
+    let mut rows = stmt.query_map(params![provider], |row| row.get(0))?;
+
+    match rows.next() {
+        Some(Ok(info)) => Ok(Some(info)),
         Some(Err(e)) => Err(e),
         None => Ok(None),
     }
@@ -71,3 +153,143 @@ pub fn list_providers(conn: &Connection) -> Result<Vec<String>, rusqlite::Error>
     }
     Ok(providers)
 }
+
+/// Encrypt rows written before tokens were encrypted. Returns how many rows changed;
+/// the caller should VACUUM afterwards so the plaintext doesn't linger in free pages.
+pub fn encrypt_plaintext_rows(conn: &Connection, cipher: &TokenCipher) -> Result<usize, Box<dyn std::error::Error>> {
+    let tx = conn.unchecked_transaction()?;
+    let changed = reencrypt_rows(&tx, |provider, value, aad| {
+        if token_crypto::is_encrypted(value) {
+            Ok(None)
+        } else {
+            cipher.encrypt(value, aad).map(Some).map_err(|e| format!("{}: {}", provider, e))
+        }
+    })?;
+    tx.commit()?;
+    Ok(changed)
+}
+
+/// Encrypt the plaintext tokens left in copies of the database: the scheduled backups in
+/// `backups_dir` and the pre-migration backups next to `conn`'s file. Run after
+/// `encrypt_plaintext_rows` changed rows, so the plaintext doesn't outlive it there.
+/// Copies that can't be opened or have no token table are left alone.
+pub fn scrub_backups(conn: &Connection, backups_dir: &Path, cipher: &TokenCipher) -> usize {
+    let mut paths: Vec<PathBuf> = crate::db::backup::list(backups_dir).into_iter().map(|b| PathBuf::from(b.path)).collect();
+    if let Some(db_path) = conn.path().filter(|p| !p.is_empty()) {
+        paths.extend(crate::db::schema::migration_backups(Path::new(db_path)));
+    }
+
+    let mut scrubbed = 0;
+    for path in paths {
+        let Ok(copy) = Connection::open(&path) else {
+            continue;
+        };
+        let has_tokens = copy
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'oauth_tokens'",
+                [],
+                |_| Ok(()),
+            )
+            .is_ok();
+        if !has_tokens {
+            continue;
+        }
+        match encrypt_plaintext_rows(&copy, cipher) {
+            Ok(n) if n > 0 => {
+                let _ = copy.execute_batch("VACUUM;");
+                scrubbed += 1;
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to encrypt OAuth tokens in {}: {}", path.display(), e),
+        }
+    }
+    scrubbed
+}
+
+/// Re-encrypt every stored secret from `old` to `new` within `tx`, which the caller
+/// commits together with the new key's settings
+pub fn rotate_key(tx: &Transaction, old: &TokenCipher, new: &TokenCipher) -> Result<usize, Box<dyn std::error::Error>> {
+    reencrypt_rows(tx, |provider, value, aad| {
+        let plaintext = reveal(old, value, aad).map_err(|e| format!("{}: {}", provider, e))?;
+        new.encrypt(&plaintext, aad).map(Some).map_err(|e| format!("{}: {}", provider, e))
+    })
+}
+
+/// `rewrite(provider, stored value, aad)` returns the new stored value, or None to keep it.
+/// Runs inside the caller's transaction.
+fn reencrypt_rows<F>(tx: &Transaction, rewrite: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    F: Fn(&str, &str, &str) -> Result<Option<String>, String>,
+{
+    let rows: Vec<(String, String, Option<String>)> = {
+        let mut stmt = tx.prepare("SELECT provider, access_token, refresh_token FROM oauth_tokens")?;
+        let mapped = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        mapped.collect::<Result<_, _>>()?
+    };
+
+    let mut changed = 0;
+    for (provider, access_token, refresh_token) in rows {
+        let new_access = rewrite(&provider, &access_token, &access_aad(&provider))?;
+        let new_refresh = match refresh_token.as_deref() {
+            Some(t) => rewrite(&provider, t, &refresh_aad(&provider))?,
+            None => None,
+        };
+        if new_access.is_none() && new_refresh.is_none() {
+            continue;
+        }
+
+        tx.execute(
+            "UPDATE oauth_tokens SET access_token = ?1, refresh_token = ?2 WHERE provider = ?3",
+            params![
+                new_access.unwrap_or(access_token),
+                new_refresh.or(refresh_token),
+                provider,
+            ],
+        )?;
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+/// Decrypt a stored value; rows not yet migrated are passed through as-is
+fn reveal(cipher: &TokenCipher, value: &str, aad: &str) -> Result<String, String> {
+    if token_crypto::is_encrypted(value) {
+        cipher.decrypt(value, aad)
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotated_tokens_decrypt_with_the_new_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::initialize(&conn).unwrap();
+        let old = TokenCipher::generate();
+        let token = OAuthToken {
+            provider: "github".to_string(),
+            access_token: "access".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: None,
+            scope: None,
+            account_info_json: None,
+            updated_at: 1,
+        };
+        store_token(&conn, &old, &token).unwrap();
+
+        // As oauth_rotate_key does: rows and key settings in one transaction
+        let new = TokenCipher::generate();
+        let tx = conn.unchecked_transaction().unwrap();
+        assert_eq!(rotate_key(&tx, &old, &new).unwrap(), 1);
+        crate::db::settings::set(&tx, token_crypto::KEY_ID_KEY, new.key_id()).unwrap();
+        tx.commit().unwrap();
+
+        let rotated = get_token(&conn, &new, "github").unwrap().unwrap();
+        assert_eq!(rotated.access_token, "access");
+        assert_eq!(rotated.refresh_token.as_deref(), Some("refresh"));
+        assert!(get_token(&conn, &old, "github").is_err());
+    }
+}
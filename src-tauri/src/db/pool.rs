@@ -0,0 +1,153 @@
+// Small SQLite connection pool. Each connection is opened on the same file in WAL
+// mode, so readers run concurrently and writers wait on busy_timeout instead of
+// every command queueing behind one mutex. Async commands use `run`, which moves
+// the work onto the blocking thread pool rather than holding a lock across awaits.
+
+use crate::error::DrodeError;
+use rusqlite::Connection;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+const DEFAULT_MAX_CONNECTIONS: usize = 4;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct DbPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    path: PathBuf,
+    max: usize,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+struct PoolState {
+    idle: Vec<Connection>,
+    open: usize,
+}
+
+/// A checked-out connection; returned to the pool on drop
+pub struct PooledConnection {
+    inner: Arc<Inner>,
+    conn: Option<Connection>,
+}
+
+impl DbPool {
+    /// Open the pool with one connection up front so a bad path fails here, not later
+    pub fn open(path: &Path) -> Result<Self, rusqlite::Error> {
+        Self::with_max_connections(path, DEFAULT_MAX_CONNECTIONS)
+    }
+
+    pub fn with_max_connections(path: &Path, max: usize) -> Result<Self, rusqlite::Error> {
+        let first = open_connection(path)?;
+        // journal_mode is persistent, so setting it once covers every later connection
+        first.execute_batch("PRAGMA journal_mode=WAL;")?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                path: path.to_path_buf(),
+                max: max.max(1),
+                state: Mutex::new(PoolState {
+                    idle: vec![first],
+                    open: 1,
+                }),
+                available: Condvar::new(),
+            }),
+        })
+    }
+
+    /// Check out a connection, opening a new one while under the limit and
+    /// otherwise waiting for one to come back
+    pub fn get(&self) -> PooledConnection {
+        let mut state = self.inner.state.lock().unwrap();
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                return self.wrap(conn);
+            }
+
+            if state.open < self.inner.max {
+                state.open += 1;
+                drop(state);
+                match open_connection(&self.inner.path) {
+                    Ok(conn) => return self.wrap(conn),
+                    Err(e) => {
+                        state = self.inner.state.lock().unwrap();
+                        state.open -= 1;
+                        if state.open == 0 {
+                            panic!("Failed to open database connection: {}", e);
+                        }
+                        eprintln!("Failed to open extra database connection: {}", e);
+                        // Fall through and wait for one of the open connections
+                    }
+                }
+            }
+
+            state = self.inner.available.wait(state).unwrap();
+        }
+    }
+
+    /// Run `f` with a pooled connection on the blocking thread pool
+    pub async fn run<F, T>(&self, f: F) -> Result<T, DrodeError>
+    where
+        F: FnOnce(&mut Connection) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut conn = pool.get();
+            f(&mut conn)
+        })
+        .await
+        .map_err(|e| DrodeError::Internal(format!("Database task failed: {}", e)))
+    }
+
+    fn wrap(&self, conn: Connection) -> PooledConnection {
+        PooledConnection {
+            inner: self.inner.clone(),
+            conn: Some(conn),
+        }
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection already returned")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection already returned")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            let mut state = self.inner.state.lock().unwrap();
+            // A caller that panicked mid-transaction must not hand an open
+            // transaction to the next user
+            if conn.is_autocommit() {
+                state.idle.push(conn);
+            } else {
+                state.open -= 1;
+            }
+            drop(state);
+            self.inner.available.notify_one();
+        }
+    }
+}
+
+fn open_connection(path: &Path) -> Result<Connection, rusqlite::Error> {
+    let conn = Connection::open(path)?;
+    // Enable cascade deletes
+    conn.execute_batch("PRAGMA foreign_keys=ON;")?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
@@ -1,63 +1,416 @@
-use rusqlite::{params, Connection};
-
-pub fn get_recent(conn: &Connection) -> Vec<String> {
-    let mut stmt = match conn.prepare(
-        "SELECT path FROM recent_projects ORDER BY position ASC",
-    ) {
-        Ok(s) => s,
-        Err(_) => return vec![],
-    };
+// Project registry. Conversations and activity are still keyed by path, so when a
+// repository is moved or renamed we recognise it by its git identity (root commit,
+// then origin remote) and re-point those rows at the new path.
 
-    let result = match stmt.query_map([], |row| row.get(0)) {
-        Ok(rows) => rows.filter_map(|r| r.ok()).collect(),
-        Err(_) => vec![],
-    };
-    result
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Project {
+    pub id: i64,
+    pub name: String,
+    pub path: String,
+    #[serde(rename = "lastOpenedAt")]
+    pub last_opened_at: Option<i64>,
+    pub pinned: bool,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    /// Detected languages / frameworks, e.g. ["rust", "node", "typescript"]
+    pub stack: Vec<String>,
+    #[serde(rename = "gitRemote")]
+    pub git_remote: Option<String>,
+    #[serde(rename = "gitRootCommit")]
+    pub git_root_commit: Option<String>,
+    /// The directory no longer exists at `path`
+    pub missing: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ProjectUpdate {
+    pub name: Option<String>,
+    pub pinned: Option<bool>,
+    /// Empty string clears the value
+    pub color: Option<String>,
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RepoIdentity {
+    pub remote: Option<String>,
+    pub root_commit: Option<String>,
+}
+
+const PROJECT_SELECT: &str = "SELECT id, name, path, last_opened_at, pinned, color, icon, stack_json,
+        git_remote, git_root_commit, created_at
+ FROM projects";
+
+fn project_from_row(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    let path: String = row.get(2)?;
+    let stack_json: Option<String> = row.get(7)?;
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        missing: !Path::new(&path).exists(),
+        path,
+        last_opened_at: row.get(3)?,
+        pinned: row.get(4)?,
+        color: row.get(5)?,
+        icon: row.get(6)?,
+        stack: stack_json
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+        git_remote: row.get(8)?,
+        git_root_commit: row.get(9)?,
+        created_at: row.get(10)?,
+    })
+}
+
+/// Pinned first, then most recently opened
+pub fn list(conn: &Connection) -> Result<Vec<Project>, rusqlite::Error> {
+    let sql = format!("{} ORDER BY pinned DESC, last_opened_at DESC, id DESC", PROJECT_SELECT);
+    let mut stmt = conn.prepare(&sql)?;
+    let result = stmt
+        .query_map([], project_from_row)?
+        .collect::<Result<_, _>>()?;
+    Ok(result)
 }
 
+pub fn get_recent(conn: &Connection) -> Result<Vec<String>, rusqlite::Error> {
+    Ok(list(conn)?.into_iter().map(|p| p.path).collect())
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Option<Project>, rusqlite::Error> {
+    let sql = format!("{} WHERE id = ?1", PROJECT_SELECT);
+    conn.query_row(&sql, params![id], project_from_row).optional()
+}
+
+pub fn get_by_path(conn: &Connection, project_path: &str) -> Result<Option<Project>, rusqlite::Error> {
+    let sql = format!("{} WHERE path = ?1", PROJECT_SELECT);
+    conn.query_row(&sql, params![project_path], project_from_row).optional()
+}
+
+/// Record that a project was opened. Unknown paths are registered, or re-linked to
+/// an existing project whose directory has gone missing but shares the same repo.
 pub fn set_current_project(
     conn: &Connection,
     project_path: &str,
-) -> Result<(), rusqlite::Error> {
-    // Remove existing entry if present
+) -> Result<Project, rusqlite::Error> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let identity = detect_identity(Path::new(project_path));
+    let stack = detect_stack(Path::new(project_path));
+
+    let id = match get_by_path(conn, project_path)? {
+        Some(existing) => existing.id,
+        None => match find_moved(conn, &identity)? {
+            Some(moved) => relink(conn, moved.id, project_path)?.id,
+            None => register(conn, project_path, now)?,
+        },
+    };
+
     conn.execute(
-        "DELETE FROM recent_projects WHERE path = ?1",
-        params![project_path],
+        "UPDATE projects SET last_opened_at = ?1, stack_json = ?2, git_remote = ?3, git_root_commit = ?4
+         WHERE id = ?5",
+        params![
+            now,
+            serde_json::to_string(&stack).unwrap_or_default(),
+            identity.remote,
+            identity.root_commit,
+            id,
+        ],
     )?;
 
-    // Shift all positions up by 1
-    conn.execute("UPDATE recent_projects SET position = position + 1", [])?;
+    get(conn, id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+}
 
-    // Insert at position 0 (most recent)
+/// Insert a bare registry entry; identity and stack are filled in when it is opened
+pub fn register(conn: &Connection, project_path: &str, last_opened_at: i64) -> Result<i64, rusqlite::Error> {
     conn.execute(
-        "INSERT INTO recent_projects (path, position) VALUES (?1, 0)",
+        "INSERT OR IGNORE INTO projects (path, name, last_opened_at, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            project_path,
+            default_name(project_path),
+            last_opened_at,
+            chrono::Utc::now().timestamp_millis(),
+        ],
+    )?;
+    conn.query_row(
+        "SELECT id FROM projects WHERE path = ?1",
         params![project_path],
+        |row| row.get(0),
+    )
+}
+
+pub fn update(conn: &Connection, id: i64, update: &ProjectUpdate) -> Result<Option<Project>, rusqlite::Error> {
+    if let Some(name) = update.name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+        conn.execute("UPDATE projects SET name = ?1 WHERE id = ?2", params![name, id])?;
+    }
+    if let Some(pinned) = update.pinned {
+        conn.execute("UPDATE projects SET pinned = ?1 WHERE id = ?2", params![pinned, id])?;
+    }
+    if let Some(color) = &update.color {
+        conn.execute(
+            "UPDATE projects SET color = NULLIF(?1, '') WHERE id = ?2",
+            params![color, id],
+        )?;
+    }
+    if let Some(icon) = &update.icon {
+        conn.execute(
+            "UPDATE projects SET icon = NULLIF(?1, '') WHERE id = ?2",
+            params![icon, id],
+        )?;
+    }
+    get(conn, id)
+}
+
+/// Point a project at a new directory and carry its path-keyed rows along
+pub fn relink(conn: &Connection, id: i64, new_path: &str) -> Result<Project, rusqlite::Error> {
+    let old_path: String = conn.query_row(
+        "SELECT path FROM projects WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
     )?;
+    if old_path == new_path {
+        return get(conn, id)?.ok_or(rusqlite::Error::QueryReturnedNoRows);
+    }
 
-    // Remove entries beyond 10
-    conn.execute(
-        "DELETE FROM recent_projects WHERE position >= 10",
-        [],
+    let tx = conn.unchecked_transaction()?;
+    // Fails on the UNIQUE constraint if new_path is already a separate project
+    tx.execute("UPDATE projects SET path = ?1 WHERE id = ?2", params![new_path, id])?;
+    tx.execute(
+        "UPDATE conversations SET project_path = ?1 WHERE project_path = ?2",
+        params![new_path, old_path],
+    )?;
+    tx.execute(
+        "UPDATE activity_events SET project_path = ?1 WHERE project_path = ?2",
+        params![new_path, old_path],
+    )?;
+    tx.execute(
+        "UPDATE settings SET value = ?1 WHERE key = 'current_project' AND value = ?2",
+        params![new_path, old_path],
+    )?;
+    // Undo of a replace rewrites files by absolute path, so those move with the project
+    tx.execute(
+        "UPDATE replace_undo_files SET path = ?1 || substr(path, length(?2) + 1)
+         WHERE undo_id IN (SELECT id FROM replace_undo WHERE project_path = ?2)
+           AND substr(path, 1, length(?2) + 1) = ?2 || '/'",
+        params![new_path, old_path],
     )?;
+    tx.execute(
+        "UPDATE replace_undo SET project_path = ?1 WHERE project_path = ?2",
+        params![new_path, old_path],
+    )?;
+    // The symbol index is keyed by absolute paths too. symbols points at symbol_files, so
+    // the check waits for the commit, when both sides have moved.
+    tx.execute_batch("PRAGMA defer_foreign_keys = ON;")?;
+    for table in ["symbol_files", "symbols"] {
+        tx.execute(
+            &format!(
+                "UPDATE {} SET project_path = ?1, path = ?1 || substr(path, length(?2) + 1)
+                 WHERE project_path = ?2 AND substr(path, 1, length(?2) + 1) = ?2 || '/'",
+                table
+            ),
+            params![new_path, old_path],
+        )?;
+    }
+    // Anything outside the old root can't be moved along; the next sync re-parses it
+    tx.execute("DELETE FROM symbol_files WHERE project_path = ?1", params![old_path])?;
+    tx.commit()?;
 
-    Ok(())
+    get(conn, id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
 pub fn remove(conn: &Connection, project_path: &str) -> Result<Vec<String>, rusqlite::Error> {
     conn.execute(
-        "DELETE FROM recent_projects WHERE path = ?1",
+        "DELETE FROM projects WHERE path = ?1",
         params![project_path],
     )?;
 
-    // Re-normalize positions
-    let paths = get_recent(conn);
-    conn.execute("DELETE FROM recent_projects", [])?;
-    for (i, path) in paths.iter().enumerate() {
+    get_recent(conn)
+}
+
+/// Fill in identity and stack for projects carried over from the old recent list,
+/// so they can be recognised if they are moved before being opened again
+pub fn backfill_identities(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let pending: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, path FROM projects WHERE stack_json IS NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_, _>>()?
+    };
+
+    for (id, path) in pending {
+        let dir = Path::new(&path);
+        if !dir.is_dir() {
+            continue;
+        }
+        let identity = detect_identity(dir);
         conn.execute(
-            "INSERT INTO recent_projects (path, position) VALUES (?1, ?2)",
-            params![path, i as i32],
+            "UPDATE projects SET stack_json = ?1, git_remote = ?2, git_root_commit = ?3 WHERE id = ?4",
+            params![
+                serde_json::to_string(&detect_stack(dir)).unwrap_or_default(),
+                identity.remote,
+                identity.root_commit,
+                id,
+            ],
         )?;
     }
+    Ok(())
+}
+
+// Per-project settings namespace
+
+pub fn get_setting(conn: &Connection, project_id: i64, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM project_settings WHERE project_id = ?1 AND key = ?2",
+        params![project_id, key],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+pub fn set_setting(conn: &Connection, project_id: i64, key: &str, value: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT OR REPLACE INTO project_settings (project_id, key, value) VALUES (?1, ?2, ?3)",
+        params![project_id, key, value],
+    )?;
+    Ok(())
+}
+
+pub fn remove_setting(conn: &Connection, project_id: i64, key: &str) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "DELETE FROM project_settings WHERE project_id = ?1 AND key = ?2",
+        params![project_id, key],
+    )?;
+    Ok(())
+}
+
+pub fn list_settings(conn: &Connection, project_id: i64) -> Result<HashMap<String, String>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT key, value FROM project_settings WHERE project_id = ?1")?;
+    let result = stmt
+        .query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    Ok(result)
+}
+
+// Detection
+
+/// A missing project that looks like the same repository. Projects that still exist
+/// are never matched, so two clones of one repo stay separate; a differing root
+/// commit rules out a remote-only match.
+fn find_moved(conn: &Connection, identity: &RepoIdentity) -> Result<Option<Project>, rusqlite::Error> {
+    if identity.remote.is_none() && identity.root_commit.is_none() {
+        return Ok(None);
+    }
+
+    let sql = format!(
+        "{} WHERE git_root_commit = ?1 OR git_remote = ?2 ORDER BY last_opened_at DESC",
+        PROJECT_SELECT
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let candidates: Vec<Project> = stmt
+        .query_map(params![identity.root_commit, identity.remote], project_from_row)?
+        .collect::<Result<_, _>>()?;
+
+    Ok(candidates.into_iter().find(|p| {
+        let root_conflict = matches!(
+            (&p.git_root_commit, &identity.root_commit),
+            (Some(a), Some(b)) if a != b
+        );
+        p.missing && !root_conflict
+    }))
+}
+
+pub fn detect_identity(dir: &Path) -> RepoIdentity {
+    if !dir.join(".git").exists() {
+        return RepoIdentity::default();
+    }
+
+    RepoIdentity {
+        remote: git_output(dir, &["config", "--get", "remote.origin.url"])
+            .map(|url| normalize_remote(&url)),
+        // Repos with several roots (merged histories) report each; the first is stable
+        root_commit: git_output(dir, &["rev-list", "--max-parents=0", "HEAD"])
+            .and_then(|out| out.lines().next().map(|l| l.to_string())),
+    }
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// `git@github.com:o/r.git`, `https://user@github.com/o/r` -> `github.com/o/r`
+fn normalize_remote(url: &str) -> String {
+    let mut s = url.trim();
+    if let Some(i) = s.find("://") {
+        s = &s[i + 3..];
+    }
+    if let Some(i) = s.find('@') {
+        s = &s[i + 1..];
+    }
+    let s = s.trim_end_matches('/').trim_end_matches(".git");
+    // scp-like syntax uses ':' between host and path
+    let s = match s.split_once(':') {
+        Some((host, rest)) if !rest.starts_with(|c: char| c.is_ascii_digit()) => format!("{}/{}", host, rest),
+        _ => s.to_string(),
+    };
+    match s.split_once('/') {
+        Some((host, rest)) => format!("{}/{}", host.to_lowercase(), rest),
+        None => s.to_lowercase(),
+    }
+}
+
+pub fn detect_stack(dir: &Path) -> Vec<String> {
+    const MARKERS: &[(&str, &str)] = &[
+        ("Cargo.toml", "rust"),
+        ("package.json", "node"),
+        ("tsconfig.json", "typescript"),
+        ("deno.json", "deno"),
+        ("pyproject.toml", "python"),
+        ("requirements.txt", "python"),
+        ("go.mod", "go"),
+        ("Gemfile", "ruby"),
+        ("pom.xml", "java"),
+        ("build.gradle", "java"),
+        ("build.gradle.kts", "kotlin"),
+        ("Package.swift", "swift"),
+        ("composer.json", "php"),
+        ("mix.exs", "elixir"),
+        ("CMakeLists.txt", "cmake"),
+        ("Dockerfile", "docker"),
+        ("src-tauri/tauri.conf.json", "tauri"),
+        ("next.config.js", "nextjs"),
+        ("next.config.mjs", "nextjs"),
+        ("vite.config.ts", "vite"),
+        ("vite.config.js", "vite"),
+        ("supabase/config.toml", "supabase"),
+        ("vercel.json", "vercel"),
+    ];
+
+    let mut stack: Vec<String> = Vec::new();
+    for (marker, tech) in MARKERS {
+        if dir.join(marker).exists() && !stack.iter().any(|s| s == tech) {
+            stack.push(tech.to_string());
+        }
+    }
+    stack
+}
 
-    Ok(get_recent(conn))
+fn default_name(project_path: &str) -> String {
+    Path::new(project_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| project_path.to_string())
 }
@@ -0,0 +1,125 @@
+// Undo snapshots of project-wide replaces (schema version 11). Each one keeps the full
+// original text of every file the replace rewrote; only the most recent few per project
+// are kept.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+const KEEP_PER_PROJECT: i64 = 20;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplaceUndo {
+    pub id: String,
+    #[serde(rename = "projectPath")]
+    pub project_path: String,
+    pub query: String,
+    pub replacement: String,
+    #[serde(rename = "fileCount")]
+    pub file_count: i64,
+    #[serde(rename = "editCount")]
+    pub edit_count: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UndoFile {
+    pub path: String,
+    pub original: String,
+    /// SHA-256 of the content the replace wrote
+    pub replaced_hash: String,
+}
+
+const UNDO_SELECT: &str = "SELECT u.id, u.project_path, u.query, u.replacement,
+        (SELECT COUNT(*) FROM replace_undo_files f WHERE f.undo_id = u.id),
+        u.edit_count, u.created_at
+    FROM replace_undo u";
+
+fn undo_from_row(row: &rusqlite::Row) -> Result<ReplaceUndo, rusqlite::Error> {
+    Ok(ReplaceUndo {
+        id: row.get(0)?,
+        project_path: row.get(1)?,
+        query: row.get(2)?,
+        replacement: row.get(3)?,
+        file_count: row.get(4)?,
+        edit_count: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+pub fn record(
+    conn: &Connection,
+    project_path: &str,
+    query: &str,
+    replacement: &str,
+    edit_count: usize,
+    files: &[UndoFile],
+) -> Result<String, rusqlite::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO replace_undo (id, project_path, query, replacement, edit_count, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, project_path, query, replacement, edit_count as i64, now],
+    )?;
+    for file in files {
+        tx.execute(
+            "INSERT INTO replace_undo_files (undo_id, path, original, replaced_hash) VALUES (?1, ?2, ?3, ?4)",
+            params![id, file.path, file.original, file.replaced_hash],
+        )?;
+    }
+    tx.execute(
+        "DELETE FROM replace_undo WHERE project_path = ?1 AND id NOT IN (
+             SELECT id FROM replace_undo WHERE project_path = ?1
+             ORDER BY created_at DESC, rowid DESC LIMIT ?2)",
+        params![project_path, KEEP_PER_PROJECT],
+    )?;
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Newest first
+pub fn list(conn: &Connection, project_path: &str) -> Result<Vec<ReplaceUndo>, rusqlite::Error> {
+    let sql = format!(
+        "{} WHERE u.project_path = ?1 ORDER BY u.created_at DESC, u.rowid DESC",
+        UNDO_SELECT
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![project_path], undo_from_row)?;
+
+    let mut undos = Vec::new();
+    for row in rows {
+        undos.push(row?);
+    }
+    Ok(undos)
+}
+
+pub fn get(conn: &Connection, id: &str) -> Result<Option<ReplaceUndo>, rusqlite::Error> {
+    let sql = format!("{} WHERE u.id = ?1", UNDO_SELECT);
+    conn.query_row(&sql, params![id], undo_from_row).optional()
+}
+
+pub fn files(conn: &Connection, id: &str) -> Result<Vec<UndoFile>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT path, original, replaced_hash FROM replace_undo_files WHERE undo_id = ?1 ORDER BY path",
+    )?;
+    let rows = stmt.query_map(params![id], |row| {
+        Ok(UndoFile {
+            path: row.get(0)?,
+            original: row.get(1)?,
+            replaced_hash: row.get(2)?,
+        })
+    })?;
+
+    let mut files = Vec::new();
+    for row in rows {
+        files.push(row?);
+    }
+    Ok(files)
+}
+
+pub fn remove(conn: &Connection, id: &str) -> Result<(), rusqlite::Error> {
+    conn.execute("DELETE FROM replace_undo WHERE id = ?1", params![id])?;
+    Ok(())
+}
@@ -1,14 +1,22 @@
-use rusqlite::Connection;
+use rusqlite::{params, Connection, DatabaseName};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
-pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
-    conn.execute_batch(
-        "
-        -- Schema version tracking for future migrations
-        CREATE TABLE IF NOT EXISTS schema_version (
-            version INTEGER PRIMARY KEY,
-            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
-        );
+// Ordered schema migrations. Each entry is applied once, inside its own
+// transaction, and recorded in schema_version together with its name.
+// Append new migrations at the end; never edit or reorder applied ones.
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
 
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: "
         -- Key-value settings
         CREATE TABLE IF NOT EXISTS settings (
             key TEXT PRIMARY KEY NOT NULL,
@@ -84,51 +92,670 @@ pub fn initialize(conn: &Connection) -> Result<(), rusqlite::Error> {
                 VALUES('delete', old.rowid, old.content);
             INSERT INTO messages_fts(rowid, content) VALUES (new.rowid, new.content);
         END;
+        ",
+    },
+    Migration {
+        version: 2,
+        name: "activity_events_and_oauth_tokens",
+        sql: "
+        CREATE TABLE IF NOT EXISTS activity_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            event_id TEXT NOT NULL UNIQUE,
+            project_path TEXT NOT NULL,
+            category TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            title TEXT NOT NULL,
+            detail_json TEXT,
+            severity TEXT NOT NULL DEFAULT 'info',
+            source_id TEXT,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_activity_project ON activity_events(project_path, created_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_activity_category ON activity_events(category, created_at DESC);
 
-        -- Record schema version
-        INSERT OR IGNORE INTO schema_version (version) VALUES (1);
+        CREATE TABLE IF NOT EXISTS oauth_tokens (
+            provider TEXT PRIMARY KEY NOT NULL,
+            access_token TEXT NOT NULL,
+            refresh_token TEXT,
+            expires_at INTEGER,
+            scope TEXT,
+            account_info_json TEXT,
+            updated_at INTEGER NOT NULL
+        );
         ",
-    )?;
+    },
+    Migration {
+        version: 3,
+        name: "conversation_session_id",
+        sql: "
+        ALTER TABLE conversations ADD COLUMN session_id TEXT;
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_conversations_session
+            ON conversations(session_id) WHERE session_id IS NOT NULL;
+        ",
+    },
+    Migration {
+        version: 4,
+        name: "message_usage_columns",
+        // Virtual generated columns stay in sync with every existing insert path
+        sql: "
+        ALTER TABLE messages ADD COLUMN model TEXT
+            GENERATED ALWAYS AS (json_extract(metadata_json, '$.model')) VIRTUAL;
+        ALTER TABLE messages ADD COLUMN input_tokens INTEGER
+            GENERATED ALWAYS AS (json_extract(metadata_json, '$.inputTokens')) VIRTUAL;
+        ALTER TABLE messages ADD COLUMN output_tokens INTEGER
+            GENERATED ALWAYS AS (json_extract(metadata_json, '$.outputTokens')) VIRTUAL;
+        ALTER TABLE messages ADD COLUMN cache_read_tokens INTEGER
+            GENERATED ALWAYS AS (json_extract(metadata_json, '$.cacheReadTokens')) VIRTUAL;
+        ALTER TABLE messages ADD COLUMN cache_creation_tokens INTEGER
+            GENERATED ALWAYS AS (json_extract(metadata_json, '$.cacheCreationTokens')) VIRTUAL;
+        ALTER TABLE messages ADD COLUMN cost_usd REAL
+            GENERATED ALWAYS AS (json_extract(metadata_json, '$.totalCostUsd')) VIRTUAL;
+
+        CREATE INDEX IF NOT EXISTS idx_messages_model
+            ON messages(model, timestamp) WHERE metadata_json IS NOT NULL;
+        CREATE INDEX IF NOT EXISTS idx_messages_usage
+            ON messages(conversation_id, timestamp, input_tokens, output_tokens,
+                        cache_read_tokens, cache_creation_tokens, cost_usd)
+            WHERE metadata_json IS NOT NULL;
+        ",
+    },
+    Migration {
+        version: 5,
+        name: "conversation_tags_pinned_archived",
+        sql: "
+        ALTER TABLE conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE conversations ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+
+        CREATE INDEX IF NOT EXISTS idx_conversations_listing
+            ON conversations(project_path, archived, pinned DESC, updated_at DESC, id DESC);
+
+        CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+
+        CREATE TABLE IF NOT EXISTS conversation_tags (
+            conversation_id TEXT NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (conversation_id, tag_id),
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_conversation_tags_tag ON conversation_tags(tag_id);
+        ",
+    },
+    Migration {
+        version: 6,
+        name: "conversation_trash",
+        sql: "
+        ALTER TABLE conversations ADD COLUMN deleted_at INTEGER;
+        CREATE INDEX IF NOT EXISTS idx_conversations_deleted
+            ON conversations(deleted_at) WHERE deleted_at IS NOT NULL;
+        ",
+    },
+    Migration {
+        version: 7,
+        name: "project_registry",
+        sql: "
+        -- Replaces recent_projects. stack_json stays NULL until the directory has
+        -- been inspected; git_* identify the repo so moved directories can be re-linked.
+        CREATE TABLE IF NOT EXISTS projects (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL,
+            last_opened_at INTEGER,
+            pinned INTEGER NOT NULL DEFAULT 0,
+            color TEXT,
+            icon TEXT,
+            stack_json TEXT,
+            git_remote TEXT,
+            git_root_commit TEXT,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_projects_order
+            ON projects(pinned DESC, last_opened_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_projects_root_commit
+            ON projects(git_root_commit) WHERE git_root_commit IS NOT NULL;
+
+        CREATE TABLE IF NOT EXISTS project_settings (
+            project_id INTEGER NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (project_id, key),
+            FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+        );
+
+        -- Carry recent projects over, keeping their order (position 0 = most recent).
+        -- The name is the last path component.
+        INSERT OR IGNORE INTO projects (path, name, last_opened_at, created_at)
+            SELECT path,
+                   COALESCE(NULLIF(replace(path, rtrim(path, replace(path, '/', '')), ''), ''), path),
+                   CAST(strftime('%s', 'now') AS INTEGER) * 1000 - position,
+                   CAST(strftime('%s', 'now') AS INTEGER) * 1000
+            FROM recent_projects;
+        DROP TABLE recent_projects;
+        ",
+    },
+    Migration {
+        version: 8,
+        name: "conversation_summaries",
+        sql: "
+        -- title_source: NULL while the name is a placeholder, 'user' once renamed by hand,
+        -- 'auto' when generated. summary_message_count is the message count the summary covers.
+        ALTER TABLE conversations ADD COLUMN summary TEXT;
+        ALTER TABLE conversations ADD COLUMN summary_message_count INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE conversations ADD COLUMN title_source TEXT;
+        ALTER TABLE conversations ADD COLUMN is_legacy INTEGER NOT NULL DEFAULT 0;
+
+        -- The legacy conversation was found by its name; flag it so it can be renamed
+        UPDATE conversations SET is_legacy = 1, name = 'Conversation' WHERE name = '__legacy__';
+        UPDATE conversations SET title_source = 'user'
+            WHERE is_legacy = 0 AND name NOT IN ('New Conversation', 'Imported Conversation');
+
+        -- Tokens spent generating titles and summaries; counted in the usage stats
+        CREATE TABLE IF NOT EXISTS generation_usage (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conversation_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            model TEXT,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL NOT NULL DEFAULT 0,
+            timestamp INTEGER NOT NULL,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_generation_usage_conversation
+            ON generation_usage(conversation_id, timestamp);
+        ",
+    },
+    Migration {
+        version: 9,
+        name: "message_bookmarks",
+        sql: "
+        -- Keyed by message id without a foreign key: saving a conversation rewrites its
+        -- messages, so bookmarks of messages that disappear are removed by save_messages.
+        -- range_start/range_end are UTF-16 offsets into the message content (NULL = whole
+        -- message); quote keeps the highlighted text as it was when the bookmark was made.
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conversation_id TEXT NOT NULL,
+            message_id TEXT NOT NULL,
+            note TEXT NOT NULL DEFAULT '',
+            color TEXT NOT NULL DEFAULT 'yellow',
+            range_start INTEGER,
+            range_end INTEGER,
+            quote TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_bookmarks_conversation ON bookmarks(conversation_id, message_id);
+        CREATE INDEX IF NOT EXISTS idx_bookmarks_message ON bookmarks(message_id);
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS bookmarks_fts USING fts5(
+            note,
+            quote,
+            content=bookmarks,
+            content_rowid=id,
+            tokenize='porter unicode61'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS bookmarks_ai AFTER INSERT ON bookmarks BEGIN
+            INSERT INTO bookmarks_fts(rowid, note, quote) VALUES (new.id, new.note, new.quote);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS bookmarks_ad AFTER DELETE ON bookmarks BEGIN
+            INSERT INTO bookmarks_fts(bookmarks_fts, rowid, note, quote)
+                VALUES('delete', old.id, old.note, old.quote);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS bookmarks_au AFTER UPDATE ON bookmarks BEGIN
+            INSERT INTO bookmarks_fts(bookmarks_fts, rowid, note, quote)
+                VALUES('delete', old.id, old.note, old.quote);
+            INSERT INTO bookmarks_fts(rowid, note, quote) VALUES (new.id, new.note, new.quote);
+        END;
+        ",
+    },
+    Migration {
+        version: 10,
+        name: "file_edits",
+        sql: "
+        -- Edit/Write/MultiEdit tool uses pulled out of messages.tool_uses_json, one row per
+        -- change (seq orders the edits of a MultiEdit). For Write, new_string is the whole file.
+        -- Kept in sync by triggers like messages_fts; messages are only ever deleted and
+        -- re-inserted, never updated in place.
+        CREATE TABLE IF NOT EXISTS file_edits (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conversation_id TEXT NOT NULL,
+            message_id TEXT NOT NULL,
+            tool_use_id TEXT NOT NULL,
+            seq INTEGER NOT NULL DEFAULT 0,
+            tool TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            old_string TEXT,
+            new_string TEXT,
+            replace_all INTEGER NOT NULL DEFAULT 0,
+            status TEXT,
+            timestamp INTEGER NOT NULL,
+            FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_file_edits_path ON file_edits(file_path, timestamp);
+        CREATE INDEX IF NOT EXISTS idx_file_edits_message ON file_edits(message_id);
+        CREATE INDEX IF NOT EXISTS idx_file_edits_conversation ON file_edits(conversation_id, timestamp);
+
+        -- Malformed JSON is skipped instead of failing the message insert
+        CREATE TRIGGER IF NOT EXISTS file_edits_ai AFTER INSERT ON messages
+        WHEN new.tool_uses_json IS NOT NULL BEGIN
+            INSERT INTO file_edits (conversation_id, message_id, tool_use_id, seq, tool, file_path,
+                                    old_string, new_string, replace_all, status, timestamp)
+            SELECT new.conversation_id, new.id, json_extract(t.value, '$.id'), 0,
+                   json_extract(t.value, '$.name'), json_extract(t.value, '$.input.file_path'),
+                   json_extract(t.value, '$.input.old_string'),
+                   CASE json_extract(t.value, '$.name')
+                       WHEN 'Write' THEN json_extract(t.value, '$.input.content')
+                       ELSE json_extract(t.value, '$.input.new_string') END,
+                   COALESCE(json_extract(t.value, '$.input.replace_all'), 0),
+                   json_extract(t.value, '$.status'), new.timestamp
+            FROM json_each(CASE WHEN json_valid(new.tool_uses_json) THEN new.tool_uses_json ELSE '[]' END) t
+            WHERE json_extract(t.value, '$.name') IN ('Edit', 'Write')
+              AND json_extract(t.value, '$.input.file_path') IS NOT NULL;
+
+            INSERT INTO file_edits (conversation_id, message_id, tool_use_id, seq, tool, file_path,
+                                    old_string, new_string, replace_all, status, timestamp)
+            SELECT new.conversation_id, new.id, json_extract(t.value, '$.id'), e.key, 'MultiEdit',
+                   json_extract(t.value, '$.input.file_path'),
+                   json_extract(e.value, '$.old_string'), json_extract(e.value, '$.new_string'),
+                   COALESCE(json_extract(e.value, '$.replace_all'), 0),
+                   json_extract(t.value, '$.status'), new.timestamp
+            FROM json_each(CASE WHEN json_valid(new.tool_uses_json) THEN new.tool_uses_json ELSE '[]' END) t,
+                 json_each(t.value, '$.input.edits') e
+            WHERE json_extract(t.value, '$.name') = 'MultiEdit'
+              AND json_extract(t.value, '$.input.file_path') IS NOT NULL;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS file_edits_ad AFTER DELETE ON messages
+        WHEN old.tool_uses_json IS NOT NULL BEGIN
+            DELETE FROM file_edits WHERE message_id = old.id;
+        END;
+
+        -- Index the history that is already there
+        INSERT INTO file_edits (conversation_id, message_id, tool_use_id, seq, tool, file_path,
+                                old_string, new_string, replace_all, status, timestamp)
+        SELECT m.conversation_id, m.id, json_extract(t.value, '$.id'), 0,
+               json_extract(t.value, '$.name'), json_extract(t.value, '$.input.file_path'),
+               json_extract(t.value, '$.input.old_string'),
+               CASE json_extract(t.value, '$.name')
+                   WHEN 'Write' THEN json_extract(t.value, '$.input.content')
+                   ELSE json_extract(t.value, '$.input.new_string') END,
+               COALESCE(json_extract(t.value, '$.input.replace_all'), 0),
+               json_extract(t.value, '$.status'), m.timestamp
+        FROM messages m,
+             json_each(CASE WHEN json_valid(m.tool_uses_json) THEN m.tool_uses_json ELSE '[]' END) t
+        WHERE m.tool_uses_json IS NOT NULL
+          AND json_extract(t.value, '$.name') IN ('Edit', 'Write')
+          AND json_extract(t.value, '$.input.file_path') IS NOT NULL;
+
+        INSERT INTO file_edits (conversation_id, message_id, tool_use_id, seq, tool, file_path,
+                                old_string, new_string, replace_all, status, timestamp)
+        SELECT m.conversation_id, m.id, json_extract(t.value, '$.id'), e.key, 'MultiEdit',
+               json_extract(t.value, '$.input.file_path'),
+               json_extract(e.value, '$.old_string'), json_extract(e.value, '$.new_string'),
+               COALESCE(json_extract(e.value, '$.replace_all'), 0),
+               json_extract(t.value, '$.status'), m.timestamp
+        FROM messages m,
+             json_each(CASE WHEN json_valid(m.tool_uses_json) THEN m.tool_uses_json ELSE '[]' END) t,
+             json_each(t.value, '$.input.edits') e
+        WHERE m.tool_uses_json IS NOT NULL
+          AND json_extract(t.value, '$.name') = 'MultiEdit'
+          AND json_extract(t.value, '$.input.file_path') IS NOT NULL;
+        ",
+    },
+    Migration {
+        version: 11,
+        name: "replace_undo",
+        sql: "
+        -- What project-wide replaces overwrote, so a replace can be reverted as a whole.
+        -- replaced_hash is the SHA-256 of what the replace wrote; undo refuses to touch
+        -- a file that no longer matches it.
+        CREATE TABLE IF NOT EXISTS replace_undo (
+            id TEXT PRIMARY KEY,
+            project_path TEXT NOT NULL,
+            query TEXT NOT NULL,
+            replacement TEXT NOT NULL,
+            edit_count INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_replace_undo_project ON replace_undo(project_path, created_at);
+
+        CREATE TABLE IF NOT EXISTS replace_undo_files (
+            undo_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            original TEXT NOT NULL,
+            replaced_hash TEXT NOT NULL,
+            PRIMARY KEY (undo_id, path),
+            FOREIGN KEY (undo_id) REFERENCES replace_undo(id) ON DELETE CASCADE
+        );
+        ",
+    },
+    Migration {
+        version: 12,
+        name: "file_history",
+        sql: "
+        -- Local history: a snapshot of a file each time drode saves, renames or deletes it.
+        -- Content lives in compressed objects named by its SHA-256 in the history folder;
+        -- history_objects tracks them so retention can drop the unreferenced ones.
+        CREATE TABLE IF NOT EXISTS history_objects (
+            hash TEXT PRIMARY KEY,
+            size INTEGER NOT NULL,
+            stored_size INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS file_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            old_path TEXT,
+            source TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (hash) REFERENCES history_objects(hash)
+        );
+        CREATE INDEX IF NOT EXISTS idx_file_history_path ON file_history(path, created_at);
+        CREATE INDEX IF NOT EXISTS idx_file_history_hash ON file_history(hash);
+        ",
+    },
+    Migration {
+        version: 13,
+        name: "symbols",
+        sql: "
+        -- Symbol index for @symbol mentions and outlines, filled by symbol_index.rs.
+        -- symbol_files remembers the mtime and size each file was parsed at, so only
+        -- files that changed are parsed again.
+        CREATE TABLE IF NOT EXISTS symbol_files (
+            project_path TEXT NOT NULL,
+            path TEXT NOT NULL,
+            mtime INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            PRIMARY KEY (project_path, path)
+        );
 
-    // Version 2 migration: activity_events + oauth_tokens
-    let version: i32 = conn.query_row(
+        CREATE TABLE IF NOT EXISTS symbols (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_path TEXT NOT NULL,
+            path TEXT NOT NULL,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            container TEXT,
+            exported INTEGER NOT NULL DEFAULT 0,
+            name_line INTEGER NOT NULL,
+            name_column INTEGER NOT NULL,
+            start_line INTEGER NOT NULL,
+            end_line INTEGER NOT NULL,
+            FOREIGN KEY (project_path, path) REFERENCES symbol_files(project_path, path) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_symbols_name ON symbols(project_path, name COLLATE NOCASE);
+        CREATE INDEX IF NOT EXISTS idx_symbols_file ON symbols(project_path, path);
+        ",
+    },
+    Migration {
+        version: 14,
+        name: "summary_attempts",
+        sql: "
+        -- Message count at the last summary run that produced nothing usable, so automatic
+        -- runs back off until refresh_every more messages arrive. NULL after a success.
+        ALTER TABLE conversations ADD COLUMN summary_failed_at_count INTEGER;
+        ",
+    },
+];
+
+#[derive(Debug)]
+pub struct MigrationError {
+    pub version: i32,
+    pub name: &'static str,
+    pub source: rusqlite::Error,
+    /// Copy of the database taken before any pending migration ran
+    pub backup_path: Option<PathBuf>,
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "migration {} ({}) failed: {}",
+            self.version, self.name, self.source
+        )?;
+        if let Some(ref path) = self.backup_path {
+            write!(f, "; pre-migration backup at {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MigrationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(source: rusqlite::Error) -> Self {
+        MigrationError {
+            version: 0,
+            name: "bootstrap",
+            source,
+            backup_path: None,
+        }
+    }
+}
+
+pub fn head_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> Result<i32, rusqlite::Error> {
+    conn.query_row(
         "SELECT COALESCE(MAX(version), 0) FROM schema_version",
         [],
         |row| row.get(0),
+    )
+}
+
+/// Bring the database up to the latest schema
+pub fn initialize(conn: &Connection) -> Result<(), MigrationError> {
+    migrate_to(conn, head_version())
+}
+
+/// Apply every pending migration up to and including `target`.
+/// A failing migration is rolled back, leaving the database at the last good version.
+pub fn migrate_to(conn: &Connection, target: i32) -> Result<(), MigrationError> {
+    bootstrap_version_table(conn)?;
+
+    let current = current_version(conn)?;
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current && m.version <= target)
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    // Fresh databases have nothing worth keeping
+    let backup_path = if current > 0 {
+        backup_before_migration(conn, current).map_err(|source| MigrationError {
+            version: pending[0].version,
+            name: pending[0].name,
+            source,
+            backup_path: None,
+        })?
+    } else {
+        None
+    };
+
+    for migration in pending {
+        let fail = |source: rusqlite::Error| MigrationError {
+            version: migration.version,
+            name: migration.name,
+            source,
+            backup_path: backup_path.clone(),
+        };
+
+        // Dropping the transaction on error rolls the migration back
+        let tx = conn.unchecked_transaction().map_err(fail)?;
+        tx.execute_batch(migration.sql).map_err(fail)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_version (version, name) VALUES (?1, ?2)",
+            params![migration.version, migration.name],
+        )
+        .map_err(fail)?;
+        tx.commit().map_err(fail)?;
+    }
+
+    Ok(())
+}
+
+fn bootstrap_version_table(conn: &Connection) -> Result<(), rusqlite::Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )?;
+
+    // Databases created before the registry existed lack the name column
+    let has_name: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('schema_version') WHERE name = 'name'",
+        [],
+        |row| row.get(0),
     )?;
+    if !has_name {
+        conn.execute_batch("ALTER TABLE schema_version ADD COLUMN name TEXT;")?;
+    }
+    Ok(())
+}
+
+/// Online copy of the database next to it, e.g. `drode.db.pre-v6.bak`.
+/// In-memory databases have no file to protect and return `None`.
+fn backup_before_migration(conn: &Connection, current: i32) -> Result<Option<PathBuf>, rusqlite::Error> {
+    let db_path = match conn.path() {
+        Some(p) if !p.is_empty() => PathBuf::from(p),
+        _ => return Ok(None),
+    };
 
-    if version < 2 {
+    let mut backup_name = db_path.file_name().unwrap_or_default().to_os_string();
+    backup_name.push(format!(".pre-v{}.bak", current));
+    let backup_path = db_path.with_file_name(backup_name);
+
+    conn.backup(DatabaseName::Main, &backup_path, None)?;
+    rotate_migration_backups(&db_path, crate::db::backup::keep_count(conn));
+    Ok(Some(backup_path))
+}
+
+/// Keep the newest `keep` pre-migration backups, as the scheduled ones are (db/backup.rs),
+/// but always the one just made
+fn rotate_migration_backups(db_path: &Path, keep: usize) {
+    for path in migration_backups(db_path).into_iter().skip(keep) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// The `{name}.pre-vN.bak` copies next to the database, newest version first
+pub fn migration_backups(db_path: &Path) -> Vec<PathBuf> {
+    let Some(name) = db_path.file_name() else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.pre-v", name.to_string_lossy());
+    let dir = match db_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<(i32, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let version = file_name.strip_prefix(&prefix)?.strip_suffix(".bak")?.parse().ok()?;
+            Some((version, entry.path()))
+        })
+        .collect();
+    backups.sort_by_key(|(version, _)| std::cmp::Reverse(*version));
+    backups.into_iter().map(|(_, path)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(conn: &Connection) {
         conn.execute_batch(
-            "
-            CREATE TABLE IF NOT EXISTS activity_events (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                event_id TEXT NOT NULL UNIQUE,
-                project_path TEXT NOT NULL,
-                category TEXT NOT NULL,
-                event_type TEXT NOT NULL,
-                title TEXT NOT NULL,
-                detail_json TEXT,
-                severity TEXT NOT NULL DEFAULT 'info',
-                source_id TEXT,
-                created_at INTEGER NOT NULL
-            );
-            CREATE INDEX IF NOT EXISTS idx_activity_project ON activity_events(project_path, created_at DESC);
-            CREATE INDEX IF NOT EXISTS idx_activity_category ON activity_events(category, created_at DESC);
-
-            CREATE TABLE IF NOT EXISTS oauth_tokens (
-                provider TEXT PRIMARY KEY NOT NULL,
-                access_token TEXT NOT NULL,
-                refresh_token TEXT,
-                expires_at INTEGER,
-                scope TEXT,
-                account_info_json TEXT,
-                updated_at INTEGER NOT NULL
-            );
-
-            INSERT OR IGNORE INTO schema_version (version) VALUES (2);
-            ",
-        )?;
+            "INSERT INTO settings (key, value) VALUES ('seed', 'kept');
+             INSERT INTO conversations (id, project_path, name, created_at, updated_at, is_active)
+                 VALUES ('c1', '/seed/project', 'Seed chat', 1, 2, 1);
+             INSERT INTO messages (id, conversation_id, role, content, timestamp, metadata_json, sort_order)
+                 VALUES ('m1', 'c1', 'assistant', 'migrated hello', 3, '{\"inputTokens\":7}', 0);",
+        )
+        .unwrap();
     }
 
-    Ok(())
+    #[test]
+    fn every_version_migrates_to_head() {
+        for version in 1..=MIGRATIONS.len() as i32 {
+            let conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+            migrate_to(&conn, version).unwrap();
+            assert_eq!(current_version(&conn).unwrap(), version);
+            seed(&conn);
+
+            initialize(&conn).unwrap_or_else(|e| panic!("from v{}: {}", version, e));
+            assert_eq!(current_version(&conn).unwrap(), head_version());
+            let value: String = conn
+                .query_row("SELECT value FROM settings WHERE key = 'seed'", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(value, "kept", "from v{}", version);
+            let name: String = conn
+                .query_row("SELECT name FROM conversations WHERE id = 'c1'", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(name, "Seed chat", "from v{}", version);
+            let found: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM messages_fts WHERE messages_fts MATCH 'migrated'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(found, 1, "from v{}", version);
+        }
+    }
+
+    #[test]
+    fn migration_backups_are_rotated() {
+        let dir = std::env::temp_dir().join(format!("drode-schema-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open(dir.join("drode.db")).unwrap();
+        migrate_to(&conn, 1).unwrap();
+        conn.execute("INSERT INTO settings (key, value) VALUES ('backup_keep', '2')", [])
+            .unwrap();
+        for version in 2..=head_version() {
+            migrate_to(&conn, version).unwrap();
+        }
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.ends_with(".bak"))
+            .collect();
+        names.sort();
+        let newest = head_version() - 1;
+        assert_eq!(
+            names,
+            vec![format!("drode.db.pre-v{}.bak", newest - 1), format!("drode.db.pre-v{}.bak", newest)]
+        );
+        drop(conn);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
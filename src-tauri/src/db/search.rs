@@ -1,39 +1,119 @@
 // FTS5 search support (roadmap Tier 4)
 // The messages_fts virtual table and sync triggers are created in schema.rs.
-// This module provides the search query interface.
-
-// use rusqlite::{params, Connection};
-
-// Stub for future implementation:
-//
-// #[derive(Debug, serde::Serialize)]
-// pub struct SearchResult {
-//     pub message_id: String,
-//     pub conversation_id: String,
-//     pub conversation_name: String,
-//     pub snippet: String,
-// }
-//
-// pub fn search_messages(
-//     conn: &Connection,
-//     project_path: &str,
-//     query: &str,
-// ) -> Vec<SearchResult> {
-//     let mut stmt = conn.prepare(
-//         "SELECT m.id, m.conversation_id, c.name,
-//            snippet(messages_fts, 0, '<mark>', '</mark>', '...', 32) as snippet
-//          FROM messages_fts
-//          JOIN messages m ON messages_fts.rowid = m.rowid
-//          JOIN conversations c ON m.conversation_id = c.id
-//          WHERE c.project_path = ?1 AND messages_fts MATCH ?2
-//          ORDER BY rank LIMIT 50"
-//     ).unwrap();
-//     stmt.query_map(params![project_path, query], |row| {
-//         Ok(SearchResult {
-//             message_id: row.get(0)?,
-//             conversation_id: row.get(1)?,
-//             conversation_name: row.get(2)?,
-//             snippet: row.get(3)?,
-//         })
-//     }).unwrap().filter_map(|r| r.ok()).collect()
-// }
+// This module provides the search query interface. Archived conversations are
+// searched like any other; results carry the flag so the UI can mark them.
+// Conversations in the trash are left out. Bookmark notes and highlights
+// (bookmarks_fts, schema version 9) are matched too and listed first.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+const MAX_RESULTS: i64 = 50;
+
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    #[serde(rename = "messageId")]
+    pub message_id: String,
+    #[serde(rename = "conversationId")]
+    pub conversation_id: String,
+    #[serde(rename = "conversationName")]
+    pub conversation_name: String,
+    pub archived: bool,
+    pub snippet: String,
+    pub timestamp: i64,
+    /// Set when the match is in a bookmark's note or highlight rather than the message itself
+    #[serde(rename = "bookmarkId", skip_serializing_if = "Option::is_none")]
+    pub bookmark_id: Option<i64>,
+}
+
+pub fn search_messages(
+    conn: &Connection,
+    project_path: &str,
+    query: &str,
+) -> Result<Vec<SearchResult>, rusqlite::Error> {
+    let Some(fts_query) = to_fts_query(query) else {
+        return Ok(vec![]);
+    };
+
+    let mut results = search_bookmarks(conn, project_path, &fts_query)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.conversation_id, c.name, c.archived,
+           snippet(messages_fts, 0, '<mark>', '</mark>', '...', 32) as snippet,
+           m.timestamp
+         FROM messages_fts
+         JOIN messages m ON messages_fts.rowid = m.rowid
+         JOIN conversations c ON m.conversation_id = c.id
+         WHERE c.project_path = ?1 AND c.deleted_at IS NULL AND messages_fts MATCH ?2
+         ORDER BY rank LIMIT ?3",
+    )?;
+
+    let remaining = MAX_RESULTS - results.len() as i64;
+    let rows = stmt.query_map(params![project_path, fts_query, remaining], |row| {
+        Ok(SearchResult {
+            message_id: row.get(0)?,
+            conversation_id: row.get(1)?,
+            conversation_name: row.get(2)?,
+            archived: row.get(3)?,
+            snippet: row.get(4)?,
+            timestamp: row.get(5)?,
+            bookmark_id: None,
+        })
+    })?;
+
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Takes at most half of the result budget so message matches still get through
+fn search_bookmarks(
+    conn: &Connection,
+    project_path: &str,
+    fts_query: &str,
+) -> Result<Vec<SearchResult>, rusqlite::Error> {
+    // Column -1 lets snippet() pick whichever of note/quote matched best
+    let mut stmt = conn.prepare(
+        "SELECT b.message_id, b.conversation_id, c.name, c.archived,
+           snippet(bookmarks_fts, -1, '<mark>', '</mark>', '...', 32) as snippet,
+           COALESCE(m.timestamp, b.created_at), b.id
+         FROM bookmarks_fts
+         JOIN bookmarks b ON bookmarks_fts.rowid = b.id
+         JOIN conversations c ON b.conversation_id = c.id
+         LEFT JOIN messages m ON m.id = b.message_id
+         WHERE c.project_path = ?1 AND c.deleted_at IS NULL AND bookmarks_fts MATCH ?2
+         ORDER BY rank LIMIT ?3",
+    )?;
+
+    let rows = stmt.query_map(params![project_path, fts_query, MAX_RESULTS / 2], |row| {
+        Ok(SearchResult {
+            message_id: row.get(0)?,
+            conversation_id: row.get(1)?,
+            conversation_name: row.get(2)?,
+            archived: row.get(3)?,
+            snippet: row.get(4)?,
+            timestamp: row.get(5)?,
+            bookmark_id: row.get(6)?,
+        })
+    })?;
+
+    let mut results = Vec::new();
+    for row in rows {
+        results.push(row?);
+    }
+    Ok(results)
+}
+
+/// Quote every term so user input can't trip FTS5 query syntax (`-`, `:`, `"` ...)
+fn to_fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
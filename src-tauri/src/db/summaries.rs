@@ -0,0 +1,303 @@
+// Generated conversation titles and rolling summaries (schema version 8).
+// Deciding what needs generating, building the prompt and storing the answer live
+// here; commands/summaries.rs runs the model through the Claude CLI.
+
+use crate::state::MessageMetadata;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+pub const ENABLED_KEY: &str = "auto_summary_enabled";
+pub const MODEL_KEY: &str = "auto_summary_model";
+pub const REFRESH_EVERY_KEY: &str = "auto_summary_every";
+const DEFAULT_MODEL: &str = "haiku";
+const DEFAULT_REFRESH_EVERY: u32 = 20;
+
+const MAX_TITLE_LEN: usize = 60;
+// Keeps the prompt cheap on long conversations; the oldest messages are dropped first
+const MAX_MESSAGE_CHARS: usize = 2_000;
+const MAX_TRANSCRIPT_CHARS: usize = 24_000;
+
+/// Where the CLI runs for summaries: outside any project, so no project instructions end
+/// up in the prompt, and recognisable, so the session importer can leave its runs out
+pub fn cli_workdir() -> PathBuf {
+    std::env::temp_dir().join("drode-summaries")
+}
+
+/// Whether a CLI session was one of our summary runs. The CLI records the resolved
+/// working directory, which differs from temp_dir() where that is a symlink (macOS).
+pub fn is_cli_workdir(path: &str) -> bool {
+    let dir = cli_workdir();
+    Path::new(path) == dir || dir.canonicalize().is_ok_and(|real| Path::new(path) == real)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SummarySettings {
+    pub enabled: bool,
+    pub model: String,
+    /// Refresh the summary once this many messages were added since the last one; 0 never refreshes
+    #[serde(rename = "refreshEvery")]
+    pub refresh_every: u32,
+}
+
+pub fn settings(conn: &Connection) -> SummarySettings {
+    SummarySettings {
+        enabled: crate::db::settings::get(conn, ENABLED_KEY).as_deref() == Some("true"),
+        model: crate::db::settings::get(conn, MODEL_KEY)
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        refresh_every: crate::db::settings::get(conn, REFRESH_EVERY_KEY)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_REFRESH_EVERY),
+    }
+}
+
+pub fn save_settings(conn: &Connection, settings: &SummarySettings) -> Result<(), rusqlite::Error> {
+    crate::db::settings::set(conn, ENABLED_KEY, if settings.enabled { "true" } else { "false" })?;
+    crate::db::settings::set(conn, MODEL_KEY, settings.model.trim())?;
+    crate::db::settings::set(conn, REFRESH_EVERY_KEY, &settings.refresh_every.to_string())
+}
+
+/// Everything the generator needs, read in one go so no connection is held while the model runs
+#[derive(Debug, Clone)]
+pub struct SummaryJob {
+    pub conversation_id: String,
+    pub wants_title: bool,
+    pub previous_summary: Option<String>,
+    /// (role, content) of the messages the previous summary doesn't cover yet
+    pub messages: Vec<(String, String)>,
+    pub message_count: i64,
+}
+
+/// What, if anything, should be generated for the conversation right now.
+/// Nothing happens before the first user/assistant exchange; `force` refreshes the
+/// summary even when fewer than `refresh_every` messages were added. After a failed run
+/// automatic generation waits for `refresh_every` more messages (at least one).
+pub fn pending_job(
+    conn: &Connection,
+    conversation_id: &str,
+    refresh_every: u32,
+    force: bool,
+) -> Result<Option<SummaryJob>, rusqlite::Error> {
+    let conv = conn
+        .query_row(
+            "SELECT title_source, summary, summary_message_count, summary_failed_at_count
+             FROM conversations WHERE id = ?1 AND deleted_at IS NULL",
+            params![conversation_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((title_source, summary, covered, failed_at)) = conv else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT role, content FROM messages
+         WHERE conversation_id = ?1 AND role IN ('user', 'assistant') AND content != ''
+         ORDER BY sort_order ASC",
+    )?;
+    let messages: Vec<(String, String)> = stmt
+        .query_map(params![conversation_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let has_exchange = messages.iter().any(|(r, _)| r == "user")
+        && messages.iter().any(|(r, _)| r == "assistant");
+    if !has_exchange {
+        return Ok(None);
+    }
+
+    let message_count = messages.len() as i64;
+    let backing_off = failed_at.is_some_and(|n| message_count - n < i64::from(refresh_every.max(1)));
+    if backing_off && !force {
+        return Ok(None);
+    }
+    let wants_title = title_source.is_none();
+    let summary_due = summary.is_none()
+        || (refresh_every > 0 && message_count - covered >= i64::from(refresh_every))
+        || (force && message_count > covered);
+    if !wants_title && !summary_due {
+        return Ok(None);
+    }
+
+    // A refresh only needs what happened since the last summary
+    let skip = if summary.is_some() {
+        covered.clamp(0, message_count) as usize
+    } else {
+        0
+    };
+    Ok(Some(SummaryJob {
+        conversation_id: conversation_id.to_string(),
+        wants_title,
+        previous_summary: summary,
+        messages: messages.into_iter().skip(skip).collect(),
+        message_count,
+    }))
+}
+
+pub fn build_prompt(job: &SummaryJob) -> String {
+    let mut prompt = String::from(
+        "You write titles and summaries for a coding assistant's chat history.\n\
+         Reply with a single JSON object and nothing else: {\"title\": string, \"summary\": string}.\n\
+         The title is at most 6 words, plain text, no quotes or trailing punctuation.\n\
+         The summary is 2-4 sentences covering the goal, what was done and what is still open.\n",
+    );
+    if !job.wants_title {
+        prompt.push_str("The conversation already has a title; return null for \"title\".\n");
+    }
+    if let Some(ref previous) = job.previous_summary {
+        prompt.push_str("\nSummary so far (update it with the new messages):\n");
+        prompt.push_str(previous);
+        prompt.push('\n');
+    }
+
+    prompt.push_str("\nMessages:\n");
+    prompt.push_str(&transcript(&job.messages));
+    prompt
+}
+
+fn transcript(messages: &[(String, String)]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut total = 0;
+    for (role, content) in messages.iter().rev() {
+        let content = clip(content.trim(), MAX_MESSAGE_CHARS);
+        let part = format!("[{}] {}\n", role, content);
+        total += part.len();
+        if total > MAX_TRANSCRIPT_CHARS && !parts.is_empty() {
+            break;
+        }
+        parts.push(part);
+    }
+    parts.reverse();
+    parts.concat()
+}
+
+fn clip(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+/// Result text and usage from `claude --print --output-format json`
+pub fn parse_cli_output(stdout: &str, requested_model: &str) -> Result<(String, MessageMetadata), String> {
+    let json: Value = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Unexpected CLI output: {}", e))?;
+    if json["is_error"].as_bool().unwrap_or(false) {
+        return Err(json["result"].as_str().unwrap_or("Generation failed").to_string());
+    }
+    let result = json["result"].as_str().unwrap_or_default().to_string();
+
+    let usage = &json["usage"];
+    // modelUsage is keyed by the resolved model id, e.g. "claude-haiku-4-5"
+    let model = json["modelUsage"]
+        .as_object()
+        .and_then(|m| m.keys().next().cloned())
+        .unwrap_or_else(|| requested_model.to_string());
+    let metadata = MessageMetadata {
+        duration_ms: json["duration_ms"].as_i64(),
+        duration_api_ms: json["duration_api_ms"].as_i64(),
+        input_tokens: usage["input_tokens"].as_i64(),
+        output_tokens: usage["output_tokens"].as_i64(),
+        cache_read_tokens: usage["cache_read_input_tokens"].as_i64(),
+        cache_creation_tokens: usage["cache_creation_input_tokens"].as_i64(),
+        total_cost_usd: json["total_cost_usd"].as_f64(),
+        model: Some(model),
+    };
+    Ok((result, metadata))
+}
+
+/// Pull `(title, summary)` out of the model's reply, tolerating prose or code fences around the JSON
+pub fn parse_reply(text: &str) -> Option<(Option<String>, String)> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    if end < start {
+        return None;
+    }
+    let json: Value = serde_json::from_str(&text[start..=end]).ok()?;
+
+    let summary = json["summary"].as_str()?.trim().to_string();
+    if summary.is_empty() {
+        return None;
+    }
+    let title = json["title"].as_str().map(clean_title).filter(|t| !t.is_empty());
+    Some((title, summary))
+}
+
+fn clean_title(raw: &str) -> String {
+    let line = raw.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let line = line
+        .trim()
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '`')
+        .trim_end_matches(['.', '!', ':'])
+        .trim();
+    let mut title: String = line.chars().take(MAX_TITLE_LEN).collect();
+    if line.chars().count() > MAX_TITLE_LEN {
+        title = format!("{}…", title.trim_end());
+    }
+    title
+}
+
+/// Store the generated text. The title is only applied while the conversation still has a
+/// placeholder name, so a rename made while the model was running wins.
+pub fn apply(
+    conn: &Connection,
+    job: &SummaryJob,
+    title: Option<&str>,
+    summary: &str,
+) -> Result<(), rusqlite::Error> {
+    let title = title.filter(|_| job.wants_title);
+    conn.execute(
+        "UPDATE conversations SET
+             summary = ?1,
+             summary_message_count = ?2,
+             summary_failed_at_count = NULL,
+             name = CASE WHEN ?3 IS NOT NULL AND title_source IS NULL THEN ?3 ELSE name END,
+             title_source = CASE WHEN ?3 IS NOT NULL AND title_source IS NULL THEN 'auto' ELSE title_source END
+         WHERE id = ?4",
+        params![summary, job.message_count, title, job.conversation_id],
+    )?;
+    Ok(())
+}
+
+/// Remember that the run for `job` produced nothing usable, so automatic runs back off
+pub fn record_failure(conn: &Connection, job: &SummaryJob) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "UPDATE conversations SET summary_failed_at_count = ?1 WHERE id = ?2",
+        params![job.message_count, job.conversation_id],
+    )?;
+    Ok(())
+}
+
+/// Record a generation call so it shows up in the usage stats
+pub fn record_usage(
+    conn: &Connection,
+    conversation_id: &str,
+    kind: &str,
+    usage: &MessageMetadata,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO generation_usage (conversation_id, kind, model, input_tokens, output_tokens,
+             cache_read_tokens, cache_creation_tokens, cost_usd, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            conversation_id,
+            kind,
+            usage.model,
+            usage.input_tokens.unwrap_or(0),
+            usage.output_tokens.unwrap_or(0),
+            usage.cache_read_tokens.unwrap_or(0),
+            usage.cache_creation_tokens.unwrap_or(0),
+            usage.total_cost_usd.unwrap_or(0.0),
+            chrono::Utc::now().timestamp_millis(),
+        ],
+    )?;
+    Ok(())
+}
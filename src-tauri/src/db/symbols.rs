@@ -0,0 +1,168 @@
+// Persisted symbol index (schema version 13). symbol_index.rs parses the files; this
+// module stores what it found per file and answers lookups.
+
+use rusqlite::{params, Connection, ToSql};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// function, method, class, interface, type, enum, struct, trait, module, constant,
+    /// variable or macro
+    pub kind: String,
+    /// Class, impl or module the symbol is declared in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    pub exported: bool,
+    pub path: String,
+    /// Where the name is; 1-based, the column in UTF-16 code units
+    pub line: usize,
+    pub column: usize,
+    /// Lines the whole declaration spans
+    #[serde(rename = "startLine")]
+    pub start_line: usize,
+    #[serde(rename = "endLine")]
+    pub end_line: usize,
+}
+
+const SYMBOL_SELECT: &str =
+    "SELECT name, kind, container, exported, path, name_line, name_column, start_line, end_line FROM symbols";
+
+fn symbol_from_row(row: &rusqlite::Row) -> Result<Symbol, rusqlite::Error> {
+    Ok(Symbol {
+        name: row.get(0)?,
+        kind: row.get(1)?,
+        container: row.get(2)?,
+        exported: row.get(3)?,
+        path: row.get(4)?,
+        line: row.get::<_, i64>(5)? as usize,
+        column: row.get::<_, i64>(6)? as usize,
+        start_line: row.get::<_, i64>(7)? as usize,
+        end_line: row.get::<_, i64>(8)? as usize,
+    })
+}
+
+/// Replace everything known about one file
+pub fn store_file(
+    conn: &Connection,
+    project_path: &str,
+    path: &str,
+    mtime: i64,
+    size: u64,
+    symbols: &[Symbol],
+) -> Result<(), rusqlite::Error> {
+    conn.execute(
+        "INSERT INTO symbol_files (project_path, path, mtime, size) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_path, path) DO UPDATE SET mtime = excluded.mtime, size = excluded.size",
+        params![project_path, path, mtime, size as i64],
+    )?;
+    conn.execute(
+        "DELETE FROM symbols WHERE project_path = ?1 AND path = ?2",
+        params![project_path, path],
+    )?;
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO symbols (project_path, path, name, kind, container, exported,
+             name_line, name_column, start_line, end_line)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    for symbol in symbols {
+        stmt.execute(params![
+            project_path,
+            path,
+            symbol.name,
+            symbol.kind,
+            symbol.container,
+            symbol.exported,
+            symbol.line as i64,
+            symbol.column as i64,
+            symbol.start_line as i64,
+            symbol.end_line as i64,
+        ])?;
+    }
+    Ok(())
+}
+
+/// A file, or every file below a folder
+pub fn remove_path(conn: &Connection, project_path: &str, path: &str) -> Result<usize, rusqlite::Error> {
+    let prefix = format!("{}/", path.trim_end_matches('/'));
+    conn.execute(
+        "DELETE FROM symbol_files WHERE project_path = ?1 AND (path = ?2 OR substr(path, 1, length(?3)) = ?3)",
+        params![project_path, path, prefix],
+    )
+}
+
+/// (mtime, size) each indexed file was parsed at
+pub fn file_stamps(conn: &Connection, project_path: &str) -> Result<HashMap<String, (i64, u64)>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT path, mtime, size FROM symbol_files WHERE project_path = ?1")?;
+    let rows = stmt.query_map(params![project_path], |row| {
+        Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get::<_, i64>(2)? as u64)))
+    })?;
+    rows.collect()
+}
+
+pub fn count(conn: &Connection, project_path: &str) -> Result<usize, rusqlite::Error> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM symbols WHERE project_path = ?1",
+        params![project_path],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n as usize)
+}
+
+/// Symbols whose name contains `query` (case-insensitively), exact and prefix matches
+/// first, then exported ones and shorter names
+pub fn search(
+    conn: &Connection,
+    project_path: &str,
+    query: &str,
+    kinds: &[String],
+    limit: usize,
+) -> Result<Vec<Symbol>, rusqlite::Error> {
+    let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    let mut sql = format!(
+        "{} WHERE project_path = ?1 AND name LIKE ?2 ESCAPE '\\'",
+        SYMBOL_SELECT
+    );
+    let mut values: Vec<Box<dyn ToSql>> = vec![
+        Box::new(project_path.to_string()),
+        Box::new(format!("%{}%", escaped)),
+        Box::new(format!("{}%", escaped)),
+        Box::new(query.to_string()),
+    ];
+    if !kinds.is_empty() {
+        let placeholders: Vec<String> = (0..kinds.len()).map(|i| format!("?{}", i + 5)).collect();
+        sql.push_str(&format!(" AND kind IN ({})", placeholders.join(", ")));
+        for kind in kinds {
+            values.push(Box::new(kind.clone()));
+        }
+    }
+    sql.push_str(&format!(
+        " ORDER BY CASE WHEN name = ?4 COLLATE NOCASE THEN 0 WHEN name LIKE ?3 ESCAPE '\\' THEN 1 ELSE 2 END,
+             exported DESC, length(name), name, path, name_line
+         LIMIT {}",
+        limit
+    ));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let refs: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+    let rows = stmt.query_map(refs.as_slice(), symbol_from_row)?;
+    rows.collect()
+}
+
+/// Declarations named exactly `name`: those in `prefer_path` first, then exported ones
+pub fn definitions(
+    conn: &Connection,
+    project_path: &str,
+    name: &str,
+    prefer_path: Option<&str>,
+) -> Result<Vec<Symbol>, rusqlite::Error> {
+    let sql = format!(
+        "{} WHERE project_path = ?1 AND name = ?2
+         ORDER BY path = ?3 DESC, exported DESC, path, name_line",
+        SYMBOL_SELECT
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![project_path, name, prefer_path.unwrap_or("")], symbol_from_row)?;
+    rows.collect()
+}
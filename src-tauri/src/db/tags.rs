@@ -0,0 +1,69 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagInfo {
+    pub name: String,
+    #[serde(rename = "conversationCount")]
+    pub conversation_count: i64,
+}
+
+/// Tags in use, optionally restricted to one project's conversations
+pub fn list(conn: &Connection, project_path: Option<&str>) -> Result<Vec<TagInfo>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT t.name, COUNT(ct.conversation_id)
+         FROM tags t
+         JOIN conversation_tags ct ON ct.tag_id = t.id
+         JOIN conversations c ON c.id = ct.conversation_id
+         WHERE c.deleted_at IS NULL AND (?1 IS NULL OR c.project_path = ?1)
+         GROUP BY t.id
+         ORDER BY t.name COLLATE NOCASE ASC",
+    )?;
+
+    let rows = stmt.query_map(params![project_path], |row| {
+        Ok(TagInfo {
+            name: row.get(0)?,
+            conversation_count: row.get(1)?,
+        })
+    })?;
+
+    let mut tags = Vec::new();
+    for row in rows {
+        tags.push(row?);
+    }
+    Ok(tags)
+}
+
+pub fn add_to_conversation(
+    conn: &Connection,
+    conversation_id: &str,
+    tag: &str,
+) -> Result<(), rusqlite::Error> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", params![tag])?;
+    tx.execute(
+        "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag_id)
+         SELECT ?1, id FROM tags WHERE name = ?2",
+        params![conversation_id, tag],
+    )?;
+    tx.commit()
+}
+
+pub fn remove_from_conversation(
+    conn: &Connection,
+    conversation_id: &str,
+    tag: &str,
+) -> Result<(), rusqlite::Error> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM conversation_tags
+         WHERE conversation_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+        params![conversation_id, tag],
+    )?;
+    // Drop tags nobody uses any more so the tag list stays meaningful
+    tx.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT DISTINCT tag_id FROM conversation_tags)",
+        [],
+    )?;
+    tx.commit()
+}
@@ -0,0 +1,252 @@
+// At-rest encryption for OAuth tokens (XChaCha20-Poly1305).
+// The key either lives in <config>/oauth.key (0600) or is derived from a user
+// passphrase with Argon2id. Encrypted values look like `enc:v1:<key id>:<base64>`
+// and are bound to their provider through the AEAD associated data.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+
+pub const KEY_MODE_KEY: &str = "oauth_key_mode";
+pub const KEY_ID_KEY: &str = "oauth_key_id";
+pub const KEY_SALT_KEY: &str = "oauth_key_salt";
+pub const PASSPHRASE_ENV: &str = "DRODE_TOKEN_PASSPHRASE";
+
+#[derive(Clone)]
+pub struct TokenCipher {
+    key: [u8; 32],
+    key_id: String,
+}
+
+impl TokenCipher {
+    pub fn from_key(key: [u8; 32]) -> Self {
+        let digest = Sha256::digest(key);
+        let key_id = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        Self { key, key_id }
+    }
+
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self::from_key(key)
+    }
+
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self, String> {
+        let mut key = [0u8; 32];
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| format!("Failed to derive key: {}", e))?;
+        Ok(Self::from_key(key))
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// `aad` ties the ciphertext to its row so values can't be swapped between providers
+    pub fn encrypt(&self, plaintext: &str, aad: &str) -> Result<String, String> {
+        let cipher = XChaCha20Poly1305::new((&self.key).into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: aad.as_bytes() })
+            .map_err(|_| "Encryption failed".to_string())?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", PREFIX, self.key_id, STANDARD.encode(blob)))
+    }
+
+    pub fn decrypt(&self, value: &str, aad: &str) -> Result<String, String> {
+        let rest = value
+            .strip_prefix(PREFIX)
+            .ok_or_else(|| "Value is not encrypted".to_string())?;
+        let (key_id, encoded) = rest
+            .split_once(':')
+            .ok_or_else(|| "Malformed encrypted value".to_string())?;
+        if key_id != self.key_id {
+            return Err(format!("Value was encrypted with a different key ({})", key_id));
+        }
+
+        let blob = STANDARD
+            .decode(encoded)
+            .map_err(|_| "Malformed encrypted value".to_string())?;
+        if blob.len() < NONCE_LEN {
+            return Err("Malformed encrypted value".to_string());
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+
+        let cipher = XChaCha20Poly1305::new((&self.key).into());
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: aad.as_bytes() })
+            .map_err(|_| "Decryption failed (wrong key or tampered value)".to_string())?;
+        String::from_utf8(plaintext).map_err(|_| "Decrypted value is not UTF-8".to_string())
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+pub fn is_passphrase_mode(conn: &Connection) -> bool {
+    crate::db::settings::get(conn, KEY_MODE_KEY).as_deref() == Some("passphrase")
+}
+
+/// Open the cipher configured for this database.
+/// Returns Ok(None) in passphrase mode when no passphrase is available yet (locked).
+pub fn open(conn: &Connection, key_path: &Path) -> Result<Option<TokenCipher>, String> {
+    let expected_id = crate::db::settings::get(conn, KEY_ID_KEY);
+
+    if is_passphrase_mode(conn) {
+        return match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) => unlock(conn, &passphrase).map(Some),
+            Err(_) => Ok(None),
+        };
+    }
+
+    recover_staged_key(key_path, expected_id.as_deref());
+
+    if key_path.exists() {
+        let cipher = read_key_file(key_path)?;
+        if let Some(expected) = expected_id.filter(|id| id != cipher.key_id()) {
+            return Err(format!(
+                "{} does not match the key tokens were encrypted with ({})",
+                key_path.display(),
+                expected
+            ));
+        }
+        crate::db::settings::set(conn, KEY_ID_KEY, cipher.key_id()).map_err(|e| e.to_string())?;
+        return Ok(Some(cipher));
+    }
+
+    // A recorded key id without its key file means the file was lost; minting a new
+    // key would silently orphan every stored token
+    if expected_id.is_some() {
+        return Err(format!("Token key file {} is missing", key_path.display()));
+    }
+
+    let cipher = TokenCipher::generate();
+    write_key_file(key_path, &cipher)?;
+    crate::db::settings::set(conn, KEY_MODE_KEY, "file").map_err(|e| e.to_string())?;
+    crate::db::settings::set(conn, KEY_ID_KEY, cipher.key_id()).map_err(|e| e.to_string())?;
+    Ok(Some(cipher))
+}
+
+/// Where a rotation writes the new key file before the rows are re-encrypted
+pub fn staged_key_path(key_path: &Path) -> PathBuf {
+    key_path.with_extension("key.new")
+}
+
+/// Finish a rotation that committed but stopped before moving its staged key into place:
+/// the staged key is the right one when it matches the recorded key id. A staged key that
+/// doesn't match is left over from a rotation that rolled back.
+fn recover_staged_key(key_path: &Path, expected_id: Option<&str>) {
+    let staged = staged_key_path(key_path);
+    let Ok(cipher) = read_key_file(&staged) else {
+        return;
+    };
+    if expected_id == Some(cipher.key_id()) {
+        if let Err(e) = std::fs::rename(&staged, key_path) {
+            eprintln!("Failed to move {} into place: {}", staged.display(), e);
+        }
+    } else {
+        let _ = std::fs::remove_file(&staged);
+    }
+}
+
+/// Derive the passphrase key and check it against the recorded key id
+pub fn unlock(conn: &Connection, passphrase: &str) -> Result<TokenCipher, String> {
+    let salt = crate::db::settings::get(conn, KEY_SALT_KEY)
+        .and_then(|s| STANDARD.decode(s).ok())
+        .ok_or_else(|| "No passphrase salt recorded".to_string())?;
+    let cipher = TokenCipher::from_passphrase(passphrase, &salt)?;
+
+    match crate::db::settings::get(conn, KEY_ID_KEY) {
+        Some(expected) if expected == cipher.key_id() => Ok(cipher),
+        _ => Err("Incorrect passphrase".to_string()),
+    }
+}
+
+/// A fresh passphrase-derived cipher plus the base64 salt to record alongside it
+pub fn new_passphrase_cipher(passphrase: &str) -> Result<(TokenCipher, String), String> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = TokenCipher::from_passphrase(passphrase, &salt)?;
+    Ok((cipher, STANDARD.encode(salt)))
+}
+
+pub fn read_key_file(path: &Path) -> Result<TokenCipher, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let bytes = STANDARD
+        .decode(contents.trim())
+        .map_err(|_| format!("{} is not a valid key file", path.display()))?;
+    let key: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| format!("{} is not a valid key file", path.display()))?;
+    Ok(TokenCipher::from_key(key))
+}
+
+/// Write the key readable by the owner only
+pub fn write_key_file(path: &Path, cipher: &TokenCipher) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    file.write_all(STANDARD.encode(cipher.key).as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    // mode() only applies on creation; tighten a pre-existing file too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_rotation_recovers_staged_key() {
+        let dir = std::env::temp_dir().join(format!("drode-keys-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key_path = dir.join("oauth.key");
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::initialize(&conn).unwrap();
+
+        let old = open(&conn, &key_path).unwrap().unwrap();
+        // The rotation committed the new key id but stopped before the rename
+        let new = TokenCipher::generate();
+        write_key_file(&staged_key_path(&key_path), &new).unwrap();
+        crate::db::settings::set(&conn, KEY_ID_KEY, new.key_id()).unwrap();
+
+        let opened = open(&conn, &key_path).unwrap().unwrap();
+        assert_eq!(opened.key_id(), new.key_id());
+        assert_ne!(opened.key_id(), old.key_id());
+        assert!(!staged_key_path(&key_path).exists());
+
+        // A staged key from a rotation that rolled back is dropped
+        write_key_file(&staged_key_path(&key_path), &TokenCipher::generate()).unwrap();
+        assert_eq!(open(&conn, &key_path).unwrap().unwrap().key_id(), new.key_id());
+        assert!(!staged_key_path(&key_path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
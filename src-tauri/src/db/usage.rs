@@ -0,0 +1,199 @@
+// Token and cost analytics over the usage columns promoted from messages.metadata_json
+// (see schema version 4), plus the background title/summary calls in generation_usage
+// (version 8), which add tokens and cost but not messages.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageGroup {
+    Conversation,
+    Project,
+    Model,
+    Day,
+    Week,
+    Month,
+}
+
+impl UsageGroup {
+    /// (key expression, label expression) for the GROUP BY
+    fn columns(self) -> (&'static str, &'static str) {
+        match self {
+            UsageGroup::Conversation => ("c.id", "MAX(c.name)"),
+            UsageGroup::Project => ("c.project_path", "c.project_path"),
+            UsageGroup::Model => ("COALESCE(m.model, 'unknown')", "COALESCE(m.model, 'unknown')"),
+            UsageGroup::Day => (
+                "strftime('%Y-%m-%d', m.timestamp / 1000, 'unixepoch', 'localtime')",
+                "strftime('%Y-%m-%d', m.timestamp / 1000, 'unixepoch', 'localtime')",
+            ),
+            UsageGroup::Week => (
+                "strftime('%Y-W%W', m.timestamp / 1000, 'unixepoch', 'localtime')",
+                "strftime('%Y-W%W', m.timestamp / 1000, 'unixepoch', 'localtime')",
+            ),
+            UsageGroup::Month => (
+                "strftime('%Y-%m', m.timestamp / 1000, 'unixepoch', 'localtime')",
+                "strftime('%Y-%m', m.timestamp / 1000, 'unixepoch', 'localtime')",
+            ),
+        }
+    }
+
+    fn is_period(self) -> bool {
+        matches!(self, UsageGroup::Day | UsageGroup::Week | UsageGroup::Month)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UsageRow {
+    pub key: String,
+    pub label: String,
+    #[serde(rename = "messageCount")]
+    pub message_count: i64,
+    #[serde(rename = "inputTokens")]
+    pub input_tokens: i64,
+    #[serde(rename = "outputTokens")]
+    pub output_tokens: i64,
+    #[serde(rename = "cacheReadTokens")]
+    pub cache_read_tokens: i64,
+    #[serde(rename = "cacheCreationTokens")]
+    pub cache_creation_tokens: i64,
+    #[serde(rename = "costUsd")]
+    pub cost_usd: f64,
+    /// cache_read / (cache_read + cache_creation); None when no cache tokens were recorded
+    #[serde(rename = "cacheHitRatio")]
+    pub cache_hit_ratio: Option<f64>,
+    #[serde(rename = "firstAt")]
+    pub first_at: i64,
+    #[serde(rename = "lastAt")]
+    pub last_at: i64,
+}
+
+// Every recorded model call, whether it produced a chat message or a generated title/summary
+const USAGE_SOURCE: &str = "(
+        SELECT conversation_id, model, timestamp, input_tokens, output_tokens,
+               cache_read_tokens, cache_creation_tokens, cost_usd, 1 AS is_message
+        FROM messages WHERE metadata_json IS NOT NULL
+        UNION ALL
+        SELECT conversation_id, model, timestamp, input_tokens, output_tokens,
+               cache_read_tokens, cache_creation_tokens, cost_usd, 0 AS is_message
+        FROM generation_usage
+    )";
+
+pub fn query_usage(
+    conn: &Connection,
+    group: UsageGroup,
+    project_path: Option<&str>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<UsageRow>, rusqlite::Error> {
+    let (key_expr, label_expr) = group.columns();
+
+    let mut sql = format!(
+        "SELECT {key} AS k, {label},
+                COALESCE(SUM(m.is_message), 0),
+                COALESCE(SUM(m.input_tokens), 0),
+                COALESCE(SUM(m.output_tokens), 0),
+                COALESCE(SUM(m.cache_read_tokens), 0),
+                COALESCE(SUM(m.cache_creation_tokens), 0),
+                COALESCE(SUM(m.cost_usd), 0.0),
+                MIN(m.timestamp),
+                MAX(m.timestamp)
+         FROM {usage} m
+         JOIN conversations c ON c.id = m.conversation_id
+         WHERE 1 = 1",
+        key = key_expr,
+        label = label_expr,
+        usage = USAGE_SOURCE,
+    );
+
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    if let Some(path) = project_path {
+        params_vec.push(Box::new(path.to_string()));
+        sql.push_str(&format!(" AND c.project_path = ?{}", params_vec.len()));
+    }
+    if let Some(since) = since {
+        params_vec.push(Box::new(since));
+        sql.push_str(&format!(" AND m.timestamp >= ?{}", params_vec.len()));
+    }
+    if let Some(until) = until {
+        params_vec.push(Box::new(until));
+        sql.push_str(&format!(" AND m.timestamp < ?{}", params_vec.len()));
+    }
+
+    sql.push_str(" GROUP BY k");
+    // Periods read chronologically; everything else by spend
+    if group.is_period() {
+        sql.push_str(" ORDER BY k ASC");
+    } else {
+        sql.push_str(" ORDER BY 8 DESC, 5 DESC");
+    }
+
+    let mut stmt = conn.prepare(&sql)?;
+    let param_refs: Vec<&dyn rusqlite::types::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+
+    let rows = stmt.query_map(param_refs.as_slice(), |row| {
+        let cache_read_tokens: i64 = row.get(5)?;
+        let cache_creation_tokens: i64 = row.get(6)?;
+        let cache_total = cache_read_tokens + cache_creation_tokens;
+        Ok(UsageRow {
+            key: row.get(0)?,
+            label: row.get(1)?,
+            message_count: row.get(2)?,
+            input_tokens: row.get(3)?,
+            output_tokens: row.get(4)?,
+            cache_read_tokens,
+            cache_creation_tokens,
+            cost_usd: row.get(7)?,
+            cache_hit_ratio: if cache_total > 0 {
+                Some(cache_read_tokens as f64 / cache_total as f64)
+            } else {
+                None
+            },
+            first_at: row.get(8)?,
+            last_at: row.get(9)?,
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+pub fn to_csv(rows: &[UsageRow]) -> String {
+    let mut out = String::from(
+        "key,label,messages,input_tokens,output_tokens,cache_read_tokens,cache_creation_tokens,cost_usd,cache_hit_ratio,first_at,last_at\n",
+    );
+    for r in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{:.6},{},{},{}\n",
+            csv_field(&r.key),
+            csv_field(&r.label),
+            r.message_count,
+            r.input_tokens,
+            r.output_tokens,
+            r.cache_read_tokens,
+            r.cache_creation_tokens,
+            r.cost_usd,
+            r.cache_hit_ratio.map(|v| format!("{:.4}", v)).unwrap_or_default(),
+            format_ts(r.first_at),
+            format_ts(r.last_at),
+        ));
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_ts(millis: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
@@ -0,0 +1,66 @@
+// Native dialogs opened from the backend. Exports are written where the user picks, and
+// project and allowed folders are the ones the user chose here: the webview never names
+// these paths, so a compromised page can't use them to write over arbitrary files or to
+// widen what the file commands may touch.
+
+use crate::error::DrodeError;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+
+/// Ask where to save `content`, suggesting `file_name`, and write it there. Returns the
+/// chosen path, or None when the dialog was cancelled.
+pub async fn save(
+    app_handle: AppHandle,
+    file_name: String,
+    filter: &'static str,
+    extension: &'static str,
+    content: Vec<u8>,
+) -> Result<Option<String>, DrodeError> {
+    // The blocking dialog must stay off the main thread, which runs the event loop
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(chosen) = app_handle
+            .dialog()
+            .file()
+            .set_file_name(file_name)
+            .add_filter(filter, &[extension])
+            .blocking_save_file()
+        else {
+            return Ok(None);
+        };
+        let path = chosen
+            .into_path()
+            .map_err(|e| DrodeError::internal(format!("Can't save there: {}", e)))?;
+        std::fs::write(&path, content).map_err(|e| DrodeError::io(&path, e))?;
+        Ok(Some(path.to_string_lossy().to_string()))
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Saving failed: {}", e)))?
+}
+
+/// Ask for a folder. Returns None when the dialog was cancelled.
+pub async fn pick_folder(app_handle: AppHandle, title: &'static str) -> Result<Option<PathBuf>, DrodeError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let Some(chosen) = app_handle.dialog().file().set_title(title).blocking_pick_folder() else {
+            return Ok(None);
+        };
+        chosen
+            .into_path()
+            .map(Some)
+            .map_err(|e| DrodeError::internal(format!("Can't use that folder: {}", e)))
+    })
+    .await
+    .map_err(|e| DrodeError::Internal(format!("Folder dialog failed: {}", e)))?
+}
+
+/// `name` made safe to suggest as a file name
+pub fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_control() || r#"/\:*?"<>|"#.contains(c) { '_' } else { c })
+        .collect();
+    match stem.trim().trim_matches('.') {
+        "" => "conversation".to_string(),
+        stem => stem.to_string(),
+    }
+}
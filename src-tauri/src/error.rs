@@ -0,0 +1,274 @@
+// Error type shared by every command. Serialized to the frontend as
+// `{ code, message, details? }`; `code` values are part of the IPC contract,
+// so add new ones rather than renaming existing ones.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum DrodeError {
+    /// SQLite failure
+    Database(String),
+    /// Filesystem failure other than a missing path
+    Io { message: String, path: Option<String> },
+    NotFound(String),
+    /// A child process (claude, git, sh ...) could not be started
+    Spawn { program: String, message: String },
+    /// The remote API answered with a non-success status
+    Http { status: u16, message: String, provider: Option<String> },
+    /// The request never got an HTTP answer (DNS, TLS, timeout ...)
+    Network(String),
+    /// No stored credentials for the provider
+    AuthMissing { provider: String },
+    /// The provider rejected our credentials (HTTP 401/403); the UI should re-authenticate
+    Unauthorized { provider: String, status: u16 },
+    /// Stored credentials exist but are encrypted with a passphrase that hasn't been entered
+    AuthLocked,
+    /// A file command was given a path outside the open project and the allowlist
+    PathDenied { path: String, reason: String },
+    /// Files changed on disk since the operation was prepared (a replace preview, say)
+    Conflict { message: String, paths: Vec<String> },
+    /// A conditional write found a different version on disk; `current` is None if the
+    /// file was deleted. Shares the `conflict` code so the UI can offer a merge.
+    FileChanged {
+        path: String,
+        current: Option<String>,
+        version: Option<crate::path_policy::FileVersion>,
+    },
+    Validation(String),
+    Internal(String),
+}
+
+impl DrodeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DrodeError::Database(_) => "database",
+            DrodeError::Io { .. } => "io",
+            DrodeError::NotFound(_) => "not_found",
+            DrodeError::Spawn { .. } => "spawn_failed",
+            DrodeError::Http { .. } => "http",
+            DrodeError::Network(_) => "network",
+            DrodeError::AuthMissing { .. } => "auth_missing",
+            DrodeError::Unauthorized { .. } => "unauthorized",
+            DrodeError::AuthLocked => "auth_locked",
+            DrodeError::PathDenied { .. } => "path_denied",
+            DrodeError::Conflict { .. } | DrodeError::FileChanged { .. } => "conflict",
+            DrodeError::Validation(_) => "validation",
+            DrodeError::Internal(_) => "internal",
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            DrodeError::Io { path: Some(path), .. } => Some(serde_json::json!({ "path": path })),
+            DrodeError::PathDenied { path, .. } => Some(serde_json::json!({ "path": path })),
+            DrodeError::Conflict { paths, .. } => Some(serde_json::json!({ "paths": paths })),
+            DrodeError::FileChanged { path, current, version } => Some(serde_json::json!({
+                "paths": [path],
+                "currentContent": current,
+                "currentVersion": version,
+            })),
+            DrodeError::Spawn { program, .. } => Some(serde_json::json!({ "program": program })),
+            DrodeError::Http { status, provider, .. } => {
+                Some(serde_json::json!({ "status": status, "provider": provider }))
+            }
+            DrodeError::AuthMissing { provider } => Some(serde_json::json!({ "provider": provider })),
+            DrodeError::Unauthorized { provider, status } => {
+                Some(serde_json::json!({ "provider": provider, "status": status }))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        DrodeError::Validation(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        DrodeError::NotFound(message.into())
+    }
+
+    pub fn internal(message: impl fmt::Display) -> Self {
+        DrodeError::Internal(message.to_string())
+    }
+
+    /// IO error that remembers which path it was about
+    pub fn io(path: impl AsRef<std::path::Path>, e: std::io::Error) -> Self {
+        let path = path.as_ref().to_string_lossy().to_string();
+        if e.kind() == std::io::ErrorKind::NotFound {
+            DrodeError::NotFound(format!("{}: {}", path, e))
+        } else {
+            DrodeError::Io {
+                message: e.to_string(),
+                path: Some(path),
+            }
+        }
+    }
+
+    pub fn spawn(program: &str, e: std::io::Error) -> Self {
+        DrodeError::Spawn {
+            program: program.to_string(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for DrodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrodeError::Database(m) => write!(f, "Database error: {}", m),
+            DrodeError::Io { message, path: Some(path) } => write!(f, "{}: {}", path, message),
+            DrodeError::Io { message, path: None } => write!(f, "{}", message),
+            DrodeError::NotFound(m) => write!(f, "Not found: {}", m),
+            DrodeError::Spawn { program, message } => write!(f, "Failed to start {}: {}", program, message),
+            DrodeError::Http { status, message, .. } => write!(f, "HTTP {}: {}", status, message),
+            DrodeError::Network(m) => write!(f, "Network error: {}", m),
+            DrodeError::AuthMissing { provider } => {
+                write!(f, "{} not connected. Please authenticate first.", provider_label(provider))
+            }
+            DrodeError::Unauthorized { provider, .. } => {
+                write!(f, "{} rejected the stored credentials. Please reconnect.", provider_label(provider))
+            }
+            DrodeError::AuthLocked => {
+                write!(f, "OAuth tokens are locked. Unlock them with your passphrase first.")
+            }
+            DrodeError::PathDenied { path, reason } => write!(f, "Access denied: {} {}", path, reason),
+            DrodeError::Conflict { message, .. } => write!(f, "{}", message),
+            DrodeError::FileChanged { path, current: Some(_), .. } => {
+                write!(f, "{} changed on disk since it was loaded", path)
+            }
+            DrodeError::FileChanged { path, current: None, .. } => {
+                write!(f, "{} was deleted since it was loaded", path)
+            }
+            DrodeError::Validation(m) => write!(f, "{}", m),
+            DrodeError::Internal(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+impl std::error::Error for DrodeError {}
+
+impl Serialize for DrodeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let details = self.details();
+        let mut s = serializer.serialize_struct("DrodeError", if details.is_some() { 3 } else { 2 })?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        if let Some(details) = details {
+            s.serialize_field("details", &details)?;
+        }
+        s.end()
+    }
+}
+
+impl From<rusqlite::Error> for DrodeError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => DrodeError::NotFound("No matching row".to_string()),
+            e => DrodeError::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for DrodeError {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            DrodeError::NotFound(e.to_string())
+        } else {
+            DrodeError::Io {
+                message: e.to_string(),
+                path: None,
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for DrodeError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => DrodeError::Http {
+                status: status.as_u16(),
+                message: e.to_string(),
+                provider: None,
+            },
+            // Body decoding failures are the API sending something unexpected
+            None if e.is_decode() => DrodeError::Internal(format!("Unexpected response: {}", e)),
+            None => DrodeError::Network(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for DrodeError {
+    fn from(e: serde_json::Error) -> Self {
+        DrodeError::Internal(format!("Invalid JSON: {}", e))
+    }
+}
+
+impl From<crate::db::schema::MigrationError> for DrodeError {
+    fn from(e: crate::db::schema::MigrationError) -> Self {
+        DrodeError::Database(e.to_string())
+    }
+}
+
+/// For db helpers that return `Box<dyn Error>`; keeps the specific kind when we can tell
+impl From<Box<dyn std::error::Error>> for DrodeError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        let e = match e.downcast::<rusqlite::Error>() {
+            Ok(e) => return (*e).into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(e) => return (*e).into(),
+            Err(e) => e,
+        };
+        match e.downcast::<DrodeError>() {
+            Ok(e) => *e,
+            Err(e) => DrodeError::Internal(e.to_string()),
+        }
+    }
+}
+
+/// Turn a provider API response into an error unless it succeeded.
+/// 401/403 become `Unauthorized` so the UI can offer to reconnect.
+pub async fn check_response(provider: &str, resp: reqwest::Response) -> Result<reqwest::Response, DrodeError> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(DrodeError::Unauthorized {
+            provider: provider.to_string(),
+            status: status.as_u16(),
+        });
+    }
+
+    let body = resp.text().await.unwrap_or_default();
+    Err(DrodeError::Http {
+        status: status.as_u16(),
+        message: api_error_message(&body).unwrap_or_else(|| {
+            status.canonical_reason().unwrap_or("Request failed").to_string()
+        }),
+        provider: Some(provider.to_string()),
+    })
+}
+
+/// Pull the human-readable part out of a JSON error body, if there is one
+fn api_error_message(body: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(body).ok()?;
+    let message = json["message"]
+        .as_str()
+        .or_else(|| json["error"]["message"].as_str())
+        .or_else(|| json["error"].as_str())
+        .or_else(|| json["msg"].as_str())?;
+    Some(message.to_string())
+}
+
+fn provider_label(provider: &str) -> &str {
+    match provider {
+        "github" => "GitHub",
+        "supabase" => "Supabase",
+        "vercel" => "Vercel",
+        other => other,
+    }
+}
@@ -0,0 +1,558 @@
+// In-memory list of every file in a watched project, for quick-open. Built by the
+// project's watcher thread (watcher.rs) and kept current from the same notify events.
+// Honors .gitignore, .ignore and the user's exclude patterns; matching is fzf-style.
+
+use crate::db::settings;
+use crate::error::DrodeError;
+use crate::state::AppState;
+use crate::watcher::{ChangeKind, FsChange, IgnoreRules};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// JSON array of gitignore-style patterns left out of every project's index
+pub const EXCLUDES_KEY: &str = "file_index_excludes";
+
+pub const DEFAULT_LIMIT: usize = 50;
+pub const MAX_LIMIT: usize = 1000;
+// Stop indexing there rather than eat memory on a mis-opened home directory
+const MAX_FILES: usize = 200_000;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileMatch {
+    pub path: String,
+    #[serde(rename = "relativePath")]
+    pub relative_path: String,
+    pub score: i64,
+    /// Matched characters in `relativePath`, as char (code point) offsets
+    pub positions: Vec<usize>,
+}
+
+pub struct FileIndex {
+    root: PathBuf,
+    /// Paths relative to the root, '/'-separated
+    files: BTreeSet<String>,
+    rules: IgnoreRules,
+    /// An ignore file or the excludes changed, or notify lost events; rescan on the next tick
+    stale: bool,
+    /// Changes applied while a rebuild scans, replayed onto its result before the swap
+    during_scan: Option<Vec<FsChange>>,
+}
+
+impl FileIndex {
+    /// Empty until the first scan
+    pub fn new(root: &Path) -> Self {
+        FileIndex {
+            root: root.to_path_buf(),
+            files: BTreeSet::new(),
+            rules: IgnoreRules::new(root),
+            stale: true,
+            during_scan: None,
+        }
+    }
+
+    pub fn scan(root: &Path, excludes: &[String]) -> Self {
+        let mut index = FileIndex {
+            root: root.to_path_buf(),
+            files: BTreeSet::new(),
+            rules: IgnoreRules::with_excludes(root, excludes),
+            stale: false,
+            during_scan: None,
+        };
+        index.add_tree(root);
+        index
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Absolute paths of every indexed file
+    pub fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.files.iter().map(|rel| self.root.join(rel))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    pub fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+
+    /// Update from a watcher change (before the watcher's own .gitignore filtering)
+    pub fn apply(&mut self, change: &FsChange) {
+        if let Some(pending) = &mut self.during_scan {
+            pending.push(change.clone());
+        }
+        let path = Path::new(&change.path);
+        if self.rules.is_ignore_file(path) {
+            // Could hide or reveal anything below it
+            self.stale = true;
+            return;
+        }
+        match change.kind {
+            ChangeKind::Create | ChangeKind::Modify => self.add_path(path, change.is_dir),
+            ChangeKind::Delete => self.remove_path(path),
+            ChangeKind::Rename => {
+                if let Some(old) = change.old_path.as_deref() {
+                    self.remove_path(Path::new(old));
+                }
+                self.add_path(path, change.is_dir);
+            }
+        }
+    }
+
+    fn add_path(&mut self, path: &Path, is_dir: bool) {
+        if is_dir {
+            if !self.rules.is_ignored(path, true) {
+                self.add_tree(path);
+            }
+        } else if let Some(rel) = self.relative(path) {
+            if !self.rules.is_ignored(path, false) && path.is_file() {
+                self.files.insert(rel);
+            }
+        }
+    }
+
+    /// A path and, if it was a directory, everything that was below it
+    fn remove_path(&mut self, path: &Path) {
+        let Some(rel) = self.relative(path) else {
+            return;
+        };
+        self.files.remove(&rel);
+        let prefix = format!("{}/", rel);
+        let below: Vec<String> = self
+            .files
+            .range(prefix.clone()..)
+            .take_while(|f| f.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for file in below {
+            self.files.remove(&file);
+        }
+    }
+
+    /// Walk a directory, not descending into ignored ones or following symlinks
+    fn add_tree(&mut self, dir: &Path) {
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if self.files.len() >= MAX_FILES {
+                    return;
+                }
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                let path = entry.path();
+                if file_type.is_dir() {
+                    if !self.rules.is_ignored(&path, true) {
+                        pending.push(path);
+                    }
+                } else if !self.rules.is_ignored(&path, false) {
+                    if let Some(rel) = self.relative(&path) {
+                        self.files.insert(rel);
+                    }
+                }
+            }
+        }
+    }
+
+    fn relative(&self, path: &Path) -> Option<String> {
+        let rel = path.strip_prefix(&self.root).ok()?;
+        if rel.as_os_str().is_empty() {
+            return None;
+        }
+        let parts: Vec<_> = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        Some(parts.join("/"))
+    }
+
+    /// Best matches first. Whitespace separates terms that must all match. An empty query
+    /// lists the shallowest files.
+    pub fn find(&self, query: &str, limit: usize) -> Vec<FileMatch> {
+        let terms: Vec<Term> = query.split_whitespace().map(Term::new).collect();
+        if terms.is_empty() {
+            let mut files: Vec<&String> = self.files.iter().collect();
+            files.sort_by_key(|f| (f.matches('/').count(), f.len()));
+            return files
+                .into_iter()
+                .take(limit)
+                .map(|f| self.to_match(f, 0, Vec::new()))
+                .collect();
+        }
+
+        // Score everything first and only work out highlight positions for what is returned
+        let mut chars = Vec::new();
+        let mut scored: Vec<(i64, &String)> = Vec::new();
+        for file in &self.files {
+            chars.clear();
+            chars.extend(file.chars());
+            if let Some(score) = score_terms(&chars, &terms, None) {
+                scored.push((score, file));
+            }
+        }
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.len().cmp(&b.1.len())).then(a.1.cmp(b.1)));
+        scored.truncate(limit);
+
+        scored
+            .into_iter()
+            .map(|(score, file)| {
+                let chars: Vec<char> = file.chars().collect();
+                let mut positions = Vec::new();
+                score_terms(&chars, &terms, Some(&mut positions));
+                positions.sort_unstable();
+                positions.dedup();
+                self.to_match(file, score, positions)
+            })
+            .collect()
+    }
+
+    fn to_match(&self, rel: &str, score: i64, positions: Vec<usize>) -> FileMatch {
+        FileMatch {
+            path: self.root.join(rel).to_string_lossy().to_string(),
+            relative_path: rel.to_string(),
+            score,
+            positions,
+        }
+    }
+}
+
+pub fn excludes(conn: &Connection) -> Vec<String> {
+    settings::get(conn, EXCLUDES_KEY)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Refuse gitignore-style patterns that don't parse, naming the first bad one. Used for
+/// the index excludes here and the per-project ones in file_visibility.
+pub fn validate_excludes(patterns: &[String]) -> Result<(), DrodeError> {
+    let mut builder = ignore::gitignore::GitignoreBuilder::new("/");
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| DrodeError::validation(format!("Invalid exclude pattern {:?}: {}", pattern, e)))?;
+    }
+    Ok(())
+}
+
+pub fn set_excludes(conn: &Connection, patterns: &[String]) -> Result<(), DrodeError> {
+    validate_excludes(patterns)?;
+    settings::set(conn, EXCLUDES_KEY, &serde_json::to_string(patterns)?)?;
+    Ok(())
+}
+
+/// Rescan a project from scratch. The watcher runs this on a thread of its own: the walk
+/// happens without holding the lock, so lookups keep answering from the old index and
+/// the watcher keeps applying changes to it meanwhile; those are carried over to the new one.
+pub fn rebuild(app_handle: &AppHandle, project_path: &str, index: &RwLock<FileIndex>) {
+    let excludes = {
+        let state = app_handle.state::<AppState>();
+        let db = state.db.get();
+        excludes(&db)
+    };
+    let root = begin_rescan(index);
+    let fresh = FileIndex::scan(&root, &excludes);
+    let file_count = finish_rescan(index, fresh);
+
+    let _ = app_handle.emit(
+        "file-index-updated",
+        serde_json::json!({ "projectPath": project_path, "fileCount": file_count }),
+    );
+}
+
+/// Start recording the changes applied to `index` and return the root to walk
+fn begin_rescan(index: &RwLock<FileIndex>) -> PathBuf {
+    let mut index = index.write().unwrap();
+    // Clearing the flag first means a request for a rescan during this one isn't lost
+    index.stale = false;
+    index.during_scan = Some(Vec::new());
+    index.root().to_path_buf()
+}
+
+/// Replay what was applied during the walk onto `fresh` and swap it in; returns the file count
+fn finish_rescan(index: &RwLock<FileIndex>, mut fresh: FileIndex) -> usize {
+    let mut index = index.write().unwrap();
+    // The walk may or may not have seen these; applying them again is harmless
+    for change in index.during_scan.take().unwrap_or_default() {
+        fresh.apply(&change);
+    }
+    fresh.stale |= index.stale;
+    *index = fresh;
+    index.len()
+}
+
+// fzf-style scoring (its v1 algorithm): find the shortest window holding the term as a
+// subsequence, then reward matches on word boundaries and consecutive runs, and charge
+// for gaps.
+
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY: i64 = SCORE_MATCH / 2;
+const BONUS_BOUNDARY_WHITE: i64 = BONUS_BOUNDARY + 2;
+const BONUS_BOUNDARY_DELIMITER: i64 = BONUS_BOUNDARY + 1;
+const BONUS_NON_WORD: i64 = SCORE_MATCH / 2;
+const BONUS_CAMEL_123: i64 = BONUS_BOUNDARY + SCORE_GAP_EXTENSION;
+const BONUS_CONSECUTIVE: i64 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum CharClass {
+    White,
+    NonWord,
+    Delimiter,
+    Lower,
+    Upper,
+    Letter,
+    Number,
+}
+
+fn char_class(c: char) -> CharClass {
+    match c {
+        'a'..='z' => CharClass::Lower,
+        'A'..='Z' => CharClass::Upper,
+        '0'..='9' => CharClass::Number,
+        '/' | '\\' | ',' | ':' | ';' | '|' => CharClass::Delimiter,
+        c if c.is_whitespace() => CharClass::White,
+        c if c.is_lowercase() => CharClass::Lower,
+        c if c.is_uppercase() => CharClass::Upper,
+        c if c.is_numeric() => CharClass::Number,
+        c if c.is_alphabetic() => CharClass::Letter,
+        _ => CharClass::NonWord,
+    }
+}
+
+fn bonus(prev: CharClass, class: CharClass) -> i64 {
+    if class > CharClass::Delimiter {
+        match prev {
+            CharClass::White => return BONUS_BOUNDARY_WHITE,
+            CharClass::Delimiter => return BONUS_BOUNDARY_DELIMITER,
+            CharClass::NonWord => return BONUS_BOUNDARY,
+            _ => {}
+        }
+    }
+    if (prev == CharClass::Lower && class == CharClass::Upper)
+        || (prev != CharClass::Number && class == CharClass::Number)
+    {
+        return BONUS_CAMEL_123;
+    }
+    match class {
+        CharClass::NonWord | CharClass::Delimiter => BONUS_NON_WORD,
+        CharClass::White => BONUS_BOUNDARY_WHITE,
+        _ => 0,
+    }
+}
+
+fn fold(c: char) -> char {
+    if c.is_ascii() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+struct Term {
+    chars: Vec<char>,
+    /// Smart case: only a term with an uppercase letter is matched case-sensitively
+    case_sensitive: bool,
+}
+
+impl Term {
+    fn new(text: &str) -> Self {
+        let case_sensitive = text.chars().any(char::is_uppercase);
+        Term {
+            chars: text.chars().map(|c| if case_sensitive { c } else { fold(c) }).collect(),
+            case_sensitive,
+        }
+    }
+}
+
+fn score_terms(text: &[char], terms: &[Term], mut positions: Option<&mut Vec<usize>>) -> Option<i64> {
+    let mut total = 0;
+    for term in terms {
+        total += fuzzy_match(text, term, positions.as_deref_mut())?;
+    }
+    Some(total)
+}
+
+fn fuzzy_match(text: &[char], term: &Term, positions: Option<&mut Vec<usize>>) -> Option<i64> {
+    let pattern = &term.chars;
+    let norm = |c: char| if term.case_sensitive { c } else { fold(c) };
+
+    // Forward: the earliest end of a match
+    let mut pidx = 0;
+    let mut start = None;
+    let mut end = 0;
+    for (idx, &c) in text.iter().enumerate() {
+        if norm(c) == pattern[pidx] {
+            start.get_or_insert(idx);
+            pidx += 1;
+            if pidx == pattern.len() {
+                end = idx + 1;
+                break;
+            }
+        }
+    }
+    let mut start = start?;
+    if pidx < pattern.len() {
+        return None;
+    }
+
+    // Backward from there: the latest start, for the tightest window
+    let mut pidx = pattern.len();
+    for idx in (start..end).rev() {
+        if norm(text[idx]) == pattern[pidx - 1] {
+            pidx -= 1;
+            if pidx == 0 {
+                start = idx;
+                break;
+            }
+        }
+    }
+
+    Some(window_score(text, term, start, end, positions))
+}
+
+fn window_score(text: &[char], term: &Term, start: usize, end: usize, mut positions: Option<&mut Vec<usize>>) -> i64 {
+    let pattern = &term.chars;
+    let mut pidx = 0;
+    let mut score = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0;
+    let mut first_bonus = 0;
+    // The start of the path counts as following a '/'
+    let mut prev_class = if start > 0 { char_class(text[start - 1]) } else { CharClass::Delimiter };
+
+    for (idx, &c) in text.iter().enumerate().take(end).skip(start) {
+        let class = char_class(c);
+        let c = if term.case_sensitive { c } else { fold(c) };
+        if pidx < pattern.len() && c == pattern[pidx] {
+            if let Some(positions) = positions.as_deref_mut() {
+                positions.push(idx);
+            }
+            score += SCORE_MATCH;
+            let mut b = bonus(prev_class, class);
+            if consecutive == 0 {
+                first_bonus = b;
+            } else {
+                // A run keeps the bonus of the boundary it started on
+                if b >= BONUS_BOUNDARY && b > first_bonus {
+                    first_bonus = b;
+                }
+                b = b.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            score += if pidx == 0 { b * BONUS_FIRST_CHAR_MULTIPLIER } else { b };
+            in_gap = false;
+            consecutive += 1;
+            pidx += 1;
+        } else {
+            score += if in_gap { SCORE_GAP_EXTENSION } else { SCORE_GAP_START };
+            in_gap = true;
+            consecutive = 0;
+            first_bonus = 0;
+        }
+        prev_class = class;
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watcher::ChangeKind;
+
+    fn index_of(files: &[&str]) -> (PathBuf, FileIndex) {
+        let root = std::env::temp_dir().join(format!("drode-index-{}", uuid::Uuid::new_v4()));
+        for file in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let index = FileIndex::scan(&root, &[]);
+        (root, index)
+    }
+
+    fn found(index: &FileIndex, query: &str) -> Vec<String> {
+        index.find(query, 10).into_iter().map(|m| m.relative_path).collect()
+    }
+
+    #[test]
+    fn matches_prefer_word_boundaries() {
+        let (root, index) = index_of(&["src/file_index.rs", "src/profile_indexer.rs", "docs/fi.md"]);
+        assert_eq!(found(&index, "fidx")[0], "src/file_index.rs");
+        assert_eq!(found(&index, "src/ fi").len(), 2);
+        assert!(found(&index, "zzz").is_empty());
+
+        // Smart case: an upper-case letter makes the query case-sensitive
+        assert!(found(&index, "FIDX").is_empty());
+        let hit = &index.find("fidx", 1)[0];
+        // Offsets are code points in the relative path
+        assert_eq!(hit.positions, vec![4, 5, 11, 13]);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn applies_watcher_changes() {
+        let (root, mut index) = index_of(&["a.rs"]);
+        let change = |kind, path: &str, old: Option<&str>| FsChange {
+            kind,
+            path: root.join(path).to_string_lossy().to_string(),
+            old_path: old.map(|o| root.join(o).to_string_lossy().to_string()),
+            is_dir: false,
+            by_claude: false,
+        };
+        std::fs::write(root.join("b.rs"), "").unwrap();
+        index.apply(&change(ChangeKind::Create, "b.rs", None));
+        std::fs::rename(root.join("a.rs"), root.join("c.rs")).unwrap();
+        index.apply(&change(ChangeKind::Rename, "c.rs", Some("a.rs")));
+        let mut files: Vec<String> = index.files.iter().cloned().collect();
+        files.sort();
+        assert_eq!(files, vec!["b.rs", "c.rs"]);
+
+        std::fs::write(root.join(".gitignore"), "b.rs\n").unwrap();
+        index.apply(&change(ChangeKind::Create, ".gitignore", None));
+        assert!(index.is_stale());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn changes_during_a_rescan_reach_the_new_index() {
+        let (root, index) = index_of(&["kept.rs", "deleted.rs"]);
+        let index = RwLock::new(index);
+        let change = |kind, path: &str| FsChange {
+            kind,
+            path: root.join(path).to_string_lossy().to_string(),
+            old_path: None,
+            is_dir: false,
+            by_claude: false,
+        };
+
+        let walk_root = begin_rescan(&index);
+        let fresh = FileIndex::scan(&walk_root, &[]);
+        // Both changes happen after the walk passed, as the watcher would report them
+        std::fs::write(root.join("created.rs"), "").unwrap();
+        std::fs::remove_file(root.join("deleted.rs")).unwrap();
+        index.write().unwrap().apply(&change(ChangeKind::Create, "created.rs"));
+        index.write().unwrap().apply(&change(ChangeKind::Delete, "deleted.rs"));
+        index.write().unwrap().mark_stale();
+        assert_eq!(finish_rescan(&index, fresh), 2);
+
+        let index = index.read().unwrap();
+        let files: Vec<&str> = index.files.iter().map(String::as_str).collect();
+        assert_eq!(files, vec!["created.rs", "kept.rs"]);
+        // A rescan requested during this one is still due
+        assert!(index.is_stale());
+        assert!(index.during_scan.is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
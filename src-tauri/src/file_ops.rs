@@ -0,0 +1,483 @@
+// Copying, moving and duplicating files and folders in batches, for the file tree's paste
+// and drag and drop, and bringing back what delete_file sent to the trash. Paths are
+// checked against the path policy by the commands (commands/files.rs) before they get here.
+
+use crate::error::DrodeError;
+use crate::path_policy::PathPolicy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// How often progress is reported while a batch runs
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// Gives up finding a free "name copy N" after this many tries
+const MAX_RENAME_ATTEMPTS: usize = 1000;
+
+/// What to do when the destination already exists
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Leave both alone and report the source as skipped
+    #[default]
+    Skip,
+    /// Send the existing entry to the trash first; fails where there is no trash
+    Overwrite,
+    /// Pick a free name: "name copy.ext", "name copy 2.ext" ...
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Copy,
+    Move,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Transferred {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Failure {
+    pub path: String,
+    pub error: DrodeError,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct TransferSummary {
+    pub done: Vec<Transferred>,
+    /// Sources left alone because of a conflict
+    pub skipped: Vec<String>,
+    pub failed: Vec<Failure>,
+    /// Files copied or moved, counting those inside folders
+    #[serde(rename = "fileCount")]
+    pub file_count: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Progress {
+    /// Files done so far out of `total`
+    pub processed: usize,
+    pub total: usize,
+    /// The file being worked on
+    pub path: String,
+}
+
+struct Counter<'a> {
+    progress: Progress,
+    last_report: Instant,
+    report: &'a mut dyn FnMut(&Progress),
+}
+
+impl Counter<'_> {
+    fn advance(&mut self, path: &Path, files: usize) {
+        self.progress.processed += files;
+        if self.last_report.elapsed() >= PROGRESS_INTERVAL {
+            self.progress.path = path.to_string_lossy().to_string();
+            (self.report)(&self.progress);
+            self.last_report = Instant::now();
+        }
+    }
+}
+
+enum Outcome {
+    Done(PathBuf),
+    Skipped,
+}
+
+/// Copy or move each source into `target`, a folder. Sources and target must already be
+/// resolved through the path policy. One failing source doesn't stop the others.
+pub fn transfer(
+    sources: &[PathBuf],
+    target: &Path,
+    mode: Mode,
+    conflict: ConflictPolicy,
+    report: &mut dyn FnMut(&Progress),
+) -> TransferSummary {
+    let total = sources.iter().map(|source| count_files(source)).sum();
+    let mut counter = Counter {
+        progress: Progress {
+            processed: 0,
+            total,
+            path: String::new(),
+        },
+        last_report: Instant::now(),
+        report,
+    };
+    let mut summary = TransferSummary::default();
+
+    for source in sources {
+        let from = source.to_string_lossy().to_string();
+        match transfer_one(source, target, mode, conflict, &mut counter) {
+            Ok(Outcome::Done(dest)) => summary.done.push(Transferred {
+                from,
+                to: dest.to_string_lossy().to_string(),
+            }),
+            Ok(Outcome::Skipped) => summary.skipped.push(from),
+            Err(error) => summary.failed.push(Failure { path: from, error }),
+        }
+    }
+    summary.file_count = counter.progress.processed;
+    (counter.report)(&counter.progress);
+    summary
+}
+
+fn transfer_one(
+    source: &Path,
+    target: &Path,
+    mode: Mode,
+    conflict: ConflictPolicy,
+    counter: &mut Counter,
+) -> Result<Outcome, DrodeError> {
+    let name = source.to_string_lossy().to_string();
+    let metadata = fs::symlink_metadata(source).map_err(|e| DrodeError::io(source, e))?;
+    let Some(file_name) = source.file_name() else {
+        return Err(DrodeError::validation(format!("{} has no name", name)));
+    };
+    if metadata.is_dir() && target.starts_with(source) {
+        return Err(DrodeError::validation(format!("{} can't be put inside itself", name)));
+    }
+
+    let mut dest = target.join(file_name);
+    if dest == source {
+        // Copying an entry onto itself makes a copy next to it; moving it does nothing
+        match mode {
+            Mode::Copy => dest = free_name(&dest)?,
+            Mode::Move => return Ok(Outcome::Skipped),
+        }
+    } else if fs::symlink_metadata(&dest).is_ok() {
+        match conflict {
+            ConflictPolicy::Skip => return Ok(Outcome::Skipped),
+            ConflictPolicy::Rename => dest = free_name(&dest)?,
+            ConflictPolicy::Overwrite => {
+                if source.starts_with(&dest) {
+                    return Err(DrodeError::validation(format!(
+                        "{} can't replace a folder it is in",
+                        name
+                    )));
+                }
+                to_trash(&dest)?;
+            }
+        }
+    }
+
+    match mode {
+        Mode::Copy => copy_tree(source, &dest, counter)?,
+        Mode::Move => move_tree(source, &dest, counter)?,
+    }
+    Ok(Outcome::Done(dest))
+}
+
+/// The first of "name copy.ext", "name copy 2.ext" ... that doesn't exist
+pub fn free_name(path: &Path) -> Result<PathBuf, DrodeError> {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    // Folders and dotfiles keep their whole name as the stem
+    let split = if path.is_dir() {
+        None
+    } else {
+        file_name.rfind('.').filter(|&i| i > 0)
+    };
+    let (stem, ext) = match split {
+        Some(i) => file_name.split_at(i),
+        None => (file_name.as_str(), ""),
+    };
+    for n in 1..=MAX_RENAME_ATTEMPTS {
+        let candidate = match n {
+            1 => parent.join(format!("{} copy{}", stem, ext)),
+            n => parent.join(format!("{} copy {}{}", stem, n, ext)),
+        };
+        if fs::symlink_metadata(&candidate).is_err() {
+            return Ok(candidate);
+        }
+    }
+    Err(DrodeError::validation(format!("No free name for a copy of {}", file_name)))
+}
+
+fn count_files(path: &Path) -> usize {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|entry| count_files(&entry.path())).sum())
+            .unwrap_or(0),
+        Ok(_) => 1,
+        Err(_) => 0,
+    }
+}
+
+/// Symlinks are copied as links, not followed
+fn copy_tree(source: &Path, dest: &Path, counter: &mut Counter) -> Result<(), DrodeError> {
+    let metadata = fs::symlink_metadata(source).map_err(|e| DrodeError::io(source, e))?;
+    if metadata.file_type().is_symlink() {
+        copy_link(source, dest)?;
+        counter.advance(source, 1);
+    } else if metadata.is_dir() {
+        fs::create_dir(dest).map_err(|e| DrodeError::io(dest, e))?;
+        for entry in fs::read_dir(source).map_err(|e| DrodeError::io(source, e))? {
+            let entry = entry.map_err(|e| DrodeError::io(source, e))?;
+            copy_tree(&entry.path(), &dest.join(entry.file_name()), counter)?;
+        }
+        // Last, so a read-only folder can still be filled
+        let _ = fs::set_permissions(dest, metadata.permissions());
+    } else {
+        fs::copy(source, dest).map_err(|e| DrodeError::io(source, e))?;
+        counter.advance(source, 1);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_link(source: &Path, dest: &Path) -> Result<(), DrodeError> {
+    let link = fs::read_link(source).map_err(|e| DrodeError::io(source, e))?;
+    std::os::unix::fs::symlink(link, dest).map_err(|e| DrodeError::io(dest, e))
+}
+
+#[cfg(not(unix))]
+fn copy_link(source: &Path, dest: &Path) -> Result<(), DrodeError> {
+    fs::copy(source, dest).map(|_| ()).map_err(|e| DrodeError::io(source, e))
+}
+
+/// A rename where possible; across file systems, a copy and then a delete
+fn move_tree(source: &Path, dest: &Path, counter: &mut Counter) -> Result<(), DrodeError> {
+    let files = count_files(source);
+    if let Err(rename_error) = fs::rename(source, dest) {
+        if fs::symlink_metadata(source).is_err() {
+            return Err(DrodeError::io(source, rename_error));
+        }
+        copy_tree(source, dest, counter)?;
+        let removed = if fs::symlink_metadata(source).is_ok_and(|m| m.is_dir()) {
+            fs::remove_dir_all(source)
+        } else {
+            fs::remove_file(source)
+        };
+        return removed.map_err(|e| DrodeError::io(source, e));
+    }
+    counter.advance(source, files);
+    Ok(())
+}
+
+/// To the trash, failing when there is none, so whatever gets replaced can still be recovered
+fn to_trash(path: &Path) -> Result<(), DrodeError> {
+    trash::delete(path).map_err(|e| {
+        DrodeError::internal(format!("Couldn't move {} to the trash: {}", path.display(), e))
+    })
+}
+
+/// To the trash if there is one, for good otherwise
+pub fn discard(path: &Path) -> Result<(), DrodeError> {
+    if to_trash(path).is_ok() {
+        return Ok(());
+    }
+    let removed = if fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()) {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    removed.map_err(|e| DrodeError::io(path, e))
+}
+
+/// An entry in the trash that came from inside the project or the allowed folders
+#[derive(Debug, Serialize, Clone)]
+pub struct DeletedItem {
+    /// Where it was
+    pub path: String,
+    /// Seconds since the epoch
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: i64,
+}
+
+/// Trashed entries the path policy allows, newest first
+pub fn list_deleted(policy: &PathPolicy) -> Result<Vec<DeletedItem>, DrodeError> {
+    let mut items: Vec<DeletedItem> = trash_list()?
+        .iter()
+        .map(|item| DeletedItem {
+            path: item.original_path().to_string_lossy().to_string(),
+            deleted_at: item.time_deleted,
+        })
+        .filter(|item| policy.resolve_entry(&item.path).is_ok())
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.deleted_at));
+    Ok(items)
+}
+
+/// Put the most recently trashed entry that was at `path` back there
+pub fn restore_deleted(policy: &PathPolicy, path: &str) -> Result<PathBuf, DrodeError> {
+    let real = policy.resolve_entry(path)?;
+    if fs::symlink_metadata(&real).is_ok() {
+        return Err(DrodeError::Conflict {
+            message: format!("{} already exists; move it away to restore the deleted one", path),
+            paths: vec![path.to_string()],
+        });
+    }
+    let item = trash_list()?
+        .into_iter()
+        .filter(|item| item.original_path() == real || item.original_path() == Path::new(path))
+        .max_by_key(|item| item.time_deleted)
+        .ok_or_else(|| DrodeError::not_found(format!("{} in the trash", path)))?;
+    trash_restore(item)?;
+    Ok(real)
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+fn trash_list() -> Result<Vec<trash::TrashItem>, DrodeError> {
+    trash::os_limited::list().map_err(|e| DrodeError::internal(format!("Failed to read the trash: {}", e)))
+}
+
+#[cfg(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+fn trash_restore(item: trash::TrashItem) -> Result<(), DrodeError> {
+    trash::os_limited::restore_all([item])
+        .map_err(|e| DrodeError::internal(format!("Failed to restore from the trash: {}", e)))
+}
+
+// The trash crate can only delete on macOS
+#[cfg(not(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+fn trash_list() -> Result<Vec<trash::TrashItem>, DrodeError> {
+    Err(DrodeError::validation("Restoring from the trash isn't supported on this platform"))
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+fn trash_restore(_item: trash::TrashItem) -> Result<(), DrodeError> {
+    Err(DrodeError::validation("Restoring from the trash isn't supported on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        root: PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let root = std::env::temp_dir().join(format!("drode-ops-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&root).unwrap();
+            Fixture {
+                root: root.canonicalize().unwrap(),
+            }
+        }
+
+        fn file(&self, rel: &str, content: &str) -> PathBuf {
+            let path = self.root.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            path
+        }
+
+        fn dir(&self, rel: &str) -> PathBuf {
+            let path = self.root.join(rel);
+            fs::create_dir_all(&path).unwrap();
+            path
+        }
+
+        fn transfer(&self, sources: &[&str], target: &str, mode: Mode, conflict: ConflictPolicy) -> TransferSummary {
+            let sources: Vec<PathBuf> = sources.iter().map(|s| self.root.join(s)).collect();
+            transfer(&sources, &self.root.join(target), mode, conflict, &mut |_| {})
+        }
+
+        fn read(&self, rel: &str) -> String {
+            fs::read_to_string(self.root.join(rel)).unwrap()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn free_names_count_up_and_keep_the_extension() {
+        let fx = Fixture::new();
+        let file = fx.file("notes.txt", "");
+        assert_eq!(free_name(&file).unwrap(), fx.root.join("notes copy.txt"));
+        fx.file("notes copy.txt", "");
+        fx.file("notes copy 2.txt", "");
+        assert_eq!(free_name(&file).unwrap(), fx.root.join("notes copy 3.txt"));
+
+        // Folders and dotfiles keep their whole name as the stem
+        let folder = fx.dir("v1.2");
+        assert_eq!(free_name(&folder).unwrap(), fx.root.join("v1.2 copy"));
+        let dotfile = fx.file(".env", "");
+        assert_eq!(free_name(&dotfile).unwrap(), fx.root.join(".env copy"));
+    }
+
+    #[test]
+    fn conflicts_follow_the_policy() {
+        let fx = Fixture::new();
+        fx.file("src/a.txt", "new");
+        fx.file("dst/a.txt", "old");
+
+        let summary = fx.transfer(&["src/a.txt"], "dst", Mode::Copy, ConflictPolicy::Skip);
+        assert_eq!(summary.skipped, [fx.root.join("src/a.txt").to_string_lossy()]);
+        assert_eq!(fx.read("dst/a.txt"), "old");
+
+        let summary = fx.transfer(&["src/a.txt"], "dst", Mode::Copy, ConflictPolicy::Rename);
+        assert_eq!(summary.done[0].to, fx.root.join("dst/a copy.txt").to_string_lossy());
+        assert_eq!((fx.read("dst/a.txt").as_str(), fx.read("dst/a copy.txt").as_str()), ("old", "new"));
+
+        // The replaced file goes to the trash; without one nothing is replaced
+        let summary = fx.transfer(&["src/a.txt"], "dst", Mode::Move, ConflictPolicy::Overwrite);
+        if summary.failed.is_empty() {
+            assert_eq!(fx.read("dst/a.txt"), "new");
+            assert!(!fx.root.join("src/a.txt").exists());
+        } else {
+            assert_eq!(fx.read("dst/a.txt"), "old");
+            assert_eq!(fx.read("src/a.txt"), "new");
+        }
+    }
+
+    #[test]
+    fn folders_are_not_put_inside_themselves() {
+        let fx = Fixture::new();
+        fx.file("a/b/c.txt", "c");
+        for mode in [Mode::Copy, Mode::Move] {
+            let summary = fx.transfer(&["a"], "a/b", mode, ConflictPolicy::Rename);
+            assert_eq!(summary.failed.len(), 1);
+            assert!(matches!(summary.failed[0].error, DrodeError::Validation(_)));
+            let summary = fx.transfer(&["a"], "a", mode, ConflictPolicy::Rename);
+            assert_eq!(summary.failed.len(), 1);
+        }
+        assert_eq!(fx.read("a/b/c.txt"), "c");
+        assert!(!fx.root.join("a/b/a").exists());
+
+        // Onto its own folder a copy lands next to it and a move does nothing
+        let summary = fx.transfer(&["a"], "", Mode::Copy, ConflictPolicy::Skip);
+        assert_eq!(summary.done[0].to, fx.root.join("a copy").to_string_lossy());
+        assert_eq!(fx.read("a copy/b/c.txt"), "c");
+        let summary = fx.transfer(&["a"], "", Mode::Move, ConflictPolicy::Skip);
+        assert_eq!(summary.skipped.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_copied_as_links() {
+        let fx = Fixture::new();
+        fx.file("src/target.txt", "t");
+        std::os::unix::fs::symlink("target.txt", fx.root.join("src/link")).unwrap();
+        std::os::unix::fs::symlink("/nowhere", fx.root.join("src/dangling")).unwrap();
+        fx.dir("dst");
+
+        let summary = fx.transfer(&["src"], "dst", Mode::Copy, ConflictPolicy::Skip);
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        assert_eq!(summary.file_count, 3);
+        for (link, points_to) in [("dst/src/link", "target.txt"), ("dst/src/dangling", "/nowhere")] {
+            let copied = fx.root.join(link);
+            assert!(fs::symlink_metadata(&copied).unwrap().file_type().is_symlink());
+            assert_eq!(fs::read_link(&copied).unwrap(), Path::new(points_to));
+        }
+    }
+}
@@ -0,0 +1,610 @@
+// Reading files for the editor. The encoding is worked out from the start of the file
+// (UTF-8, UTF-16 with a BOM, otherwise Latin-1), and a file read whole must be valid
+// UTF-8 throughout to count as UTF-8; saves encode back to it. Binary files are told
+// apart and shown as a hex dump, and files too large to send whole are read a window at
+// a time, by lines or by bytes.
+
+use crate::error::DrodeError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// Larger files are only read in windows
+pub const MAX_FULL_READ: u64 = 16 * 1024 * 1024;
+// Files with a NUL byte in this much of their start are treated as binary
+const SNIFF_BYTES: usize = 8192;
+const DEFAULT_WINDOW_BYTES: usize = 256 * 1024;
+const MAX_WINDOW_BYTES: usize = 4 * 1024 * 1024;
+const DEFAULT_LINE_COUNT: usize = 1000;
+const DEFAULT_HEX_BYTES: usize = 4096;
+const MAX_HEX_BYTES: usize = 64 * 1024;
+const HEX_ROW: usize = 16;
+// Even, so UTF-16 code units never straddle two chunks
+const CHUNK_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin-1")]
+    Latin1,
+}
+
+impl Encoding {
+    /// Bytes per code unit
+    fn unit(self) -> usize {
+        match self {
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf8 | Encoding::Latin1 => 1,
+        }
+    }
+
+    fn is_newline(self, unit: &[u8]) -> bool {
+        match self {
+            Encoding::Utf16Le => unit == [b'\n', 0],
+            Encoding::Utf16Be => unit == [0, b'\n'],
+            Encoding::Utf8 | Encoding::Latin1 => unit[0] == b'\n',
+        }
+    }
+
+    /// Invalid sequences become U+FFFD
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+        }
+    }
+
+    /// None when `text` has a character the encoding can't hold
+    pub fn encode(self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Utf8 => Some(text.as_bytes().to_vec()),
+            Encoding::Latin1 => text.chars().map(|c| u8::try_from(c).ok()).collect(),
+            Encoding::Utf16Le => Some(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16Be => Some(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    Cr,
+    Mixed,
+    /// A single line, or too little text to tell
+    None,
+}
+
+impl LineEnding {
+    fn detect(text: &str) -> Self {
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    crlf += 1;
+                }
+                '\r' => cr += 1,
+                '\n' => lf += 1,
+                _ => {}
+            }
+        }
+        match (lf > 0, crlf > 0, cr > 0) {
+            (false, false, false) => LineEnding::None,
+            (true, false, false) => LineEnding::Lf,
+            (false, true, false) => LineEnding::Crlf,
+            (false, false, true) => LineEnding::Cr,
+            _ => LineEnding::Mixed,
+        }
+    }
+}
+
+/// What the start of a file says about it
+#[derive(Debug, Clone, Copy)]
+pub struct Sniff {
+    pub binary: bool,
+    pub encoding: Encoding,
+    /// Length of the byte order mark, 0 if there is none
+    pub bom: usize,
+    pub line_ending: LineEnding,
+}
+
+pub fn sniff(sample: &[u8]) -> Sniff {
+    let (encoding, bom) = if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        (Encoding::Utf8, 3)
+    } else if sample.starts_with(&[0xFF, 0xFE]) {
+        (Encoding::Utf16Le, 2)
+    } else if sample.starts_with(&[0xFE, 0xFF]) {
+        (Encoding::Utf16Be, 2)
+    } else {
+        // A multi-byte character cut off by the end of the sample is still UTF-8
+        let utf8 = match std::str::from_utf8(sample) {
+            Ok(_) => true,
+            Err(e) => e.error_len().is_none(),
+        };
+        (if utf8 { Encoding::Utf8 } else { Encoding::Latin1 }, 0)
+    };
+    let binary = bom == 0 && sample.contains(&0);
+    Sniff {
+        binary,
+        encoding,
+        bom,
+        line_ending: if binary {
+            LineEnding::None
+        } else {
+            LineEnding::detect(&encoding.decode(&sample[bom..]))
+        },
+    }
+}
+
+/// `sniff` for a file that is read in full. Bytes past the sample that aren't UTF-8 make
+/// it Latin-1, which decodes (and encodes back) every byte as it is; decoding it as UTF-8
+/// would replace them and the next save would lose them.
+pub fn detect(bytes: &[u8]) -> Sniff {
+    let mut found = sniff(&bytes[..bytes.len().min(SNIFF_BYTES)]);
+    if found.encoding == Encoding::Utf8 && !found.binary && std::str::from_utf8(&bytes[found.bom..]).is_err() {
+        found.encoding = Encoding::Latin1;
+        found.bom = 0;
+    }
+    found
+}
+
+/// `text` encoded like `existing`, the file it is about to replace, byte order mark
+/// included, so saving a Latin-1 or UTF-16 file doesn't turn it into UTF-8. Empty and
+/// binary files get plain UTF-8.
+pub fn encode_like(existing: &[u8], text: &str, name: &str) -> Result<Vec<u8>, DrodeError> {
+    let found = detect(existing);
+    if found.binary {
+        return Ok(text.as_bytes().to_vec());
+    }
+    let encoded = found.encoding.encode(text).ok_or_else(|| {
+        DrodeError::validation(format!(
+            "{} is saved as {}, which can't hold some of the new characters",
+            name,
+            found.encoding.label()
+        ))
+    })?;
+    let mut bytes = existing[..found.bom].to_vec();
+    bytes.extend(encoded);
+    Ok(bytes)
+}
+
+/// Which part of a file to read. With no lines or bytes given the whole file is read, or
+/// its first window if it is too large.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ReadRange {
+    /// 1-based
+    #[serde(rename = "startLine")]
+    pub start_line: Option<usize>,
+    #[serde(rename = "lineCount")]
+    pub line_count: Option<usize>,
+    /// Bytes into the file
+    pub offset: Option<u64>,
+    pub length: Option<usize>,
+    /// Return a hex dump even if the file is text. Binary files are always dumped.
+    #[serde(default)]
+    pub hex: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileChunk {
+    pub size: u64,
+    pub binary: bool,
+    /// None for binary files
+    pub encoding: Option<Encoding>,
+    pub bom: bool,
+    #[serde(rename = "lineEnding")]
+    pub line_ending: LineEnding,
+    /// The decoded text, or `hexdump -C` style rows in hex mode
+    pub content: String,
+    pub hex: bool,
+    /// Byte range of the file that `content` covers
+    pub start: u64,
+    pub end: u64,
+    /// 1-based line `content` starts at, when known
+    #[serde(rename = "startLine")]
+    pub start_line: Option<usize>,
+    /// Lines in the whole file; known when the window reached the end from a known line
+    #[serde(rename = "totalLines")]
+    pub total_lines: Option<usize>,
+    pub eof: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileInfo {
+    pub size: u64,
+    pub binary: bool,
+    pub encoding: Option<Encoding>,
+    pub bom: bool,
+    #[serde(rename = "lineEnding")]
+    pub line_ending: LineEnding,
+    /// None for binary files
+    #[serde(rename = "lineCount")]
+    pub line_count: Option<usize>,
+}
+
+/// Metadata of the whole file; counting lines reads it through once, in chunks, which
+/// also checks the encoding the way `detect` does
+pub fn info(file: &mut File, name: &str) -> Result<FileInfo, DrodeError> {
+    let size = file.metadata().map_err(|e| DrodeError::io(name, e))?.len();
+    let mut found = sniff(&read_at(file, name, 0, SNIFF_BYTES)?);
+    let line_count = if found.binary {
+        None
+    } else {
+        let mut newlines = 0;
+        let mut offset = found.bom as u64;
+        let mut utf8 = found.encoding == Encoding::Utf8;
+        // The start of a character cut off by the end of the previous chunk
+        let mut pending = Vec::new();
+        loop {
+            let chunk = read_at(file, name, offset, CHUNK_BYTES)?;
+            newlines += newline_ends(&chunk, found.encoding).len();
+            offset += chunk.len() as u64;
+            if utf8 {
+                pending.extend_from_slice(&chunk);
+                match std::str::from_utf8(&pending) {
+                    Ok(_) => pending.clear(),
+                    Err(e) if e.error_len().is_none() => {
+                        pending.drain(..e.valid_up_to());
+                    }
+                    Err(_) => utf8 = false,
+                }
+            }
+            if chunk.len() < CHUNK_BYTES {
+                break;
+            }
+        }
+        if found.encoding == Encoding::Utf8 && (!utf8 || !pending.is_empty()) {
+            found.encoding = Encoding::Latin1;
+            found.bom = 0;
+        }
+        Some(newlines + 1)
+    };
+    Ok(FileInfo {
+        size,
+        binary: found.binary,
+        encoding: (!found.binary).then_some(found.encoding),
+        bom: found.bom > 0,
+        line_ending: found.line_ending,
+        line_count,
+    })
+}
+
+/// The whole file as text. Binary files and ones over MAX_FULL_READ are refused with a
+/// validation error saying so.
+pub fn read_text(file: &mut File, name: &str) -> Result<String, DrodeError> {
+    decode_text(&read_bytes(file, name)?, name)
+}
+
+/// The whole file, unless it is over MAX_FULL_READ
+pub fn read_bytes(file: &mut File, name: &str) -> Result<Vec<u8>, DrodeError> {
+    let size = file.metadata().map_err(|e| DrodeError::io(name, e))?.len();
+    if size > MAX_FULL_READ {
+        return Err(DrodeError::validation(format!(
+            "{} is too large to open in full ({} MB)",
+            name,
+            size / (1024 * 1024)
+        )));
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).map_err(|e| DrodeError::io(name, e))?;
+    Ok(bytes)
+}
+
+/// `bytes`, all of a file, as text in the encoding `detect` finds
+pub fn decode_text(bytes: &[u8], name: &str) -> Result<String, DrodeError> {
+    let found = detect(bytes);
+    if found.binary {
+        return Err(DrodeError::validation(format!("{} is a binary file", name)));
+    }
+    Ok(found.encoding.decode(&bytes[found.bom..]))
+}
+
+pub fn read_range(file: &mut File, name: &str, range: &ReadRange) -> Result<FileChunk, DrodeError> {
+    let size = file.metadata().map_err(|e| DrodeError::io(name, e))?.len();
+    let found = sniff(&read_at(file, name, 0, SNIFF_BYTES)?);
+    let mut chunk = FileChunk {
+        size,
+        binary: found.binary,
+        encoding: (!found.binary).then_some(found.encoding),
+        bom: found.bom > 0,
+        line_ending: found.line_ending,
+        content: String::new(),
+        hex: false,
+        start: 0,
+        end: 0,
+        start_line: None,
+        total_lines: None,
+        eof: false,
+    };
+
+    if range.hex || found.binary {
+        let start = range.offset.unwrap_or(0).min(size) / HEX_ROW as u64 * HEX_ROW as u64;
+        let length = range.length.unwrap_or(DEFAULT_HEX_BYTES).clamp(1, MAX_HEX_BYTES);
+        let bytes = read_at(file, name, start, length)?;
+        chunk.content = hex_dump(start, &bytes);
+        chunk.hex = true;
+        chunk.start = start;
+        chunk.end = start + bytes.len() as u64;
+    } else if range.start_line.is_some() || range.line_count.is_some() {
+        read_lines(file, name, &found, range, &mut chunk)?;
+    } else if range.offset.is_some() || range.length.is_some() || size > MAX_FULL_READ {
+        read_window(file, name, &found, range, &mut chunk)?;
+    } else {
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(0)).map_err(|e| DrodeError::io(name, e))?;
+        file.read_to_end(&mut bytes).map_err(|e| DrodeError::io(name, e))?;
+        // The whole file is here, so check all of it rather than the sample
+        let found = detect(&bytes);
+        chunk.encoding = Some(found.encoding);
+        chunk.bom = found.bom > 0;
+        let text = &bytes[found.bom.min(bytes.len())..];
+        chunk.content = found.encoding.decode(text);
+        chunk.start = found.bom as u64;
+        chunk.end = bytes.len() as u64;
+        chunk.start_line = Some(1);
+        chunk.total_lines = Some(newline_ends(text, found.encoding).len() + 1);
+    }
+    chunk.eof = chunk.end >= size;
+    Ok(chunk)
+}
+
+/// `line_count` lines from `start_line`, within MAX_WINDOW_BYTES. Finding the start means
+/// reading everything before it.
+fn read_lines(file: &mut File, name: &str, found: &Sniff, range: &ReadRange, chunk: &mut FileChunk) -> Result<(), DrodeError> {
+    let start_line = range.start_line.unwrap_or(1).max(1);
+    let wanted = range.line_count.unwrap_or(DEFAULT_LINE_COUNT).max(1);
+
+    // Skip to the first byte of start_line, or of the last line if the file is shorter
+    let mut line = 1;
+    let mut start = found.bom as u64;
+    let mut offset = start;
+    'scan: while line < start_line {
+        let bytes = read_at(file, name, offset, CHUNK_BYTES)?;
+        for newline_end in newline_ends(&bytes, found.encoding) {
+            line += 1;
+            start = offset + newline_end as u64;
+            if line == start_line {
+                break 'scan;
+            }
+        }
+        offset += bytes.len() as u64;
+        if bytes.len() < CHUNK_BYTES {
+            break;
+        }
+    }
+
+    // Read up to the end of the last wanted line
+    let mut bytes = Vec::new();
+    let mut lines = 0;
+    let mut end = start;
+    loop {
+        let room = MAX_WINDOW_BYTES - bytes.len();
+        let next = read_at(file, name, end, CHUNK_BYTES.min(room))?;
+        let mut cut = next.len();
+        for newline_end in newline_ends(&next, found.encoding) {
+            lines += 1;
+            if lines == wanted {
+                cut = newline_end;
+                break;
+            }
+        }
+        bytes.extend_from_slice(&next[..cut]);
+        end += cut as u64;
+        if lines == wanted || next.len() < CHUNK_BYTES.min(room) || bytes.len() >= MAX_WINDOW_BYTES {
+            break;
+        }
+    }
+    // The window limit can cut a line, and with it a character
+    let valid = valid_prefix(&bytes, found.encoding);
+    end -= (bytes.len() - valid) as u64;
+
+    chunk.content = found.encoding.decode(&bytes[..valid]);
+    chunk.start = start;
+    chunk.end = end;
+    chunk.start_line = Some(line);
+    if end >= chunk.size {
+        chunk.total_lines = Some(line + newline_ends(&bytes[..valid], found.encoding).len());
+    }
+    Ok(())
+}
+
+/// A byte window, moved onto character boundaries
+fn read_window(file: &mut File, name: &str, found: &Sniff, range: &ReadRange, chunk: &mut FileChunk) -> Result<(), DrodeError> {
+    let unit = found.encoding.unit() as u64;
+    let offset = range.offset.unwrap_or(0).max(found.bom as u64);
+    let mut start = offset - (offset - found.bom as u64) % unit;
+    let length = range.length.unwrap_or(DEFAULT_WINDOW_BYTES).clamp(1, MAX_WINDOW_BYTES);
+    let mut bytes = read_at(file, name, start, length)?;
+
+    if found.encoding == Encoding::Utf8 {
+        // Skip the tail of a character that started before the window
+        let skip = bytes.iter().take(3).take_while(|&&b| b & 0xC0 == 0x80).count();
+        bytes.drain(..skip);
+        start += skip as u64;
+    }
+    let valid = valid_prefix(&bytes, found.encoding);
+    chunk.content = found.encoding.decode(&bytes[..valid]);
+    chunk.start = start;
+    chunk.end = start + valid as u64;
+    if start == found.bom as u64 {
+        chunk.start_line = Some(1);
+    }
+    Ok(())
+}
+
+/// Length of `bytes` without a character cut off at the end
+fn valid_prefix(bytes: &[u8], encoding: Encoding) -> usize {
+    match encoding {
+        Encoding::Latin1 => bytes.len(),
+        Encoding::Utf8 => match std::str::from_utf8(bytes) {
+            Ok(_) => bytes.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            // Invalid in the middle; decoding replaces it
+            Err(_) => bytes.len(),
+        },
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let mut len = bytes.len() - bytes.len() % 2;
+            // Don't split a surrogate pair
+            if len >= 2 {
+                let last = &bytes[len - 2..len];
+                let unit = match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes([last[0], last[1]]),
+                    _ => u16::from_be_bytes([last[0], last[1]]),
+                };
+                if (0xD800..0xDC00).contains(&unit) {
+                    len -= 2;
+                }
+            }
+            len
+        }
+    }
+}
+
+/// Offsets just past each newline in `bytes`
+fn newline_ends(bytes: &[u8], encoding: Encoding) -> Vec<usize> {
+    let unit = encoding.unit();
+    bytes
+        .chunks_exact(unit)
+        .enumerate()
+        .filter(|(_, u)| encoding.is_newline(u))
+        .map(|(i, _)| (i + 1) * unit)
+        .collect()
+}
+
+fn read_at(file: &mut File, name: &str, offset: u64, length: usize) -> Result<Vec<u8>, DrodeError> {
+    file.seek(SeekFrom::Start(offset)).map_err(|e| DrodeError::io(name, e))?;
+    let mut bytes = Vec::with_capacity(length);
+    file.by_ref()
+        .take(length as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| DrodeError::io(name, e))?;
+    Ok(bytes)
+}
+
+fn hex_dump(start: u64, bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, row) in bytes.chunks(HEX_ROW).enumerate() {
+        out.push_str(&format!("{:08x}  ", start + (i * HEX_ROW) as u64));
+        for col in 0..HEX_ROW {
+            match row.get(col) {
+                Some(b) => out.push_str(&format!("{:02x} ", b)),
+                None => out.push_str("   "),
+            }
+            if col == HEX_ROW / 2 - 1 {
+                out.push(' ');
+            }
+        }
+        out.push_str(" |");
+        out.extend(row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+        out.push_str("|\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(bytes: &[u8]) -> (std::path::PathBuf, File) {
+        let path = std::env::temp_dir().join(format!("drode-read-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, bytes).unwrap();
+        let file = File::open(&path).unwrap();
+        (path, file)
+    }
+
+    #[test]
+    fn invalid_utf8_past_the_sample_is_latin1() {
+        let mut bytes = vec![b'a'; SNIFF_BYTES + 10];
+        bytes.push(0xE9); // é in Latin-1
+        let found = detect(&bytes);
+        assert_eq!(found.encoding, Encoding::Latin1);
+        assert_eq!(sniff(&bytes[..SNIFF_BYTES]).encoding, Encoding::Utf8);
+
+        let (path, mut file) = temp_file(&bytes);
+        let text = read_text(&mut file, "f").unwrap();
+        assert!(text.ends_with('é'));
+        // Saving the text unchanged gives back the same bytes
+        assert_eq!(encode_like(&bytes, &text, "f").unwrap(), bytes);
+        // Every read that covers the whole file agrees
+        assert_eq!(info(&mut file, "f").unwrap().encoding, Some(Encoding::Latin1));
+        let chunk = read_range(&mut file, "f", &ReadRange::default()).unwrap();
+        assert_eq!(chunk.encoding, Some(Encoding::Latin1));
+        assert!(chunk.content.ends_with('é'));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn characters_across_chunks_stay_utf8() {
+        let mut bytes = vec![b'a'; CHUNK_BYTES - 1];
+        bytes.extend("é\n".as_bytes());
+        let (path, mut file) = temp_file(&bytes);
+        let found = info(&mut file, "f").unwrap();
+        assert_eq!(found.encoding, Some(Encoding::Utf8));
+        assert_eq!(found.line_count, Some(2));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn saves_keep_the_encoding_and_bom() {
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter().chain("hé\n".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        assert_eq!(encode_like(&utf16, "hé\n", "f").unwrap(), utf16);
+
+        let bom = [0xEF, 0xBB, 0xBF, b'x'];
+        assert_eq!(encode_like(&bom, "y", "f").unwrap(), [0xEF, 0xBB, 0xBF, b'y']);
+        assert_eq!(encode_like(b"", "new ✓", "f").unwrap(), "new ✓".as_bytes());
+
+        // Latin-1 can't hold it, so the save is refused rather than re-encoded
+        assert!(encode_like(&[b'a', 0xE9], "✓", "f").is_err());
+    }
+
+    #[test]
+    fn reads_a_window_of_lines() {
+        let text: String = (1..=10).map(|n| format!("line {}\n", n)).collect();
+        let (path, mut file) = temp_file(text.as_bytes());
+        let range = ReadRange {
+            start_line: Some(4),
+            line_count: Some(3),
+            ..Default::default()
+        };
+        let chunk = read_range(&mut file, "f", &range).unwrap();
+        assert_eq!(chunk.content, "line 4\nline 5\nline 6\n");
+        assert_eq!(chunk.start_line, Some(4));
+        assert_eq!(&text.as_bytes()[chunk.start as usize..chunk.end as usize], chunk.content.as_bytes());
+        assert!(!chunk.eof);
+
+        let range = ReadRange {
+            start_line: Some(9),
+            ..Default::default()
+        };
+        let chunk = read_range(&mut file, "f", &range).unwrap();
+        assert_eq!(chunk.content, "line 9\nline 10\n");
+        assert!(chunk.eof);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,431 @@
+// Project-wide find and replace. `plan` runs a content search and works out every
+// replacement with a before/after preview of its line; `prepare` turns the edits the user
+// kept into new file contents, refusing if any file changed since the preview, and
+// `write_all` writes them, putting back what it already wrote if one write fails.
+
+use crate::error::DrodeError;
+use crate::file_search::{self, Searcher};
+use crate::path_policy::{self, Expected, PathPolicy};
+use regex::Captures;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplacePlan {
+    pub id: String,
+    #[serde(rename = "projectPath")]
+    pub project_path: String,
+    pub query: String,
+    pub replacement: String,
+    pub files: Vec<FileReplace>,
+    #[serde(rename = "editCount")]
+    pub edit_count: usize,
+    /// Binary, oversized or not valid UTF-8, so left alone
+    #[serde(rename = "skippedFiles")]
+    pub skipped_files: usize,
+    /// Stopped at max results; the plan doesn't cover every match
+    pub truncated: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileReplace {
+    pub path: String,
+    #[serde(rename = "relativePath")]
+    pub relative_path: String,
+    /// SHA-256 of the content the plan was made from
+    #[serde(skip)]
+    pub hash: String,
+    pub edits: Vec<ReplaceEdit>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplaceEdit {
+    /// Unique within the plan; used to pick the edits to apply
+    pub id: usize,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in UTF-16 code units
+    pub column: usize,
+    /// The line as it is, and as it would be with just this edit
+    pub before: String,
+    pub after: String,
+    /// The text that goes in, with regex groups expanded
+    pub replacement: String,
+    #[serde(skip)]
+    pub start: usize,
+    #[serde(skip)]
+    pub end: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplaceOutcome {
+    /// Pass to `undo_replace` to revert the whole replace
+    #[serde(rename = "undoId")]
+    pub undo_id: String,
+    #[serde(rename = "filesChanged")]
+    pub files_changed: usize,
+    #[serde(rename = "editsApplied")]
+    pub edits_applied: usize,
+}
+
+/// A file about to be rewritten and what it holds now
+#[derive(Debug, Clone)]
+pub struct PendingWrite {
+    pub path: String,
+    pub original: String,
+    pub content: String,
+    pub edit_count: usize,
+}
+
+pub fn content_hash(text: &str) -> String {
+    path_policy::content_hash(text.as_bytes())
+}
+
+/// Every match of the search with its replacement. In regex mode `$1`/`${name}` in the
+/// replacement refer to capture groups; in literal mode it is used as-is.
+pub fn plan(searcher: &Searcher, replacement: &str, cancel: &AtomicBool) -> ReplacePlan {
+    let max_edits = searcher.options().max_results.unwrap_or(file_search::DEFAULT_MAX_RESULTS);
+    let mut files = Vec::new();
+    let mut edit_count = 0;
+    let mut lossy_files = 0;
+
+    let summary = searcher.walk(cancel, |path, file, summary| {
+        if file.lossy {
+            if searcher.pattern().is_match(file.text) {
+                lossy_files += 1;
+            }
+            return true;
+        }
+        let edits = plan_file(searcher, replacement, file.text, edit_count, max_edits - edit_count);
+        if !edits.is_empty() {
+            edit_count += edits.len();
+            summary.match_count += edits.len();
+            files.push(FileReplace {
+                path: path.to_string_lossy().to_string(),
+                relative_path: path
+                    .strip_prefix(searcher.root())
+                    .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_else(|_| path.to_string_lossy().to_string()),
+                hash: content_hash(file.text),
+                edits,
+            });
+        }
+        if edit_count >= max_edits {
+            summary.truncated = true;
+            return false;
+        }
+        true
+    });
+
+    ReplacePlan {
+        id: uuid::Uuid::new_v4().to_string(),
+        project_path: searcher.root().to_string_lossy().to_string(),
+        query: searcher.options().query.clone(),
+        replacement: replacement.to_string(),
+        files,
+        edit_count,
+        skipped_files: summary.skipped_files + lossy_files,
+        truncated: summary.truncated,
+        created_at: chrono::Utc::now().timestamp_millis(),
+    }
+}
+
+fn plan_file(searcher: &Searcher, replacement: &str, text: &str, first_id: usize, limit: usize) -> Vec<ReplaceEdit> {
+    if limit == 0 || !searcher.pattern().is_match(text) {
+        return Vec::new();
+    }
+    let expand = searcher.options().regex;
+    let mut edits = Vec::new();
+    let mut line_start = 0;
+
+    for (idx, raw) in text.split_inclusive('\n').enumerate() {
+        let line = raw.strip_suffix('\n').unwrap_or(raw);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        for caps in searcher.pattern().captures_iter(line) {
+            let m = caps.get(0).expect("group 0 always matches");
+            if m.start() == m.end() {
+                continue;
+            }
+            let replacement = expand_replacement(&caps, replacement, expand);
+            let after = format!("{}{}{}", &line[..m.start()], replacement, &line[m.end()..]);
+            edits.push(ReplaceEdit {
+                id: first_id + edits.len(),
+                line: idx + 1,
+                column: line[..m.start()].chars().map(char::len_utf16).sum::<usize>() + 1,
+                before: file_search::excerpt(line, m.start()),
+                after: file_search::excerpt(&after, m.start()),
+                replacement,
+                start: line_start + m.start(),
+                end: line_start + m.end(),
+            });
+            if edits.len() >= limit {
+                return edits;
+            }
+        }
+        line_start += raw.len();
+    }
+    edits
+}
+
+fn expand_replacement(caps: &Captures, replacement: &str, expand: bool) -> String {
+    if !expand {
+        return replacement.to_string();
+    }
+    let mut out = String::new();
+    caps.expand(replacement, &mut out);
+    out
+}
+
+/// New contents for the files with selected edits (all edits when `selected` is None).
+/// Fails with a conflict listing every file that changed, or vanished, since the plan.
+pub fn prepare(
+    plan: &ReplacePlan,
+    selected: Option<&HashSet<usize>>,
+    policy: &PathPolicy,
+) -> Result<Vec<PendingWrite>, DrodeError> {
+    let mut writes = Vec::new();
+    let mut changed = Vec::new();
+
+    for file in &plan.files {
+        let mut edits: Vec<&ReplaceEdit> = file
+            .edits
+            .iter()
+            .filter(|e| selected.is_none() || selected.is_some_and(|ids| ids.contains(&e.id)))
+            .collect();
+        if edits.is_empty() {
+            continue;
+        }
+        let original = match path_policy::read_to_string(policy, &file.path) {
+            Ok(text) => text,
+            Err(DrodeError::NotFound(_)) => {
+                changed.push(file.path.clone());
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if content_hash(&original) != file.hash {
+            changed.push(file.path.clone());
+            continue;
+        }
+
+        // Back to front so earlier offsets stay valid
+        edits.sort_by_key(|e| std::cmp::Reverse(e.start));
+        let mut content = original.clone();
+        for edit in &edits {
+            content.replace_range(edit.start..edit.end, &edit.replacement);
+        }
+        writes.push(PendingWrite {
+            path: file.path.clone(),
+            original,
+            content,
+            edit_count: edits.len(),
+        });
+    }
+
+    if !changed.is_empty() {
+        return Err(conflict(changed, "since the preview"));
+    }
+    Ok(writes)
+}
+
+/// Undo of a replace: the original text for every file that still holds exactly what the
+/// replace wrote. Anything edited since is reported as a conflict and nothing is restored.
+pub fn prepare_undo(
+    files: &[crate::db::replace_undo::UndoFile],
+    policy: &PathPolicy,
+) -> Result<Vec<PendingWrite>, DrodeError> {
+    let mut writes = Vec::new();
+    let mut changed = Vec::new();
+    for file in files {
+        match path_policy::read_to_string(policy, &file.path) {
+            Ok(current) if content_hash(&current) == file.replaced_hash => writes.push(PendingWrite {
+                path: file.path.clone(),
+                original: current,
+                content: file.original.clone(),
+                edit_count: 0,
+            }),
+            Ok(_) | Err(DrodeError::NotFound(_)) => changed.push(file.path.clone()),
+            Err(e) => return Err(e),
+        }
+    }
+    if !changed.is_empty() {
+        return Err(conflict(changed, "since the replace"));
+    }
+    Ok(writes)
+}
+
+/// Write every file or none: if one write fails, the files already written get their
+/// original content back before the error is returned. Each write only goes ahead while
+/// the file still holds what `original` was read from, so an edit that lands between the
+/// check and the write is reported rather than overwritten, and so is one made to a file
+/// before it is rolled back.
+pub fn write_all(policy: &PathPolicy, writes: &[PendingWrite]) -> Result<(), DrodeError> {
+    for (i, write) in writes.iter().enumerate() {
+        let expected = Expected {
+            mtime: None,
+            hash: Some(content_hash(&write.original)),
+        };
+        if let Err(e) = path_policy::write_checked(policy, &write.path, write.content.as_bytes(), &expected) {
+            for done in writes[..i].iter().rev() {
+                let written = Expected {
+                    mtime: None,
+                    hash: Some(content_hash(&done.content)),
+                };
+                if let Err(restore_err) =
+                    path_policy::write_checked(policy, &done.path, done.original.as_bytes(), &written)
+                {
+                    eprintln!("Failed to restore {}: {}", done.path, restore_err);
+                }
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+fn conflict(paths: Vec<String>, since: &str) -> DrodeError {
+    let names: Vec<&str> = paths
+        .iter()
+        .map(|p| Path::new(p).file_name().and_then(|n| n.to_str()).unwrap_or(p))
+        .collect();
+    let message = if paths.len() == 1 {
+        format!("{} changed {}", names[0], since)
+    } else {
+        format!("{} files changed {}: {}", paths.len(), since, names.join(", "))
+    };
+    DrodeError::Conflict { message, paths }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::replace_undo::UndoFile;
+    use crate::file_search::SearchOptions;
+    use std::path::PathBuf;
+
+    struct Fixture {
+        root: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(files: &[(&str, &str)]) -> Self {
+            let root = std::env::temp_dir().join(format!("drode-replace-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&root).unwrap();
+            let root = root.canonicalize().unwrap();
+            for (name, content) in files {
+                std::fs::write(root.join(name), content).unwrap();
+            }
+            Fixture { root }
+        }
+
+        fn plan(&self, query: &str, replacement: &str) -> ReplacePlan {
+            let options = SearchOptions {
+                query: query.to_string(),
+                regex: false,
+                case_sensitive: true,
+                include: Vec::new(),
+                exclude: Vec::new(),
+                include_ignored: false,
+                context_lines: 0,
+                max_results: None,
+                max_file_size: None,
+            };
+            let searcher = Searcher::new(&self.root, options).unwrap();
+            plan(&searcher, replacement, &AtomicBool::new(false))
+        }
+
+        fn policy(&self) -> PathPolicy {
+            PathPolicy::new([&self.root])
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.root.join(name).to_string_lossy().to_string()
+        }
+
+        fn read(&self, name: &str) -> String {
+            std::fs::read_to_string(self.root.join(name)).unwrap()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn only_selected_edits_are_applied() {
+        let f = Fixture::new(&[("a.txt", "old one\nold two\n"), ("b.txt", "old three\n")]);
+        let plan = f.plan("old", "new");
+        assert_eq!(plan.edit_count, 3);
+
+        // The second edit of a.txt only; b.txt isn't touched
+        let keep: HashSet<usize> = [plan.files[0].edits[1].id].into_iter().collect();
+        let writes = prepare(&plan, Some(&keep), &f.policy()).unwrap();
+        assert_eq!(writes.len(), 1);
+        assert_eq!((writes[0].content.as_str(), writes[0].edit_count), ("old one\nnew two\n", 1));
+
+        write_all(&f.policy(), &writes).unwrap();
+        assert_eq!(f.read("a.txt"), "old one\nnew two\n");
+        assert_eq!(f.read("b.txt"), "old three\n");
+    }
+
+    #[test]
+    fn files_changed_since_the_preview_are_a_conflict() {
+        let f = Fixture::new(&[("a.txt", "old\n"), ("b.txt", "old\n"), ("c.txt", "old\n")]);
+        let plan = f.plan("old", "new");
+        std::fs::write(f.root.join("b.txt"), "old, edited\n").unwrap();
+        std::fs::remove_file(f.root.join("c.txt")).unwrap();
+
+        match prepare(&plan, None, &f.policy()) {
+            Err(DrodeError::Conflict { paths, .. }) => assert_eq!(paths, vec![f.path("b.txt"), f.path("c.txt")]),
+            other => panic!("expected a conflict, got {:?}", other.map(|w| w.len())),
+        }
+        assert_eq!(f.read("a.txt"), "old\n");
+    }
+
+    #[test]
+    fn a_failed_write_rolls_back_the_ones_before_it() {
+        let f = Fixture::new(&[("a.txt", "old\n"), ("b.txt", "old\n")]);
+        let plan = f.plan("old", "new");
+        let writes = prepare(&plan, None, &f.policy()).unwrap();
+        // Edited between the check and the write
+        std::fs::write(f.root.join("b.txt"), "someone else\n").unwrap();
+
+        let result = write_all(&f.policy(), &writes);
+        assert!(matches!(result, Err(DrodeError::FileChanged { .. })));
+        assert_eq!(f.read("a.txt"), "old\n");
+        assert_eq!(f.read("b.txt"), "someone else\n");
+    }
+
+    #[test]
+    fn undo_refuses_files_edited_after_the_replace() {
+        let f = Fixture::new(&[("a.txt", "old\n"), ("b.txt", "old\n")]);
+        let plan = f.plan("old", "new");
+        let writes = prepare(&plan, None, &f.policy()).unwrap();
+        write_all(&f.policy(), &writes).unwrap();
+        let undo: Vec<UndoFile> = writes
+            .iter()
+            .map(|w| UndoFile {
+                path: w.path.clone(),
+                original: w.original.clone(),
+                replaced_hash: content_hash(&w.content),
+            })
+            .collect();
+
+        std::fs::write(f.root.join("b.txt"), "new, edited\n").unwrap();
+        match prepare_undo(&undo, &f.policy()) {
+            Err(DrodeError::Conflict { paths, .. }) => assert_eq!(paths, vec![f.path("b.txt")]),
+            other => panic!("expected a conflict, got {:?}", other.map(|w| w.len())),
+        }
+
+        std::fs::write(f.root.join("b.txt"), "new\n").unwrap();
+        let restore = prepare_undo(&undo, &f.policy()).unwrap();
+        write_all(&f.policy(), &restore).unwrap();
+        assert_eq!((f.read("a.txt"), f.read("b.txt")), ("old\n".to_string(), "old\n".to_string()));
+    }
+}
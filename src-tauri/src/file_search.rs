@@ -0,0 +1,480 @@
+// Project-wide content search (the `search_in_files` command). Walks the project
+// honoring .gitignore/.ignore and the include/exclude globs, and hands matches back in
+// batches so the UI can show them while the walk is still going.
+
+use crate::error::DrodeError;
+use crate::file_read::{self, Encoding};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_MAX_RESULTS: usize = 2000;
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 1024 * 1024;
+const MAX_CONTEXT_LINES: usize = 10;
+// Longer lines (minified code) are cut down to this much around the first match
+const MAX_PREVIEW_BYTES: usize = 400;
+const PREVIEW_LEAD_BYTES: usize = 80;
+const BATCH_SIZE: usize = 100;
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchOptions {
+    pub query: String,
+    /// Treat `query` as a regular expression rather than literal text
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default, rename = "caseSensitive")]
+    pub case_sensitive: bool,
+    /// Gitignore-style globs; when any are given only matching files are searched
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Also search files that .gitignore/.ignore leave out
+    #[serde(default, rename = "includeIgnored")]
+    pub include_ignored: bool,
+    #[serde(default, rename = "contextLines")]
+    pub context_lines: usize,
+    #[serde(rename = "maxResults")]
+    pub max_results: Option<usize>,
+    /// Bytes; larger files are skipped
+    #[serde(rename = "maxFileSize")]
+    pub max_file_size: Option<u64>,
+}
+
+/// Highlighted part of `SearchMatch::text`, in UTF-16 code units
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// One matching line
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchMatch {
+    pub path: String,
+    #[serde(rename = "relativePath")]
+    pub relative_path: String,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in UTF-16 code units of the full line, like the editor's columns
+    pub column: usize,
+    /// The line, shortened with an ellipsis when it is very long
+    pub text: String,
+    pub ranges: Vec<MatchRange>,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SearchSummary {
+    #[serde(rename = "matchCount")]
+    pub match_count: usize,
+    #[serde(rename = "fileCount")]
+    pub file_count: usize,
+    #[serde(rename = "searchedFiles")]
+    pub searched_files: usize,
+    /// Too large or binary
+    #[serde(rename = "skippedFiles")]
+    pub skipped_files: usize,
+    /// Stopped at max results
+    pub truncated: bool,
+    pub cancelled: bool,
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+pub struct FileText<'a> {
+    pub text: &'a str,
+    /// The file isn't plain UTF-8 (another encoding, or a byte order mark), so writing
+    /// `text` back would change more than the matches
+    pub lossy: bool,
+}
+
+pub struct Searcher {
+    root: PathBuf,
+    options: SearchOptions,
+    pattern: Regex,
+}
+
+impl Searcher {
+    /// Fails with a validation error for an empty query, a bad regex or a bad glob
+    pub fn new(root: &Path, options: SearchOptions) -> Result<Self, DrodeError> {
+        if options.query.is_empty() {
+            return Err(DrodeError::validation("Search text is empty"));
+        }
+        let source = if options.regex {
+            options.query.clone()
+        } else {
+            regex::escape(&options.query)
+        };
+        // Multi-line so ^ and $ anchor to lines when a whole file is checked at once
+        let pattern = RegexBuilder::new(&source)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(true)
+            .crlf(true)
+            .build()
+            .map_err(|e| DrodeError::validation(format!("Invalid regular expression: {}", e)))?;
+
+        let searcher = Searcher {
+            root: root.to_path_buf(),
+            options,
+            pattern,
+        };
+        searcher.walker()?;
+        Ok(searcher)
+    }
+
+    fn walker(&self) -> Result<WalkBuilder, DrodeError> {
+        let mut overrides = OverrideBuilder::new(&self.root);
+        for glob in &self.options.include {
+            overrides
+                .add(glob)
+                .map_err(|e| DrodeError::validation(format!("Invalid include glob {:?}: {}", glob, e)))?;
+        }
+        for glob in &self.options.exclude {
+            overrides
+                .add(&format!("!{}", glob))
+                .map_err(|e| DrodeError::validation(format!("Invalid exclude glob {:?}: {}", glob, e)))?;
+        }
+        let overrides = overrides
+            .build()
+            .map_err(|e| DrodeError::validation(format!("Invalid glob: {}", e)))?;
+
+        // Same rules as the watcher and file index: the project's own ignore files only
+        let respect_ignore = !self.options.include_ignored;
+        let mut walker = WalkBuilder::new(&self.root);
+        walker
+            .hidden(false)
+            .parents(false)
+            .git_global(false)
+            .require_git(false)
+            .git_ignore(respect_ignore)
+            .git_exclude(respect_ignore)
+            .ignore(respect_ignore)
+            .overrides(overrides)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .sort_by_file_name(|a, b| a.cmp(b));
+        Ok(walker)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn options(&self) -> &SearchOptions {
+        &self.options
+    }
+
+    pub fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+
+    /// Search the tree, calling `on_batch` with matches as they are found. `cancel` is
+    /// checked between files.
+    pub fn run(&self, cancel: &AtomicBool, mut on_batch: impl FnMut(Vec<SearchMatch>)) -> SearchSummary {
+        let max_results = self.options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+        let mut batch = Vec::new();
+        let mut last_flush = Instant::now();
+
+        let summary = self.walk(cancel, |path, content, summary| {
+            let matches = self.search_file(path, content.text, max_results - summary.match_count);
+            if !matches.is_empty() {
+                summary.file_count += 1;
+                summary.match_count += matches.len();
+                batch.extend(matches);
+            }
+            if batch.len() >= BATCH_SIZE || (!batch.is_empty() && last_flush.elapsed() >= BATCH_INTERVAL) {
+                on_batch(std::mem::take(&mut batch));
+                last_flush = Instant::now();
+            }
+            if summary.match_count >= max_results {
+                summary.truncated = true;
+                return false;
+            }
+            true
+        });
+
+        if !batch.is_empty() {
+            on_batch(batch);
+        }
+        summary
+    }
+
+    /// Visit every searchable text file until `visit` returns false. Binary and oversized
+    /// files are counted as skipped; the counters of the summary are up to `visit`.
+    pub fn walk(
+        &self,
+        cancel: &AtomicBool,
+        mut visit: impl FnMut(&Path, FileText, &mut SearchSummary) -> bool,
+    ) -> SearchSummary {
+        let started = Instant::now();
+        let max_file_size = self.options.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE);
+        let mut summary = SearchSummary::default();
+
+        let walker = match self.walker() {
+            Ok(walker) => walker,
+            Err(_) => return summary,
+        };
+        for entry in walker.build().flatten() {
+            if cancel.load(Ordering::Relaxed) {
+                summary.cancelled = true;
+                break;
+            }
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            if !entry.metadata().is_ok_and(|m| m.len() <= max_file_size) {
+                summary.skipped_files += 1;
+                continue;
+            }
+            let Some((text, lossy)) = read_text(entry.path()) else {
+                summary.skipped_files += 1;
+                continue;
+            };
+            summary.searched_files += 1;
+            if !visit(entry.path(), FileText { text: &text, lossy }, &mut summary) {
+                break;
+            }
+        }
+
+        summary.duration_ms = started.elapsed().as_millis() as u64;
+        summary
+    }
+
+    /// Matching lines of one file, at most `limit`
+    pub fn search_file(&self, path: &Path, content: &str, limit: usize) -> Vec<SearchMatch> {
+        if limit == 0 || !self.pattern.is_match(content) {
+            return Vec::new();
+        }
+        let relative_path = path
+            .strip_prefix(&self.root)
+            .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_else(|_| path.to_string_lossy().to_string());
+        let context = self.options.context_lines.min(MAX_CONTEXT_LINES);
+        let lines: Vec<&str> = content.lines().collect();
+
+        let mut matches = Vec::new();
+        for (idx, line) in lines.iter().enumerate() {
+            // Empty matches (`a*`, `^`) have nothing to highlight
+            let spans: Vec<(usize, usize)> = self
+                .pattern
+                .find_iter(line)
+                .filter(|m| m.start() < m.end())
+                .map(|m| (m.start(), m.end()))
+                .collect();
+            let Some(&(first_start, _)) = spans.first() else {
+                continue;
+            };
+
+            let (text, ranges) = preview(line, &spans);
+            matches.push(SearchMatch {
+                path: path.to_string_lossy().to_string(),
+                relative_path: relative_path.clone(),
+                line: idx + 1,
+                column: utf16_len(&line[..first_start]) + 1,
+                text,
+                ranges,
+                before: lines[idx.saturating_sub(context)..idx].iter().map(|l| l.to_string()).collect(),
+                after: lines[idx + 1..(idx + 1 + context).min(lines.len())]
+                    .iter()
+                    .map(|l| l.to_string())
+                    .collect(),
+            });
+            if matches.len() >= limit {
+                break;
+            }
+        }
+        matches
+    }
+}
+
+/// The text and whether it had to be decoded lossily; None for binary files or ones that
+/// can't be read
+fn read_text(path: &Path) -> Option<(String, bool)> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+    let found = file_read::detect(&bytes);
+    if found.binary {
+        return None;
+    }
+    // Anything but plain UTF-8 would be rewritten as UTF-8 by a replace
+    let lossy = found.encoding != Encoding::Utf8 || found.bom > 0;
+    Some((found.encoding.decode(&bytes[found.bom..]), lossy))
+}
+
+/// The line (or a window of it around the first match) and the match spans within it
+fn preview(line: &str, spans: &[(usize, usize)]) -> (String, Vec<MatchRange>) {
+    let (from, to) = window(line, spans[0].0);
+    let mut text = String::new();
+    if from > 0 {
+        text.push('…');
+    }
+    let offset = utf16_len(&text);
+    text.push_str(&line[from..to]);
+    if to < line.len() {
+        text.push('…');
+    }
+
+    let ranges = spans
+        .iter()
+        .filter(|&&(start, _)| start >= from && start < to)
+        .map(|&(start, end)| MatchRange {
+            start: offset + utf16_len(&line[from..start]),
+            end: offset + utf16_len(&line[from..end.min(to)]),
+        })
+        .collect();
+    (text, ranges)
+}
+
+/// A long line cut down to the part around byte `at`, with ellipses where it was cut
+pub fn excerpt(line: &str, at: usize) -> String {
+    let (from, to) = window(line, at);
+    format!(
+        "{}{}{}",
+        if from > 0 { "…" } else { "" },
+        &line[from..to],
+        if to < line.len() { "…" } else { "" }
+    )
+}
+
+/// Byte range of a line worth showing for a match at `at`; the whole line unless it is long
+fn window(line: &str, at: usize) -> (usize, usize) {
+    if line.len() <= MAX_PREVIEW_BYTES {
+        return (0, line.len());
+    }
+    let from = floor_boundary(line, at.saturating_sub(PREVIEW_LEAD_BYTES));
+    (from, floor_boundary(line, (from + MAX_PREVIEW_BYTES).min(line.len())))
+}
+
+fn floor_boundary(text: &str, mut idx: usize) -> usize {
+    while !text.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("drode-search-{}", uuid::Uuid::new_v4()));
+        for (path, content) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    fn options(query: &str) -> SearchOptions {
+        SearchOptions {
+            query: query.to_string(),
+            regex: false,
+            case_sensitive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            include_ignored: false,
+            context_lines: 0,
+            max_results: None,
+            max_file_size: None,
+        }
+    }
+
+    fn search(root: &Path, options: SearchOptions) -> (Vec<SearchMatch>, SearchSummary) {
+        let mut found = Vec::new();
+        let summary = Searcher::new(root, options).unwrap().run(&AtomicBool::new(false), |batch| found.extend(batch));
+        (found, summary)
+    }
+
+    fn utf16_slice(text: &str, range: MatchRange) -> String {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        String::from_utf16(&units[range.start..range.end]).unwrap()
+    }
+
+    #[test]
+    fn long_lines_are_cut_with_utf16_positions() {
+        let line = format!("{}😀 Needle and needle{}", "é".repeat(300), "x".repeat(600));
+        let searcher = Searcher::new(Path::new("/p"), options("needle")).unwrap();
+        let matches = searcher.search_file(Path::new("/p/src/a.txt"), &format!("first\n{}\n", line), 10);
+
+        assert_eq!(matches.len(), 1);
+        let found = &matches[0];
+        assert_eq!((found.line, found.relative_path.as_str()), (2, "src/a.txt"));
+        // 300 × é, then the emoji's surrogate pair and a space
+        assert_eq!(found.column, 300 + 2 + 1 + 1);
+        assert!(found.text.starts_with('…') && found.text.ends_with('…'));
+        assert!(found.text.len() < line.len());
+        let highlighted: Vec<String> = found.ranges.iter().map(|&r| utf16_slice(&found.text, r)).collect();
+        assert_eq!(highlighted, vec!["Needle", "needle"]);
+    }
+
+    #[test]
+    fn globs_and_ignore_files_pick_the_files() {
+        let root = project(&[
+            (".gitignore", "target/\n"),
+            ("src/a.rs", "needle\n"),
+            ("src/b.ts", "needle\n"),
+            ("docs/c.md", "needle\n"),
+            ("target/d.rs", "needle\n"),
+        ]);
+        let files = |options: SearchOptions| -> Vec<String> {
+            search(&root, options).0.into_iter().map(|m| m.relative_path).collect()
+        };
+
+        assert_eq!(files(options("needle")), vec!["docs/c.md", "src/a.rs", "src/b.ts"]);
+        let mut rust = options("needle");
+        rust.include = vec!["*.rs".to_string()];
+        assert_eq!(files(rust.clone()), vec!["src/a.rs"]);
+        rust.include_ignored = true;
+        assert_eq!(files(rust), vec!["src/a.rs", "target/d.rs"]);
+        let mut no_docs = options("needle");
+        no_docs.exclude = vec!["docs/".to_string(), "*.ts".to_string()];
+        assert_eq!(files(no_docs), vec!["src/a.rs"]);
+
+        let mut bad = options("needle");
+        bad.include = vec!["a{".to_string()];
+        assert!(matches!(Searcher::new(&root, bad), Err(DrodeError::Validation(_))));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn max_results_stops_the_walk() {
+        let root = project(&[("a.txt", "hit\nhit\n"), ("b.txt", "hit\nhit\n"), ("c.txt", "hit\nhit\n")]);
+        let mut limited = options("hit");
+        limited.max_results = Some(3);
+        let (found, summary) = search(&root, limited);
+
+        assert_eq!(found.len(), 3);
+        assert_eq!((summary.match_count, summary.file_count, summary.searched_files), (3, 2, 2));
+        assert!(summary.truncated);
+        assert_eq!(found[2].relative_path, "b.txt");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn cancelling_stops_between_files() {
+        let root = project(&[("a.txt", "hit\n"), ("b.txt", "hit\n"), ("c.txt", "hit\n")]);
+        let searcher = Searcher::new(&root, options("hit")).unwrap();
+        let cancel = AtomicBool::new(false);
+        let summary = searcher.walk(&cancel, |_, _, _| {
+            cancel.store(true, Ordering::Relaxed);
+            true
+        });
+
+        assert!(summary.cancelled);
+        assert_eq!(summary.searched_files, 1);
+        assert!(!summary.truncated);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+// Which entries read_directory lists. Each project has its own rules, kept as JSON in its
+// project settings: whether hidden (dot) files show, gitignore-style exclude patterns, and
+// whether gitignored entries are left out or only flagged so the tree can dim them.
+
+use crate::db::{projects, settings};
+use crate::error::DrodeError;
+use crate::file_index;
+use crate::watcher::IgnoreRules;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Project setting holding the rules
+pub const RULES_KEY: &str = "visibility_rules";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VisibilityRules {
+    /// Show files and folders whose name starts with a dot. `.gitignore` always shows.
+    #[serde(rename = "showHidden", default)]
+    pub show_hidden: bool,
+    /// Gitignore-style patterns relative to the project root, e.g. `node_modules` or
+    /// `target/`. A `!` pattern brings an entry back, such as `!.github` with hidden
+    /// files off.
+    #[serde(default)]
+    pub excludes: Vec<String>,
+    /// Leave gitignored entries out rather than flag them with `isIgnored`
+    #[serde(rename = "hideIgnored", default)]
+    pub hide_ignored: bool,
+}
+
+pub fn rules(conn: &Connection, project_id: i64) -> VisibilityRules {
+    projects::get_setting(conn, project_id, RULES_KEY)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_rules(conn: &Connection, project_id: i64, rules: &VisibilityRules) -> Result<(), DrodeError> {
+    file_index::validate_excludes(&rules.excludes)?;
+    projects::set_setting(conn, project_id, RULES_KEY, &serde_json::to_string(rules)?)?;
+    Ok(())
+}
+
+/// The rules in effect for one listing
+pub struct Visibility {
+    hide_ignored: bool,
+    excludes: Option<Gitignore>,
+    gitignore: IgnoreRules,
+}
+
+impl Visibility {
+    /// For listing `dir` (a real path): the current project's rules when it is inside the
+    /// project, the defaults otherwise
+    pub fn load(conn: &Connection, dir: &Path) -> Self {
+        let project = settings::get(conn, "current_project").and_then(|path| {
+            let root = Path::new(&path).canonicalize().ok()?;
+            dir.starts_with(&root).then_some((path, root))
+        });
+        match project {
+            Some((path, root)) => {
+                let rules = projects::get_by_path(conn, &path)
+                    .ok()
+                    .flatten()
+                    .map(|project| rules(conn, project.id))
+                    .unwrap_or_default();
+                Visibility::new(&root, &rules)
+            }
+            None => Visibility::new(dir, &VisibilityRules::default()),
+        }
+    }
+
+    pub fn new(root: &Path, rules: &VisibilityRules) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        let mut lines: Vec<&str> = Vec::new();
+        if !rules.show_hidden {
+            lines.extend([".*", "!.gitignore"]);
+        }
+        lines.extend(rules.excludes.iter().map(String::as_str));
+        for line in lines {
+            if let Err(e) = builder.add_line(None, line) {
+                eprintln!("Skipping exclude pattern {:?}: {}", line, e);
+            }
+        }
+        Visibility {
+            hide_ignored: rules.hide_ignored,
+            excludes: builder.build().ok(),
+            gitignore: IgnoreRules::new(root),
+        }
+    }
+
+    /// Whether the entry is gitignored, or None when it isn't listed at all
+    pub fn check(&mut self, path: &Path, is_dir: bool) -> Option<bool> {
+        if self.excludes.as_ref().is_some_and(|e| e.matched(path, is_dir).is_ignore()) {
+            return None;
+        }
+        let ignored = self.gitignore.is_ignored(path, is_dir);
+        if ignored && self.hide_ignored {
+            return None;
+        }
+        Some(ignored)
+    }
+}
@@ -0,0 +1,323 @@
+// Git status of a project for the file explorer, read with the git CLI. Statuses are cached
+// per project in AppState once asked for; the project's watcher thread refreshes them when
+// files or the repository (.git) change and announces each with `git-status-changed`.
+
+use crate::error::DrodeError;
+use crate::state::AppState;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use tauri::{AppHandle, Emitter, Manager};
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+pub struct GitStatus {
+    /// False outside a git work tree; everything else is then empty
+    #[serde(rename = "isRepo")]
+    pub is_repo: bool,
+    /// None when HEAD is detached
+    pub branch: Option<String>,
+    /// Commit HEAD points at; None before the first commit
+    pub head: Option<String>,
+    pub upstream: Option<String>,
+    /// Commits ahead of / behind the upstream
+    pub ahead: u32,
+    pub behind: u32,
+    /// Changed files by absolute path, spelled like the paths `read_directory` returns
+    pub files: BTreeMap<String, PathStatus>,
+    /// Every folder holding a changed file, up to the project root, by the same key
+    pub directories: BTreeMap<String, DirStatus>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PathStatus {
+    /// modified, added, deleted, renamed, copied, typechange, untracked or conflicted
+    pub kind: String,
+    /// Has changes in the index
+    pub staged: bool,
+    /// Has changes in the work tree that aren't staged
+    pub unstaged: bool,
+    /// Where a renamed or copied file came from
+    #[serde(rename = "oldPath", skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DirStatus {
+    /// The most pressing state below the folder: conflicted, then modified (anything
+    /// tracked that changed), then untracked
+    pub kind: String,
+    /// Changed files below the folder
+    pub count: usize,
+}
+
+/// Run `git status` for the project. Only paths inside `project_path` are reported, also
+/// when the project is a folder within a larger repository.
+pub fn read(project_path: &str) -> Result<GitStatus, DrodeError> {
+    let Some(prefix) = git(project_path, &["rev-parse", "--show-prefix"])? else {
+        return Ok(GitStatus {
+            updated_at: chrono::Utc::now().timestamp_millis(),
+            ..Default::default()
+        });
+    };
+    // --no-optional-locks: a plain status must not rewrite .git/index, or the watcher would
+    // see the write and refresh again
+    let output = git(
+        project_path,
+        &[
+            "--no-optional-locks",
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "--untracked-files=all",
+            "-z",
+            "--",
+            ".",
+        ],
+    )?
+    .ok_or_else(|| DrodeError::internal(format!("git status failed in {}", project_path)))?;
+    Ok(parse(project_path, prefix.trim_end_matches('\n'), &output))
+}
+
+/// Run git in `dir`; None when it exits with an error (not a repository, say)
+fn git(dir: &str, args: &[&str]) -> Result<Option<String>, DrodeError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| DrodeError::spawn("git", e))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Parse `git status --porcelain=v2 --branch -z`. Paths in the output are relative to the
+/// repository; `prefix` is where the project sits in it ("" at the top).
+pub fn parse(project_path: &str, prefix: &str, output: &str) -> GitStatus {
+    let root = Path::new(project_path);
+    let absolute = |rel: &str| -> Option<String> {
+        let rel = rel.strip_prefix(prefix)?;
+        Some(root.join(rel).to_string_lossy().to_string())
+    };
+    let mut status = GitStatus {
+        is_repo: true,
+        updated_at: chrono::Utc::now().timestamp_millis(),
+        ..Default::default()
+    };
+
+    let mut records = output.split('\0').filter(|r| !r.is_empty());
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.oid" if value != "(initial)" => status.head = Some(value.to_string()),
+                "branch.head" if value != "(detached)" => status.branch = Some(value.to_string()),
+                "branch.upstream" => status.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    for count in value.split(' ') {
+                        if let Some(n) = count.strip_prefix('+') {
+                            status.ahead = n.parse().unwrap_or(0);
+                        } else if let Some(n) = count.strip_prefix('-') {
+                            status.behind = n.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let ((kind, staged, unstaged), path, old_path) = match record.split_at(record.find(' ').unwrap_or(0)) {
+            ("1", rest) => {
+                // 1 XY sub mH mI mW hH hI path
+                let fields: Vec<&str> = rest[1..].splitn(8, ' ').collect();
+                let [xy, .., path] = fields.as_slice() else { continue };
+                (changed(xy), *path, None)
+            }
+            ("2", rest) => {
+                // 2 XY sub mH mI mW hH hI Xscore path, then the original path as its own record
+                let fields: Vec<&str> = rest[1..].splitn(9, ' ').collect();
+                let [xy, .., path] = fields.as_slice() else { continue };
+                (changed(xy), *path, records.next())
+            }
+            ("u", rest) => {
+                // u XY sub m1 m2 m3 mW h1 h2 h3 path
+                let fields: Vec<&str> = rest[1..].splitn(10, ' ').collect();
+                let Some(path) = fields.last() else { continue };
+                (("conflicted", true, true), *path, None)
+            }
+            ("?", rest) => (("untracked", false, true), &rest[1..], None),
+            _ => continue,
+        };
+        let Some(key) = absolute(path) else { continue };
+        status.files.insert(
+            key,
+            PathStatus {
+                kind: kind.to_string(),
+                staged,
+                unstaged,
+                old_path: old_path.and_then(absolute),
+            },
+        );
+    }
+
+    for (path, file) in &status.files {
+        let mut dir = Path::new(path).parent();
+        while let Some(current) = dir {
+            if !current.starts_with(root) {
+                break;
+            }
+            let entry = status
+                .directories
+                .entry(current.to_string_lossy().to_string())
+                .or_insert_with(|| DirStatus {
+                    kind: "untracked".to_string(),
+                    count: 0,
+                });
+            entry.count += 1;
+            if severity(&file.kind) > severity(&entry.kind) {
+                entry.kind = dir_kind(&file.kind).to_string();
+            }
+            dir = current.parent();
+        }
+    }
+    status
+}
+
+/// (kind, staged, unstaged) of an ordinary or renamed entry's XY field
+fn changed(xy: &str) -> (&'static str, bool, bool) {
+    let mut chars = xy.chars();
+    let (x, y) = (chars.next().unwrap_or('.'), chars.next().unwrap_or('.'));
+    // The work tree state is what the file looks like on disk, so it wins
+    let code = if y != '.' { y } else { x };
+    let kind = match code {
+        'A' => "added",
+        'D' => "deleted",
+        'R' => "renamed",
+        'C' => "copied",
+        'T' => "typechange",
+        _ => "modified",
+    };
+    (kind, x != '.', y != '.')
+}
+
+fn severity(kind: &str) -> u8 {
+    match kind {
+        "conflicted" => 2,
+        "untracked" => 0,
+        _ => 1,
+    }
+}
+
+fn dir_kind(kind: &str) -> &'static str {
+    match severity(kind) {
+        2 => "conflicted",
+        1 => "modified",
+        _ => "untracked",
+    }
+}
+
+/// The cached status, read first if there is none yet
+pub fn cached(app_handle: &AppHandle, project_path: &str) -> Result<GitStatus, DrodeError> {
+    let state = app_handle.state::<AppState>();
+    if let Some(status) = state.git_statuses.lock().unwrap().get(project_path) {
+        return Ok(status.clone());
+    }
+    let status = read(project_path)?;
+    state
+        .git_statuses
+        .lock()
+        .unwrap()
+        .insert(project_path.to_string(), status.clone());
+    Ok(status)
+}
+
+/// Re-read a project's status if it is cached, and emit `git-status-changed` when it
+/// changed. Called by the watcher.
+pub fn refresh(app_handle: &AppHandle, project_path: &str) {
+    let state = app_handle.state::<AppState>();
+    let Some(previous) = state.git_statuses.lock().unwrap().get(project_path).cloned() else {
+        return;
+    };
+    let status = match read(project_path) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("Failed to read git status of {}: {}", project_path, e);
+            return;
+        }
+    };
+    let unchanged = GitStatus {
+        updated_at: status.updated_at,
+        ..previous
+    } == status;
+    state
+        .git_statuses
+        .lock()
+        .unwrap()
+        .insert(project_path.to_string(), status.clone());
+    if !unchanged {
+        let mut event = serde_json::to_value(&status).unwrap_or_default();
+        event["projectPath"] = serde_json::json!(project_path);
+        let _ = app_handle.emit("git-status-changed", event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = concat!(
+        "# branch.oid 1234abcd\0",
+        "# branch.head main\0",
+        "# branch.upstream origin/main\0",
+        "# branch.ab +2 -1\0",
+        "1 .M N... 100644 100644 100644 aaaa bbbb app/src/main.rs\0",
+        "1 A. N... 000000 100644 100644 0000 cccc app/src/new file.rs\0",
+        "2 R. N... 100644 100644 100644 dddd dddd R100 app/lib/b.rs\0app/lib/a.rs\0",
+        "u UU N... 100644 100644 100644 100644 eeee ffff 0000 app/src/conflict.rs\0",
+        "? app/notes.txt\0",
+        "? other/outside.txt\0",
+    );
+
+    #[test]
+    fn parses_porcelain_v2_within_the_project() {
+        let status = parse("/work/app", "app/", OUTPUT);
+
+        assert!(status.is_repo);
+        assert_eq!(status.head.as_deref(), Some("1234abcd"));
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+
+        let file = |path: &str| status.files.get(path).unwrap_or_else(|| panic!("{} missing", path));
+        let modified = file("/work/app/src/main.rs");
+        assert_eq!((modified.kind.as_str(), modified.staged, modified.unstaged), ("modified", false, true));
+        let added = file("/work/app/src/new file.rs");
+        assert_eq!((added.kind.as_str(), added.staged, added.unstaged), ("added", true, false));
+        let renamed = file("/work/app/lib/b.rs");
+        assert_eq!(renamed.kind, "renamed");
+        assert_eq!(renamed.old_path.as_deref(), Some("/work/app/lib/a.rs"));
+        assert_eq!(file("/work/app/src/conflict.rs").kind, "conflicted");
+        assert_eq!(file("/work/app/notes.txt").kind, "untracked");
+        // Outside the project, and the rename's original path isn't an entry of its own
+        assert_eq!(status.files.len(), 5);
+
+        let dir = |path: &str| status.directories.get(path).map(|d| (d.kind.as_str(), d.count));
+        assert_eq!(dir("/work/app/src"), Some(("conflicted", 3)));
+        assert_eq!(dir("/work/app/lib"), Some(("modified", 1)));
+        assert_eq!(dir("/work/app"), Some(("conflicted", 5)));
+        assert_eq!(dir("/work"), None);
+    }
+
+    #[test]
+    fn detached_head_before_the_first_commit() {
+        let status = parse("/work", "", "# branch.oid (initial)\0# branch.head (detached)\0? a.txt\0");
+        assert_eq!((status.head, status.branch, status.upstream), (None, None, None));
+        let dir = &status.directories["/work"];
+        assert_eq!((dir.kind.as_str(), dir.count), ("untracked", 1));
+    }
+}
@@ -1,6 +1,19 @@
 mod state;
 mod db;
 mod commands;
+mod error;
+mod watcher;
+mod file_index;
+mod file_ops;
+mod file_search;
+mod file_replace;
+mod path_policy;
+mod file_read;
+mod file_visibility;
+mod git_status;
+mod local_history;
+mod symbol_index;
+mod dialogs;
 
 use state::AppState;
 use tauri::Manager;
@@ -15,6 +28,8 @@ pub fn run() {
         .setup(|app| {
             let state = AppState::new(app.handle());
             app.manage(state);
+            commands::backup::start_backup_scheduler(app.handle().clone());
+            commands::files::watch_current_project(app.handle());
 
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -30,16 +45,62 @@ pub fn run() {
             commands::projects::get_recent_projects,
             commands::projects::get_current_project,
             commands::projects::set_current_project,
+            commands::projects::open_project_folder,
             commands::projects::remove_recent_project,
+            commands::projects::list_projects,
+            commands::projects::update_project,
+            commands::projects::relink_project,
+            commands::projects::get_project_settings,
+            commands::projects::get_project_setting,
+            commands::projects::set_project_setting,
             // File system
             commands::files::read_directory,
             commands::files::read_file,
+            commands::files::read_file_range,
+            commands::files::get_file_info,
+            commands::files::get_file_version,
             commands::files::write_file,
             commands::files::create_file,
             commands::files::create_directory,
             commands::files::delete_file,
             commands::files::rename_file,
+            commands::files::copy_paths,
+            commands::files::move_paths,
+            commands::files::duplicate_path,
+            commands::files::list_deleted_files,
+            commands::files::restore_deleted,
             commands::files::file_exists,
+            commands::files::get_allowed_paths,
+            commands::files::add_allowed_path,
+            commands::files::remove_allowed_path,
+            commands::files::watch_project,
+            commands::files::unwatch_project,
+            commands::files::list_watched_projects,
+            commands::files::find_files,
+            commands::files::get_file_index_excludes,
+            commands::files::set_file_index_excludes,
+            commands::files::get_visibility_rules,
+            commands::files::set_visibility_rules,
+            commands::files::search_in_files,
+            commands::files::cancel_search,
+            // Find and replace
+            commands::replace::plan_replace,
+            commands::replace::apply_replace,
+            commands::replace::undo_replace,
+            commands::replace::list_replace_undos,
+            // Local history
+            commands::history::list_file_history,
+            commands::history::read_file_history_version,
+            commands::history::diff_file_history,
+            commands::history::restore_file_version,
+            commands::history::get_history_settings,
+            commands::history::set_history_settings,
+            // Git
+            commands::git::git_status,
+            // Symbols
+            commands::symbols::search_symbols,
+            commands::symbols::document_outline,
+            commands::symbols::find_definition,
             // Claude CLI
             commands::claude::start_claude_cli,
             commands::claude::send_to_claude,
@@ -61,6 +122,34 @@ pub fn run() {
             commands::conversations::rename_conversation,
             commands::conversations::get_active_conversation,
             commands::conversations::set_active_conversation,
+            commands::conversations::list_trashed_conversations,
+            commands::conversations::restore_conversation,
+            commands::conversations::empty_trash,
+            commands::conversations::get_trash_retention_days,
+            commands::conversations::set_trash_retention_days,
+            commands::conversations::set_conversation_pinned,
+            commands::conversations::set_conversation_archived,
+            commands::conversations::add_conversation_tag,
+            commands::conversations::remove_conversation_tag,
+            commands::conversations::list_conversation_tags,
+            commands::conversations::search_conversations,
+            commands::conversations::import_claude_sessions,
+            commands::conversations::export_conversation_markdown,
+            // Generated titles and summaries
+            commands::summaries::get_summary_settings,
+            commands::summaries::set_summary_settings,
+            commands::summaries::summarize_conversation,
+            // Bookmarks
+            commands::bookmarks::add_bookmark,
+            commands::bookmarks::update_bookmark,
+            commands::bookmarks::remove_bookmark,
+            commands::bookmarks::list_project_bookmarks,
+            commands::bookmarks::list_conversation_bookmarks,
+            // File edit history
+            commands::file_edits::list_file_edits,
+            commands::file_edits::list_edited_files,
+            commands::file_edits::get_file_edit_diff,
+            commands::file_edits::export_file_edits_patch,
             // Terminal
             commands::terminal::run_terminal_command,
             commands::terminal::kill_terminal_process,
@@ -71,11 +160,23 @@ pub fn run() {
             commands::activity::log_activity,
             commands::activity::get_activity_log,
             commands::activity::clear_activity_log,
+            // Usage analytics
+            commands::usage::get_usage_stats,
+            commands::usage::export_usage_csv,
+            // Database backups
+            commands::backup::list_db_backups,
+            commands::backup::create_db_backup,
+            commands::backup::restore_db_backup,
+            commands::backup::check_db_integrity,
+            commands::backup::get_db_integrity,
             // OAuth
             commands::oauth::oauth_start,
             commands::oauth::oauth_get_token,
             commands::oauth::oauth_get_status,
             commands::oauth::oauth_revoke,
+            commands::oauth::oauth_get_key_status,
+            commands::oauth::oauth_unlock,
+            commands::oauth::oauth_rotate_key,
             // GitHub
             commands::github::github_list_repos,
             commands::github::github_get_repo_tree,
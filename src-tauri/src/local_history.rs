@@ -0,0 +1,430 @@
+// Local history: a snapshot of a file every time drode saves, renames, deletes or restores
+// it, and, when enabled, whenever the watcher sees it change on disk. Snapshots are stored
+// once per distinct content, gzip-compressed and named by their SHA-256, under
+// `history/objects` in the config folder; db/file_history.rs keeps the index. Retention
+// drops entries by age and then by total size.
+
+use crate::db::file_history::{self, HistoryEntry};
+use crate::error::DrodeError;
+use crate::path_policy::{self, FileVersion, PathPolicy};
+use crate::watcher::{ChangeKind, FsChange};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ignore::WalkBuilder;
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const ENABLED_KEY: &str = "local_history_enabled";
+pub const TRACK_EXTERNAL_KEY: &str = "local_history_track_external";
+pub const MAX_AGE_DAYS_KEY: &str = "local_history_max_age_days";
+pub const MAX_SIZE_MB_KEY: &str = "local_history_max_size_mb";
+const DEFAULT_MAX_AGE_DAYS: u32 = 30;
+const DEFAULT_MAX_SIZE_MB: u64 = 256;
+
+// Larger files are not snapshotted
+const MAX_SNAPSHOT_BYTES: u64 = 5 * 1024 * 1024;
+// Deleting or renaming a folder snapshots at most this many of the files in it
+const MAX_TREE_FILES: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    /// Also snapshot changes made outside drode, as the watcher sees them
+    #[serde(rename = "trackExternal")]
+    pub track_external: bool,
+    /// 0 keeps entries regardless of age
+    #[serde(rename = "maxAgeDays")]
+    pub max_age_days: u32,
+    /// 0 doesn't limit the size
+    #[serde(rename = "maxSizeMb")]
+    pub max_size_mb: u64,
+}
+
+pub fn settings(conn: &Connection) -> HistorySettings {
+    HistorySettings {
+        enabled: crate::db::settings::get(conn, ENABLED_KEY).as_deref() != Some("false"),
+        track_external: crate::db::settings::get(conn, TRACK_EXTERNAL_KEY).as_deref() == Some("true"),
+        max_age_days: crate::db::settings::get(conn, MAX_AGE_DAYS_KEY)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_AGE_DAYS),
+        max_size_mb: crate::db::settings::get(conn, MAX_SIZE_MB_KEY)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_SIZE_MB),
+    }
+}
+
+pub fn save_settings(conn: &Connection, settings: &HistorySettings) -> Result<(), rusqlite::Error> {
+    let flag = |on: bool| if on { "true" } else { "false" };
+    crate::db::settings::set(conn, ENABLED_KEY, flag(settings.enabled))?;
+    crate::db::settings::set(conn, TRACK_EXTERNAL_KEY, flag(settings.track_external))?;
+    crate::db::settings::set(conn, MAX_AGE_DAYS_KEY, &settings.max_age_days.to_string())?;
+    crate::db::settings::set(conn, MAX_SIZE_MB_KEY, &settings.max_size_mb.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// Saved through drode
+    Save,
+    /// Found on disk different from the last snapshot
+    External,
+    Rename,
+    Delete,
+    Restore,
+}
+
+impl Source {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Save => "save",
+            Source::External => "external",
+            Source::Rename => "rename",
+            Source::Delete => "delete",
+            Source::Restore => "restore",
+        }
+    }
+}
+
+/// The object store
+#[derive(Debug, Clone)]
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        History { dir: dir.into() }
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.dir.join("objects").join(&hash[..2]).join(&hash[2..])
+    }
+
+    /// Record `content` as the state of `path`. Saves and external changes that match the
+    /// newest snapshot are not recorded again. Returns the new entry's id.
+    ///
+    /// The object is checked, written and indexed in one write transaction, and `prune`
+    /// removes object files inside its own, so a prune on another connection can't delete
+    /// an object between this finding it stored and the entry referring to it.
+    pub fn snapshot(
+        &self,
+        conn: &Connection,
+        path: &Path,
+        old_path: Option<&Path>,
+        source: Source,
+        content: &[u8],
+    ) -> Result<Option<i64>, DrodeError> {
+        let key = path.to_string_lossy();
+        let hash = path_policy::content_hash(content);
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+        if matches!(source, Source::Save | Source::External)
+            && file_history::latest_hash(&tx, &key)?.as_deref() == Some(hash.as_str())
+        {
+            return Ok(None);
+        }
+
+        let stored_size = self.store(&tx, &hash, content)?;
+        let old_key = old_path.map(|p| p.to_string_lossy().to_string());
+        let id = file_history::insert(
+            &tx,
+            &key,
+            old_key.as_deref(),
+            source.as_str(),
+            &hash,
+            content.len() as u64,
+            stored_size,
+        )?;
+        tx.commit()?;
+
+        let settings = settings(conn);
+        if settings.max_size_mb > 0 && file_history::stored_size(conn)? > settings.max_size_mb * 1024 * 1024 {
+            prune(conn, self)?;
+        }
+        Ok(Some(id))
+    }
+
+    /// Snapshot the file as it is on disk; nothing for missing, oversized or non-regular files
+    pub fn snapshot_file(
+        &self,
+        conn: &Connection,
+        path: &Path,
+        old_path: Option<&Path>,
+        source: Source,
+    ) -> Result<Option<i64>, DrodeError> {
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Ok(None);
+        };
+        if !metadata.is_file() || metadata.len() > MAX_SNAPSHOT_BYTES {
+            return Ok(None);
+        }
+        let content = std::fs::read(path).map_err(|e| DrodeError::io(path, e))?;
+        self.snapshot(conn, path, old_path, source, &content)
+    }
+
+    /// Snapshot a file, or the files of a folder (respecting its .gitignore). For renames
+    /// `old_path` is where `path` was before.
+    pub fn snapshot_tree(
+        &self,
+        conn: &Connection,
+        path: &Path,
+        old_path: Option<&Path>,
+        source: Source,
+    ) -> Result<usize, DrodeError> {
+        // A link is removed or moved as a link; what it points to is not part of it
+        if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Ok(0);
+        }
+        if !path.is_dir() {
+            return Ok(self.snapshot_file(conn, path, old_path, source)?.map_or(0, |_| 1));
+        }
+        let mut count = 0;
+        let walker = WalkBuilder::new(path)
+            .hidden(false)
+            .parents(false)
+            .git_global(false)
+            .require_git(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walker.flatten() {
+            if count >= MAX_TREE_FILES {
+                break;
+            }
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let old = match (old_path, entry.path().strip_prefix(path)) {
+                (Some(old_root), Ok(rel)) => Some(old_root.join(rel)),
+                _ => None,
+            };
+            if self.snapshot_file(conn, entry.path(), old.as_deref(), source)?.is_some() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Write the compressed object unless it is already stored; returns its size on disk
+    fn store(&self, conn: &Connection, hash: &str, content: &[u8]) -> Result<u64, DrodeError> {
+        let target = self.object_path(hash);
+        if file_history::has_object(conn, hash)? {
+            if let Ok(metadata) = std::fs::metadata(&target) {
+                return Ok(metadata.len());
+            }
+        }
+        let dir = target.parent().expect("object paths have a parent");
+        std::fs::create_dir_all(dir).map_err(|e| DrodeError::io(dir, e))?;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content).map_err(|e| DrodeError::io(&target, e))?;
+        let compressed = encoder.finish().map_err(|e| DrodeError::io(&target, e))?;
+
+        let temp = dir.join(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&temp, &compressed)
+            .and_then(|()| std::fs::rename(&temp, &target))
+            .map_err(|e| {
+                let _ = std::fs::remove_file(&temp);
+                DrodeError::io(&target, e)
+            })?;
+        Ok(compressed.len() as u64)
+    }
+
+    pub fn load(&self, hash: &str) -> Result<Vec<u8>, DrodeError> {
+        let path = self.object_path(hash);
+        let file = std::fs::File::open(&path).map_err(|e| DrodeError::io(&path, e))?;
+        let mut content = Vec::new();
+        GzDecoder::new(file)
+            .read_to_end(&mut content)
+            .map_err(|e| DrodeError::io(&path, e))?;
+        Ok(content)
+    }
+}
+
+/// Apply the retention settings; returns how many objects were removed
+pub fn prune(conn: &Connection, history: &History) -> Result<usize, DrodeError> {
+    let settings = settings(conn);
+    let cutoff = (settings.max_age_days > 0)
+        .then(|| chrono::Utc::now().timestamp_millis() - i64::from(settings.max_age_days) * 24 * 60 * 60 * 1000);
+    let max_bytes = (settings.max_size_mb > 0).then(|| settings.max_size_mb * 1024 * 1024);
+
+    // Files go before the commit: until then a snapshot can't see the rows gone and
+    // reuse an object that is about to be removed
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let orphans = file_history::prune(&tx, cutoff, max_bytes)?;
+    for hash in &orphans {
+        let _ = std::fs::remove_file(history.object_path(hash));
+    }
+    tx.commit()?;
+    Ok(orphans.len())
+}
+
+/// Snapshot what the watcher saw change, if tracking external changes is on. Changes drode
+/// made itself match their last snapshot and are skipped.
+pub fn record_changes(conn: &Connection, history: &History, changes: &[FsChange]) {
+    let settings = settings(conn);
+    if !settings.enabled || !settings.track_external {
+        return;
+    }
+    for change in changes {
+        if change.is_dir || change.kind == ChangeKind::Delete {
+            continue;
+        }
+        if let Err(e) = history.snapshot_file(conn, Path::new(&change.path), None, Source::External) {
+            eprintln!("Failed to snapshot {}: {}", change.path, e);
+        }
+    }
+}
+
+/// Unified diff between two snapshots, or a snapshot and the file as it is now (under its
+/// current name) when `to` is None
+pub fn diff(
+    conn: &Connection,
+    history: &History,
+    policy: &PathPolicy,
+    from: &HistoryEntry,
+    to: Option<&HistoryEntry>,
+) -> Result<String, DrodeError> {
+    let old = history.load(&from.hash)?;
+    let old_label = format!("{} ({})", from.path, format_ts(from.created_at));
+    let (new, new_label) = match to {
+        Some(entry) => (history.load(&entry.hash)?, format!("{} ({})", entry.path, format_ts(entry.created_at))),
+        None => {
+            let path = file_history::current_path(conn, from)?;
+            let real = policy.resolve(&path)?;
+            let current = match std::fs::read(&real) {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(DrodeError::io(&real, e)),
+            };
+            (current, format!("{} (current)", path))
+        }
+    };
+
+    match (std::str::from_utf8(&old), std::str::from_utf8(&new)) {
+        (Ok(old), Ok(new)) => Ok(crate::db::file_edits::text_diff(&old_label, &new_label, old, new)),
+        _ => Ok(format!("Binary files {} and {} differ\n", old_label, new_label)),
+    }
+}
+
+/// Put a snapshot back where the file is now, recreating it if it was deleted. What is on
+/// disk is snapshotted first, so the restore can itself be undone.
+pub fn restore(
+    conn: &Connection,
+    history: &History,
+    policy: &PathPolicy,
+    entry: &HistoryEntry,
+) -> Result<FileVersion, DrodeError> {
+    let content = history.load(&entry.hash)?;
+    let path = file_history::current_path(conn, entry)?;
+    let real = policy.resolve(&path)?;
+    if let Some(dir) = real.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| DrodeError::io(dir, e))?;
+    }
+    history.snapshot_file(conn, &real, None, Source::External)?;
+    let version = path_policy::write(policy, &path, &content)?;
+    history.snapshot(conn, &real, None, Source::Restore, &content)?;
+    Ok(version)
+}
+
+fn format_ts(millis: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::DbPool;
+
+    struct Fixture {
+        root: PathBuf,
+        db: DbPool,
+        history: History,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let root = std::env::temp_dir().join(format!("drode-history-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&root).unwrap();
+            let db = DbPool::open(&root.join("drode.db")).unwrap();
+            crate::db::schema::initialize(&db.get()).unwrap();
+            let history = History::new(root.join("history"));
+            Fixture { root, db, history }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.root.join("project").join(name)
+        }
+
+        fn snapshot(&self, name: &str, old_name: Option<&str>, source: Source, content: &str) -> i64 {
+            let old = old_name.map(|n| self.path(n));
+            self.history
+                .snapshot(&self.db.get(), &self.path(name), old.as_deref(), source, content.as_bytes())
+                .unwrap()
+                .unwrap()
+        }
+
+        fn list(&self, name: &str) -> Vec<HistoryEntry> {
+            file_history::list(&self.db.get(), &self.path(name).to_string_lossy()).unwrap()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn list_follows_renames_back() {
+        let fx = Fixture::new();
+        fx.snapshot("a.rs", None, Source::Save, "one");
+        fx.snapshot("a.rs", None, Source::Save, "two");
+        fx.snapshot("b.rs", Some("a.rs"), Source::Rename, "two");
+        fx.snapshot("b.rs", None, Source::Save, "three");
+        // A new file under the old name afterwards isn't part of b.rs
+        fx.snapshot("a.rs", None, Source::Save, "other");
+
+        let entries = fx.list("b.rs");
+        let sources: Vec<&str> = entries.iter().map(|e| e.source.as_str()).collect();
+        assert_eq!(sources, ["save", "rename", "save", "save"]);
+        let content: Vec<Vec<u8>> = entries.iter().map(|e| fx.history.load(&e.hash).unwrap()).collect();
+        assert_eq!(content, [&b"three"[..], b"two", b"two", b"one"]);
+        assert_eq!(entries[1].old_path.as_deref(), Some(&*fx.path("a.rs").to_string_lossy()));
+    }
+
+    #[test]
+    fn current_path_follows_later_renames() {
+        let fx = Fixture::new();
+        let first = fx.snapshot("a.rs", None, Source::Save, "one");
+        fx.snapshot("b.rs", Some("a.rs"), Source::Rename, "one");
+        let renamed = fx.snapshot("c.rs", Some("b.rs"), Source::Rename, "one");
+
+        let conn = fx.db.get();
+        let entry = file_history::get(&conn, first).unwrap().unwrap();
+        assert_eq!(file_history::current_path(&conn, &entry).unwrap(), fx.path("c.rs").to_string_lossy());
+        let entry = file_history::get(&conn, renamed).unwrap().unwrap();
+        assert_eq!(file_history::current_path(&conn, &entry).unwrap(), fx.path("c.rs").to_string_lossy());
+    }
+
+    #[test]
+    fn prune_removes_old_entries_and_unused_objects() {
+        let fx = Fixture::new();
+        let old = fx.snapshot("a.rs", None, Source::Save, "old");
+        let shared = fx.snapshot("a.rs", None, Source::Save, "shared");
+        fx.snapshot("b.rs", None, Source::Save, "shared");
+        let conn = fx.db.get();
+        conn.execute("UPDATE file_history SET created_at = 0 WHERE id IN (?1, ?2)", [old, shared])
+            .unwrap();
+
+        assert_eq!(prune(&conn, &fx.history).unwrap(), 1);
+        assert!(!fx.history.object_path(&path_policy::content_hash(b"old")).exists());
+        let left = fx.list("b.rs");
+        assert_eq!(left.len(), 1);
+        // Still used by b.rs
+        assert_eq!(fx.history.load(&left[0].hash).unwrap(), b"shared");
+        assert!(fx.list("a.rs").is_empty());
+    }
+}
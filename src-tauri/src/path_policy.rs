@@ -0,0 +1,539 @@
+// Confines the file commands to the open project plus any folders the user has allowed.
+// Paths from the webview are resolved to their real location (symlinks and `..` included)
+// before they are checked, and files are re-verified after opening so a path swapped for a
+// symlink between the check and the open is caught.
+//
+// Writes go to a temp file next to the target which is then renamed over it, so a crash
+// never leaves a half-written file, and can be made conditional on the version the
+// caller last saw.
+
+use crate::db::settings;
+use crate::error::DrodeError;
+use rusqlite::Connection;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{File, Metadata, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// JSON array of extra folders the file commands may touch
+pub const ALLOWED_PATHS_KEY: &str = "allowed_paths";
+
+/// What a file held when it was last read or written, for conditional writes
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FileVersion {
+    /// Milliseconds since the epoch
+    pub mtime: i64,
+    /// SHA-256 of the content, hex
+    pub hash: String,
+    pub size: u64,
+}
+
+/// The version a write expects to replace. The hash wins when both are given, since
+/// touching a file changes its mtime but not its content.
+#[derive(Debug, Clone, Default)]
+pub struct Expected {
+    pub mtime: Option<i64>,
+    pub hash: Option<String>,
+}
+
+impl Expected {
+    fn is_set(&self) -> bool {
+        self.mtime.is_some() || self.hash.is_some()
+    }
+
+    fn matches(&self, current: &FileVersion) -> bool {
+        match (&self.hash, self.mtime) {
+            (Some(hash), _) => *hash == current.hash,
+            (None, Some(mtime)) => mtime == current.mtime,
+            (None, None) => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PathPolicy {
+    /// Canonical roots; anything at or below one of them is allowed
+    roots: Vec<PathBuf>,
+}
+
+impl PathPolicy {
+    /// Roots that don't exist (a project folder that was moved, say) are skipped, and so
+    /// are ones `check_root` refuses, should one have been stored before it did
+    pub fn new<P: AsRef<Path>>(roots: impl IntoIterator<Item = P>) -> Self {
+        PathPolicy {
+            roots: roots
+                .into_iter()
+                .filter_map(|root| check_root(root.as_ref()).ok())
+                .collect(),
+        }
+    }
+
+    /// The current project and the user's allowlist
+    pub fn load(conn: &Connection) -> Self {
+        let mut roots = allowed_paths(conn);
+        if let Some(project) = settings::get(conn, "current_project") {
+            roots.push(project);
+        }
+        PathPolicy::new(roots)
+    }
+
+    /// Real location of `path`, following symlinks all the way. The file itself may not
+    /// exist yet, but its nearest existing ancestor must resolve inside a root.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, DrodeError> {
+        let real = real_path(path)?;
+        self.check(path, &real)?;
+        Ok(real)
+    }
+
+    /// Like `resolve`, but the last component is taken as-is, so deleting or renaming a
+    /// symlink acts on the link rather than on what it points to. The roots themselves
+    /// can't be deleted or renamed.
+    pub fn resolve_entry(&self, path: &str) -> Result<PathBuf, DrodeError> {
+        let requested = absolute(path)?;
+        let (Some(parent), Some(name)) = (requested.parent(), requested.file_name()) else {
+            return Err(denied(path, "has no parent folder"));
+        };
+        let real = real_path(&parent.to_string_lossy())?.join(name);
+        self.check(path, &real)?;
+        if self.roots.contains(&real) {
+            return Err(denied(path, "is a project root"));
+        }
+        Ok(real)
+    }
+
+    fn check(&self, requested: &str, real: &Path) -> Result<(), DrodeError> {
+        if self.roots.iter().any(|root| real.starts_with(root)) {
+            Ok(())
+        } else if self.roots.is_empty() {
+            Err(denied(requested, "can't be checked while no project is open"))
+        } else {
+            Err(denied(requested, "is outside the project and the allowed folders"))
+        }
+    }
+}
+
+pub fn allowed_paths(conn: &Connection) -> Vec<String> {
+    settings::get(conn, ALLOWED_PATHS_KEY)
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Store the allowlist. Each folder must pass `check_root`; callers take new ones from
+/// the native folder dialog, never from the webview.
+pub fn set_allowed_paths(conn: &Connection, paths: &[String]) -> Result<(), DrodeError> {
+    for path in paths {
+        check_root(Path::new(path))?;
+    }
+    settings::set(conn, ALLOWED_PATHS_KEY, &serde_json::to_string(paths)?)?;
+    Ok(())
+}
+
+/// Canonical form of a folder that may become a root: a project or an allowed folder.
+/// The filesystem root and the home folder, or anything above it, are refused since they
+/// would let the file commands reach everything the user owns.
+pub fn check_root(path: &Path) -> Result<PathBuf, DrodeError> {
+    let shown = path.to_string_lossy();
+    if !path.is_absolute() {
+        return Err(denied(&shown, "is not an absolute path"));
+    }
+    let real = path.canonicalize().map_err(|e| DrodeError::io(path, e))?;
+    if !real.is_dir() {
+        return Err(DrodeError::validation(format!("Not a folder: {}", shown)));
+    }
+    if real.parent().is_none() {
+        return Err(denied(&shown, "is the root of the filesystem"));
+    }
+    let home = dirs::home_dir().and_then(|home| home.canonicalize().ok());
+    if home.is_some_and(|home| home.starts_with(&real)) {
+        return Err(denied(&shown, "contains the whole home folder"));
+    }
+    Ok(real)
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Open for reading, verified like every other access
+pub fn open(policy: &PathPolicy, path: &str) -> Result<File, DrodeError> {
+    let real = policy.resolve(path)?;
+    open_verified(policy, path, &real, OpenOptions::new().read(true))
+}
+
+pub fn read_to_string(policy: &PathPolicy, path: &str) -> Result<String, DrodeError> {
+    let mut file = open(policy, path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(|e| DrodeError::io(path, e))?;
+    Ok(content)
+}
+
+pub fn version(policy: &PathPolicy, path: &str) -> Result<FileVersion, DrodeError> {
+    let real = policy.resolve(path)?;
+    let (_, version) = read_current(policy, path, &real)?;
+    Ok(version)
+}
+
+/// The version of `bytes`, just read from `file`, so a save can expect exactly what was read
+pub fn version_of(file: &File, path: &str, bytes: &[u8]) -> Result<FileVersion, DrodeError> {
+    let metadata = file.metadata().map_err(|e| DrodeError::io(path, e))?;
+    Ok(FileVersion {
+        mtime: mtime_millis(&metadata),
+        hash: content_hash(bytes),
+        size: bytes.len() as u64,
+    })
+}
+
+/// Create or overwrite
+pub fn write(policy: &PathPolicy, path: &str, content: &[u8]) -> Result<FileVersion, DrodeError> {
+    write_checked(policy, path, content, &Expected::default())
+}
+
+/// Create or overwrite, unless the file no longer is the `expected` version; then nothing is
+/// written and the error carries what is on disk now. The new content goes to a temp file
+/// in the same folder, with the old file's permissions, and is renamed over the target.
+pub fn write_checked(
+    policy: &PathPolicy,
+    path: &str,
+    content: &[u8],
+    expected: &Expected,
+) -> Result<FileVersion, DrodeError> {
+    let real = policy.resolve(path)?;
+    let existing = match read_current(policy, path, &real) {
+        Ok(current) => Some(current),
+        Err(DrodeError::NotFound(_)) => None,
+        Err(e) => return Err(e),
+    };
+
+    if expected.is_set() {
+        match &existing {
+            Some((bytes, current)) if !expected.matches(current) => {
+                return Err(DrodeError::FileChanged {
+                    path: path.to_string(),
+                    current: Some(String::from_utf8_lossy(bytes).into_owned()),
+                    version: Some(current.clone()),
+                });
+            }
+            None => {
+                return Err(DrodeError::FileChanged {
+                    path: path.to_string(),
+                    current: None,
+                    version: None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let (Some(dir), Some(name)) = (real.parent(), real.file_name()) else {
+        return Err(denied(path, "has no parent folder"));
+    };
+    let temp = dir.join(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        &uuid::Uuid::new_v4().simple().to_string()[..8]
+    ));
+    let permissions = match existing {
+        Some(_) => std::fs::metadata(&real).ok().map(|m| m.permissions()),
+        None => None,
+    };
+
+    let written = write_temp(&temp, content, permissions).and_then(|()| {
+        // The folder must still be the one that was checked when the temp file landed in it
+        let landed = temp
+            .parent()
+            .and_then(|p| p.canonicalize().ok())
+            .is_some_and(|p| p == dir);
+        if !landed {
+            return Err(denied(path, "changed while it was being written"));
+        }
+        std::fs::rename(&temp, &real).map_err(|e| DrodeError::io(path, e))
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e);
+    }
+    sync_dir(dir);
+
+    let mtime = std::fs::metadata(&real)
+        .map(|m| mtime_millis(&m))
+        .map_err(|e| DrodeError::io(path, e))?;
+    Ok(FileVersion {
+        mtime,
+        hash: content_hash(content),
+        size: content.len() as u64,
+    })
+}
+
+/// Content and version of the file at `real`, read through a verified handle
+fn read_current(policy: &PathPolicy, requested: &str, real: &Path) -> Result<(Vec<u8>, FileVersion), DrodeError> {
+    let mut file = open_verified(policy, requested, real, OpenOptions::new().read(true))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).map_err(|e| DrodeError::io(requested, e))?;
+    let version = version_of(&file, requested, &bytes)?;
+    Ok((bytes, version))
+}
+
+fn write_temp(temp: &Path, content: &[u8], permissions: Option<std::fs::Permissions>) -> Result<(), DrodeError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    let mut file = options.open(temp).map_err(|e| DrodeError::io(temp, e))?;
+    if let Some(permissions) = permissions {
+        file.set_permissions(permissions).map_err(|e| DrodeError::io(temp, e))?;
+    }
+    file.write_all(content).map_err(|e| DrodeError::io(temp, e))?;
+    file.sync_all().map_err(|e| DrodeError::io(temp, e))
+}
+
+// Make the rename itself durable; best effort, and not possible on Windows
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_: &Path) {}
+
+fn mtime_millis(metadata: &Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Open the already-checked `real` path without following a symlink in its last component,
+/// then make sure the path still resolves to itself and to the file we actually opened.
+fn open_verified(
+    policy: &PathPolicy,
+    requested: &str,
+    real: &Path,
+    options: &mut OpenOptions,
+) -> Result<File, DrodeError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    let file = options.open(real).map_err(|e| match e.raw_os_error() {
+        #[cfg(unix)]
+        Some(libc::ELOOP) => denied(requested, "was replaced by a symlink"),
+        _ => DrodeError::io(requested, e),
+    })?;
+
+    let still_real = real.canonicalize().map_err(|e| DrodeError::io(requested, e))?;
+    policy.check(requested, &still_real)?;
+    let opened = file.metadata().map_err(|e| DrodeError::io(requested, e))?;
+    let on_disk = std::fs::metadata(&still_real).map_err(|e| DrodeError::io(requested, e))?;
+    if still_real != real || !same_file(&opened, &on_disk) {
+        return Err(denied(requested, "changed while it was being opened"));
+    }
+    Ok(file)
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+// No stable file identity on other platforms; the path re-check above still applies
+#[cfg(not(unix))]
+fn same_file(_: &Metadata, _: &Metadata) -> bool {
+    true
+}
+
+fn absolute(path: &str) -> Result<PathBuf, DrodeError> {
+    let requested = PathBuf::from(path);
+    if requested.is_absolute() {
+        Ok(requested)
+    } else {
+        Err(denied(path, "is not an absolute path"))
+    }
+}
+
+/// Canonicalize the longest existing prefix and append the rest. `..` is only allowed in
+/// the part that exists, where the OS resolves it; a dangling symlink is refused since
+/// writing through it would create its target wherever that is.
+fn real_path(path: &str) -> Result<PathBuf, DrodeError> {
+    let requested = absolute(path)?;
+    let mut existing = requested.as_path();
+    let mut missing = Vec::new();
+    loop {
+        match existing.canonicalize() {
+            Ok(real) => {
+                return Ok(missing.iter().rev().fold(real, |acc, name| acc.join(name)));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if existing.symlink_metadata().is_ok() {
+                    return Err(denied(path, "goes through a broken symlink"));
+                }
+                match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        missing.push(name.to_os_string());
+                        existing = parent;
+                    }
+                    _ => return Err(denied(path, "uses '..' below a folder that doesn't exist")),
+                }
+            }
+            Err(e) => return Err(DrodeError::io(path, e)),
+        }
+    }
+}
+
+fn denied(path: &str, reason: &str) -> DrodeError {
+    DrodeError::PathDenied {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixture {
+        dir: PathBuf,
+        project: PathBuf,
+        outside: PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("drode-policy-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            // temp_dir() is itself a symlink on some systems
+            let dir = dir.canonicalize().unwrap();
+            let project = dir.join("project");
+            let outside = dir.join("outside");
+            std::fs::create_dir_all(project.join("src")).unwrap();
+            std::fs::create_dir_all(&outside).unwrap();
+            std::fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+            std::fs::write(outside.join("secret"), "token\n").unwrap();
+            Fixture { dir, project, outside }
+        }
+
+        fn policy(&self) -> PathPolicy {
+            PathPolicy::new([&self.project])
+        }
+
+        fn path(&self, rel: &str) -> String {
+            self.project.join(rel).to_string_lossy().to_string()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn is_denied(result: Result<PathBuf, DrodeError>) -> bool {
+        matches!(result, Err(DrodeError::PathDenied { .. }))
+    }
+
+    #[test]
+    fn dot_dot_is_resolved_before_the_check() {
+        let f = Fixture::new();
+        let policy = f.policy();
+        assert_eq!(policy.resolve(&f.path("src/../src/main.rs")).unwrap(), f.project.join("src/main.rs"));
+        assert!(is_denied(policy.resolve(&f.path("../outside/secret"))));
+        assert!(is_denied(policy.resolve(&f.path("src/../../outside/new-file"))));
+        // `..` below a folder that doesn't exist can't be resolved by the OS, so it's refused
+        assert!(is_denied(policy.resolve(&f.path("missing/../../outside/secret"))));
+        assert!(is_denied(policy.resolve("relative/path")));
+        // New files below existing folders are fine
+        assert_eq!(policy.resolve(&f.path("src/new/file.rs")).unwrap(), f.project.join("src/new/file.rs"));
+    }
+
+    #[test]
+    fn roots_that_would_open_everything_are_refused() {
+        let f = Fixture::new();
+        let root = f.project.ancestors().last().unwrap();
+        assert!(matches!(check_root(root), Err(DrodeError::PathDenied { .. })));
+        if let Some(home) = dirs::home_dir() {
+            assert!(matches!(check_root(&home), Err(DrodeError::PathDenied { .. })));
+        }
+        assert_eq!(check_root(&f.project).unwrap(), f.project);
+        assert!(check_root(&f.project.join("src/main.rs")).is_err());
+
+        // One stored before the check existed is dropped rather than trusted
+        let policy = PathPolicy::new([root, f.project.as_path()]);
+        assert!(is_denied(policy.resolve(&f.outside.join("secret").to_string_lossy())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_followed_to_their_target() {
+        let f = Fixture::new();
+        let policy = f.policy();
+        std::os::unix::fs::symlink(f.outside.join("secret"), f.project.join("escape")).unwrap();
+        std::os::unix::fs::symlink(&f.outside, f.project.join("escape-dir")).unwrap();
+        assert!(is_denied(policy.resolve(&f.path("escape"))));
+        assert!(is_denied(policy.resolve(&f.path("escape-dir/new-file"))));
+
+        std::os::unix::fs::symlink(f.project.join("src/main.rs"), f.project.join("inside")).unwrap();
+        assert_eq!(policy.resolve(&f.path("inside")).unwrap(), f.project.join("src/main.rs"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks_are_refused() {
+        let f = Fixture::new();
+        std::os::unix::fs::symlink(f.outside.join("not-yet"), f.project.join("dangling")).unwrap();
+        assert!(is_denied(f.policy().resolve(&f.path("dangling"))));
+        assert!(f.policy().resolve(&f.path("dangling/below")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_entry_keeps_the_link_and_protects_roots() {
+        let f = Fixture::new();
+        let policy = f.policy();
+        std::os::unix::fs::symlink(f.outside.join("secret"), f.project.join("escape")).unwrap();
+        // Deleting the link is fine; it's the link that goes, not its target
+        assert_eq!(policy.resolve_entry(&f.path("escape")).unwrap(), f.project.join("escape"));
+        assert!(is_denied(policy.resolve_entry(&f.project.to_string_lossy())));
+        assert!(is_denied(policy.resolve_entry(&f.outside.join("secret").to_string_lossy())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_swapped_for_a_symlink_after_the_check_is_refused() {
+        let f = Fixture::new();
+        let policy = f.policy();
+        let path = f.path("src/main.rs");
+        let real = policy.resolve(&path).unwrap();
+
+        std::fs::remove_file(&real).unwrap();
+        std::os::unix::fs::symlink(f.outside.join("secret"), &real).unwrap();
+        let opened = open_verified(&policy, &path, &real, OpenOptions::new().read(true));
+        assert!(matches!(opened, Err(DrodeError::PathDenied { .. })));
+        assert!(is_denied(policy.resolve(&path)));
+    }
+
+    #[test]
+    fn conditional_write_refuses_a_changed_file() {
+        let f = Fixture::new();
+        let policy = f.policy();
+        let path = f.path("src/main.rs");
+        let version = version(&policy, &path).unwrap();
+        std::fs::write(&path, "fn main() { changed() }\n").unwrap();
+
+        let expected = Expected {
+            mtime: None,
+            hash: Some(version.hash),
+        };
+        let result = write_checked(&policy, &path, b"mine\n", &expected);
+        assert!(matches!(result, Err(DrodeError::FileChanged { .. })));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn main() { changed() }\n");
+    }
+}
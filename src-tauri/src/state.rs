@@ -1,13 +1,14 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager};
 
 // Conversation-related types (shared across commands and db modules)
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MessageMetadata {
     #[serde(rename = "durationMs", skip_serializing_if = "Option::is_none")]
     pub duration_ms: Option<i64>,
@@ -66,6 +67,8 @@ pub struct Conversation {
     pub created_at: i64,
     #[serde(rename = "updatedAt")]
     pub updated_at: i64,
+    #[serde(default, rename = "sessionId", skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
     pub messages: Vec<ConversationMessage>,
 }
 
@@ -79,6 +82,29 @@ pub struct ConversationSummary {
     pub updated_at: i64,
     #[serde(rename = "messageCount")]
     pub message_count: usize,
+    #[serde(default, rename = "sessionId", skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, rename = "deletedAt", skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// None while the name is a placeholder, "user" or "auto" once it has a real title
+    #[serde(default, rename = "titleSource", skip_serializing_if = "Option::is_none")]
+    pub title_source: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConversationPage {
+    pub conversations: Vec<ConversationSummary>,
+    /// Opaque cursor for the next page; None once the last page was returned
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
 }
 
 // StoreData and WindowBounds kept for JSON migration deserialization only
@@ -121,11 +147,30 @@ impl Default for WindowBounds {
 // Core application state — SQLite-backed
 
 pub struct AppState {
-    pub db: Mutex<rusqlite::Connection>,
+    pub db: crate::db::pool::DbPool,
     #[allow(dead_code)]
     pub legacy_config_path: PathBuf,
     // Process registry for terminal commands - maps terminal_id to process ID
     pub terminal_pids: Mutex<HashMap<String, u32>>,
+    pub backups_dir: PathBuf,
+    // Result of the startup integrity check, kept for windows that load after it ran
+    pub integrity: Mutex<Option<crate::db::backup::IntegrityReport>>,
+    pub token_key_path: PathBuf,
+    // None while a passphrase-protected key is still locked
+    pub token_cipher: Mutex<Option<crate::db::token_crypto::TokenCipher>>,
+    // Conversations with a title/summary generation in flight
+    pub summary_jobs: Mutex<HashSet<String>>,
+    // File watchers by project path; dropping one stops it
+    pub watchers: Mutex<HashMap<String, crate::watcher::ProjectWatcher>>,
+    pub claude_runs: Mutex<crate::watcher::ClaudeRuns>,
+    // Cancellation flags of running content searches, by search id
+    pub searches: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    // Find/replace previews by plan id, until applied
+    pub replace_plans: Mutex<HashMap<String, crate::file_replace::ReplacePlan>>,
+    // Local history snapshots, under history/ in the config folder
+    pub history: crate::local_history::History,
+    // Git status by project path, once asked for; kept current by the watchers
+    pub git_statuses: Mutex<HashMap<String, crate::git_status::GitStatus>>,
 }
 
 impl AppState {
@@ -136,21 +181,61 @@ impl AppState {
             .unwrap_or_else(|_| PathBuf::from("."));
         let db_path = config_dir.join("drode.db");
         let legacy_config_path = config_dir.join("config.json");
+        let backups_dir = config_dir.join("backups");
+        let token_key_path = config_dir.join("oauth.key");
+        let history = crate::local_history::History::new(config_dir.join("history"));
 
         let _ = fs::create_dir_all(&config_dir);
 
-        let conn = rusqlite::Connection::open(&db_path)
+        // WAL mode for crash safety + concurrent reads (set by the pool)
+        let db = crate::db::pool::DbPool::open(&db_path)
             .expect("Failed to open database");
+        let conn = db.get();
+
+        // A failed migration is rolled back; refuse to start on a half-known schema
+        if let Err(e) = crate::db::schema::initialize(&conn) {
+            panic!("Failed to initialize database schema: {}", e);
+        }
+
+        let integrity = match crate::db::backup::integrity_check(&conn) {
+            Ok(report) => Some(report),
+            Err(e) => {
+                eprintln!("Database integrity check failed to run: {}", e);
+                None
+            }
+        };
+
+        if let Err(e) = crate::db::projects::backfill_identities(&conn) {
+            eprintln!("Failed to inspect registered projects: {}", e);
+        }
 
-        // WAL mode for crash safety + concurrent reads
-        conn.execute_batch("PRAGMA journal_mode=WAL;")
-            .expect("Failed to set WAL mode");
-        // Enable cascade deletes
-        conn.execute_batch("PRAGMA foreign_keys=ON;")
-            .expect("Failed to enable foreign keys");
+        if let Err(e) = crate::db::conversations::purge_expired_trash(&conn) {
+            eprintln!("Failed to purge expired trash: {}", e);
+        }
+
+        if let Err(e) = crate::local_history::prune(&conn, &history) {
+            eprintln!("Failed to prune local history: {}", e);
+        }
 
-        crate::db::schema::initialize(&conn)
-            .expect("Failed to initialize database schema");
+        let token_cipher = match crate::db::token_crypto::open(&conn, &token_key_path) {
+            Ok(cipher) => cipher,
+            Err(e) => {
+                eprintln!("OAuth tokens unavailable: {}", e);
+                None
+            }
+        };
+        if let Some(cipher) = &token_cipher {
+            match crate::db::oauth::encrypt_plaintext_rows(&conn, cipher) {
+                // Rewrite the file so the old plaintext doesn't survive in free pages,
+                // and encrypt the copies in the backups too
+                Ok(n) if n > 0 => {
+                    let _ = conn.execute_batch("VACUUM;");
+                    crate::db::oauth::scrub_backups(&conn, &backups_dir, cipher);
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Failed to encrypt stored OAuth tokens: {}", e),
+            }
+        }
 
         // One-time migration from JSON if needed
         if legacy_config_path.exists() {
@@ -169,19 +254,23 @@ impl AppState {
             }
         }
 
+        drop(conn);
+
         Self {
-            db: Mutex::new(conn),
+            db,
             legacy_config_path,
             terminal_pids: Mutex::new(HashMap::new()),
+            backups_dir,
+            integrity: Mutex::new(integrity),
+            token_key_path,
+            token_cipher: Mutex::new(token_cipher),
+            summary_jobs: Mutex::new(HashSet::new()),
+            watchers: Mutex::new(HashMap::new()),
+            claude_runs: Mutex::new(crate::watcher::ClaudeRuns::default()),
+            searches: Mutex::new(HashMap::new()),
+            replace_plans: Mutex::new(HashMap::new()),
+            history,
+            git_statuses: Mutex::new(HashMap::new()),
         }
     }
 }
-
-#[derive(Serialize)]
-pub struct OperationResult {
-    pub success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
-}
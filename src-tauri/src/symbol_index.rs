@@ -0,0 +1,534 @@
+// Symbols (functions, classes, types ...) of TypeScript/JavaScript, Rust and Python files,
+// parsed with tree-sitter. The watcher thread keeps each project's index in SQLite
+// (db/symbols.rs) current: `sync` after every full scan re-parses only files whose mtime or
+// size changed, and `apply_changes` handles the files in each fs-change batch.
+
+use crate::db::symbols::{self, Symbol};
+use crate::file_index::FileIndex;
+use crate::state::AppState;
+use crate::watcher::{ChangeKind, FsChange};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter, Manager};
+use tree_sitter::{Node, Parser};
+
+// Generated and minified files are rarely worth it
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+// Files parsed per transaction during a sync
+const SYNC_BATCH: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    TypeScript,
+    Tsx,
+    JavaScript,
+    Rust,
+    Python,
+}
+
+impl Lang {
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "ts" | "mts" | "cts" => Some(Lang::TypeScript),
+            "tsx" => Some(Lang::Tsx),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Lang::JavaScript),
+            "rs" => Some(Lang::Rust),
+            "py" | "pyi" => Some(Lang::Python),
+            _ => None,
+        }
+    }
+
+    fn grammar(self) -> tree_sitter::Language {
+        match self {
+            Lang::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Lang::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Lang::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Lang::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Lang::Python => tree_sitter_python::LANGUAGE.into(),
+        }
+    }
+}
+
+/// A symbol with the symbols declared inside it
+#[derive(Debug, Serialize, Clone)]
+pub struct OutlineNode {
+    #[serde(flatten)]
+    pub symbol: Symbol,
+    pub children: Vec<OutlineNode>,
+}
+
+/// Every symbol of a file, in source order
+pub fn extract(lang: Lang, path: &str, source: &str) -> Vec<Symbol> {
+    extract_nodes(lang, path, source)
+        .into_iter()
+        .map(|(symbol, _)| symbol)
+        .collect()
+}
+
+/// The file's symbols nested by where they are declared
+pub fn outline(lang: Lang, path: &str, source: &str) -> Vec<OutlineNode> {
+    let mut roots: Vec<OutlineNode> = Vec::new();
+    // Open symbols and the byte they end at
+    let mut stack: Vec<(OutlineNode, usize)> = Vec::new();
+    for (symbol, (start, end)) in extract_nodes(lang, path, source) {
+        while stack.last().is_some_and(|(_, open_end)| start >= *open_end) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push((OutlineNode { symbol, children: Vec::new() }, end));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+fn close(stack: &mut Vec<(OutlineNode, usize)>, roots: &mut Vec<OutlineNode>) {
+    if let Some((node, _)) = stack.pop() {
+        match stack.last_mut() {
+            Some((parent, _)) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+}
+
+/// Symbols with the byte range of their declaration
+fn extract_nodes(lang: Lang, path: &str, source: &str) -> Vec<(Symbol, (usize, usize))> {
+    let mut parser = Parser::new();
+    if parser.set_language(&lang.grammar()).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+    let mut extractor = Extractor {
+        lang,
+        path,
+        source,
+        symbols: Vec::new(),
+    };
+    extractor.visit(tree.root_node(), None, false);
+    extractor.symbols
+}
+
+struct Extractor<'a> {
+    lang: Lang,
+    path: &'a str,
+    source: &'a str,
+    symbols: Vec<(Symbol, (usize, usize))>,
+}
+
+impl<'a> Extractor<'a> {
+    fn text(&self, node: Node) -> &'a str {
+        node.utf8_text(self.source.as_bytes()).unwrap_or("")
+    }
+
+    fn children(&mut self, node: Node, container: Option<&str>, exported: bool) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            self.visit(child, container, exported);
+        }
+    }
+
+    fn push(&mut self, decl: Node, name: Node, kind: &str, container: Option<&str>, exported: bool) {
+        let name_pos = name.start_position();
+        let line_start = name.start_byte() - name_pos.column;
+        let column = self.source[line_start..name.start_byte()]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>()
+            + 1;
+        self.symbols.push((
+            Symbol {
+                name: self.text(name).to_string(),
+                kind: kind.to_string(),
+                container: container.map(str::to_string),
+                exported,
+                path: self.path.to_string(),
+                line: name_pos.row + 1,
+                column,
+                start_line: decl.start_position().row + 1,
+                end_line: decl.end_position().row + 1,
+            },
+            (decl.start_byte(), decl.end_byte()),
+        ));
+    }
+
+    fn visit(&mut self, node: Node, container: Option<&str>, exported: bool) {
+        match self.lang {
+            Lang::TypeScript | Lang::Tsx | Lang::JavaScript => self.visit_js(node, container, exported),
+            Lang::Rust => self.visit_rust(node, container),
+            Lang::Python => self.visit_python(node, container),
+        }
+    }
+
+    fn visit_js(&mut self, node: Node, container: Option<&str>, exported: bool) {
+        let name = node.child_by_field_name("name");
+        let kind = match node.kind() {
+            // A top-level `namespace` is parsed as an expression statement
+            "program" | "statement_block" | "ambient_declaration" | "lexical_declaration"
+            | "variable_declaration" | "expression_statement" => {
+                return self.children(node, container, exported);
+            }
+            "export_statement" => return self.children(node, container, true),
+            "function_declaration" | "generator_function_declaration" | "function_signature" => "function",
+            "class_declaration" | "abstract_class_declaration" | "class" => "class",
+            "method_definition" | "method_signature" | "abstract_method_signature" => "method",
+            "interface_declaration" => "interface",
+            "type_alias_declaration" => "type",
+            "enum_declaration" => "enum",
+            "internal_module" | "module" => "module",
+            "variable_declarator" => {
+                let (Some(name), Some(parent)) = (name, node.parent()) else {
+                    return;
+                };
+                // Only declarations at the top of a file or module
+                let top_level = parent
+                    .parent()
+                    .is_some_and(|p| matches!(p.kind(), "program" | "export_statement" | "statement_block"));
+                if name.kind() != "identifier" || !top_level {
+                    return;
+                }
+                let is_function = node
+                    .child_by_field_name("value")
+                    .is_some_and(|v| matches!(v.kind(), "arrow_function" | "function_expression" | "function"));
+                let kind = if is_function {
+                    "function"
+                } else if self.text(parent).starts_with("const") {
+                    "constant"
+                } else {
+                    "variable"
+                };
+                return self.push(node, name, kind, container, exported);
+            }
+            _ => return,
+        };
+        let Some(name) = name else {
+            return;
+        };
+        self.push(node, name, kind, container, exported);
+        if matches!(kind, "class" | "interface" | "module") {
+            if let Some(body) = node.child_by_field_name("body") {
+                let owner = self.text(name);
+                self.children(body, Some(owner), false);
+            }
+        }
+    }
+
+    fn visit_rust(&mut self, node: Node, container: Option<&str>) {
+        let kind = match node.kind() {
+            "source_file" | "declaration_list" => return self.children(node, container, false),
+            "impl_item" => {
+                let owner = node.child_by_field_name("type").map(|t| self.text(t));
+                if let Some(body) = node.child_by_field_name("body") {
+                    self.children(body, owner.or(container), false);
+                }
+                return;
+            }
+            "function_item" | "function_signature_item" if container.is_some() => "method",
+            "function_item" | "function_signature_item" => "function",
+            "struct_item" | "union_item" => "struct",
+            "enum_item" => "enum",
+            "trait_item" => "trait",
+            "type_item" => "type",
+            "const_item" | "static_item" => "constant",
+            "mod_item" => "module",
+            "macro_definition" => "macro",
+            _ => return,
+        };
+        let Some(name) = node.child_by_field_name("name") else {
+            return;
+        };
+        let mut cursor = node.walk();
+        let public = node
+            .children(&mut cursor)
+            .any(|c| c.kind() == "visibility_modifier");
+        self.push(node, name, kind, container, public);
+        if matches!(kind, "trait" | "module") {
+            if let Some(body) = node.child_by_field_name("body") {
+                let owner = self.text(name);
+                self.children(body, Some(owner), false);
+            }
+        }
+    }
+
+    fn visit_python(&mut self, node: Node, container: Option<&str>) {
+        let kind = match node.kind() {
+            "module" | "decorated_definition" => return self.children(node, container, false),
+            "function_definition" if container.is_some() => "method",
+            "function_definition" => "function",
+            "class_definition" => "class",
+            "expression_statement" if container.is_none() => {
+                // Module-level `NAME = ...`
+                let Some(assignment) = node.named_child(0).filter(|n| n.kind() == "assignment") else {
+                    return;
+                };
+                let Some(name) = assignment.child_by_field_name("left").filter(|n| n.kind() == "identifier") else {
+                    return;
+                };
+                let text = self.text(name);
+                let kind = if text.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') {
+                    "constant"
+                } else {
+                    "variable"
+                };
+                return self.push(node, name, kind, None, !text.starts_with('_'));
+            }
+            _ => return,
+        };
+        let Some(name) = node.child_by_field_name("name") else {
+            return;
+        };
+        let public = !self.text(name).starts_with('_');
+        self.push(node, name, kind, container, public);
+        if kind == "class" {
+            if let Some(body) = node.child_by_field_name("body") {
+                let owner = self.text(name);
+                self.children(body, Some(owner), false);
+            }
+        }
+    }
+}
+
+/// Parse a file from disk; None when it isn't a supported language or is too large
+fn parse_file(path: &Path) -> Option<(i64, u64, Vec<Symbol>)> {
+    let lang = Lang::of(path)?;
+    let (mtime, size) = stamp(path)?;
+    if size > MAX_FILE_BYTES {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    let source = String::from_utf8_lossy(&bytes);
+    Some((mtime, size, extract(lang, &path.to_string_lossy(), &source)))
+}
+
+fn stamp(path: &Path) -> Option<(i64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_millis() as i64;
+    Some((mtime, metadata.len()))
+}
+
+/// What a file's stored symbols become
+enum Update {
+    Store { path: String, mtime: i64, size: u64, found: Vec<Symbol> },
+    Remove(String),
+}
+
+/// Parse `path` for storing under `key`; a file that can't be parsed (any more) is dropped
+fn reparse(path: &Path, key: String) -> Update {
+    match parse_file(path) {
+        Some((mtime, size, found)) => Update::Store { path: key, mtime, size, found },
+        None => Update::Remove(key),
+    }
+}
+
+/// Write parsed results in one short transaction. Parsing happens before, so the write
+/// lock is never held while files are read.
+fn store(conn: &Connection, project_path: &str, updates: &[Update]) -> Result<(), rusqlite::Error> {
+    let tx = conn.unchecked_transaction()?;
+    for update in updates {
+        match update {
+            Update::Store { path, mtime, size, found } => {
+                symbols::store_file(&tx, project_path, path, *mtime, *size, found)?
+            }
+            Update::Remove(path) => {
+                symbols::remove_path(&tx, project_path, path)?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+/// Bring the stored index in line with the project's file index. Returns the number of
+/// files that were parsed.
+pub fn sync(conn: &Connection, project_path: &str, index: &RwLock<FileIndex>) -> Result<usize, rusqlite::Error> {
+    let files: Vec<std::path::PathBuf> = {
+        let index = index.read().unwrap();
+        index
+            .paths()
+            .filter(|path| Lang::of(path).is_some())
+            .collect()
+    };
+    let mut stamps = symbols::file_stamps(conn, project_path)?;
+
+    let mut parsed = 0;
+    for batch in files.chunks(SYNC_BATCH) {
+        let updates: Vec<Update> = batch
+            .iter()
+            .filter_map(|path| {
+                let key = path.to_string_lossy().to_string();
+                let known = stamps.remove(&key);
+                if known.is_some() && known == stamp(path) {
+                    return None;
+                }
+                Some(reparse(path, key))
+            })
+            .collect();
+        parsed += updates.iter().filter(|u| matches!(u, Update::Store { .. })).count();
+        store(conn, project_path, &updates)?;
+    }
+    // Whatever is left is no longer in the project
+    let tx = conn.unchecked_transaction()?;
+    for path in stamps.keys() {
+        tx.execute(
+            "DELETE FROM symbol_files WHERE project_path = ?1 AND path = ?2",
+            rusqlite::params![project_path, path],
+        )?;
+    }
+    tx.commit()?;
+    Ok(parsed)
+}
+
+/// `sync` for a watched project, announcing the result with `symbol-index-updated`
+pub fn rebuild(app_handle: &AppHandle, project_path: &str, index: &RwLock<FileIndex>) {
+    let state = app_handle.state::<AppState>();
+    let db = state.db.get();
+    if let Err(e) = sync(&db, project_path, index) {
+        eprintln!("Failed to index symbols of {}: {}", project_path, e);
+        return;
+    }
+    let symbol_count = symbols::count(&db, project_path).unwrap_or(0);
+    let _ = app_handle.emit(
+        "symbol-index-updated",
+        serde_json::json!({ "projectPath": project_path, "symbolCount": symbol_count }),
+    );
+}
+
+/// Re-parse or drop the files of a watcher batch. Returns true if a folder appeared, whose
+/// files only a `sync` picks up.
+pub fn apply_changes(conn: &Connection, project_path: &str, changes: &[FsChange]) -> bool {
+    let mut new_folders = false;
+    let mut updates = Vec::new();
+    for change in changes {
+        if let Some(old) = &change.old_path {
+            updates.push(Update::Remove(old.clone()));
+        }
+        if change.kind == ChangeKind::Delete {
+            updates.push(Update::Remove(change.path.clone()));
+            continue;
+        }
+        if change.is_dir {
+            new_folders = true;
+            continue;
+        }
+        let path = Path::new(&change.path);
+        if Lang::of(path).is_some() {
+            updates.push(reparse(path, change.path.clone()));
+        }
+    }
+    if let Err(e) = store(conn, project_path, &updates) {
+        eprintln!("Failed to update symbols of {}: {}", project_path, e);
+    }
+    new_folders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (name, kind, container, exported) of each symbol, in source order
+    fn summary(lang: Lang, source: &str) -> Vec<(String, String, Option<String>, bool)> {
+        extract(lang, "test", source)
+            .into_iter()
+            .map(|s| (s.name, s.kind, s.container, s.exported))
+            .collect()
+    }
+
+    fn symbol(name: &str, kind: &str, container: Option<&str>, exported: bool) -> (String, String, Option<String>, bool) {
+        (name.to_string(), kind.to_string(), container.map(str::to_string), exported)
+    }
+
+    #[test]
+    fn rust_symbols() {
+        let source = "pub struct Point;\n\
+                      impl Point {\n    pub fn new() -> Self { Point }\n    fn len(&self) -> u8 { 0 }\n}\n\
+                      trait Shape { fn area(&self); }\n\
+                      fn helper() {}\n\
+                      pub mod units { pub const MM: u8 = 1; }\n\
+                      macro_rules! m { () => {} }\n";
+        assert_eq!(
+            summary(Lang::Rust, source),
+            [
+                symbol("Point", "struct", None, true),
+                symbol("new", "method", Some("Point"), true),
+                symbol("len", "method", Some("Point"), false),
+                symbol("Shape", "trait", None, false),
+                symbol("area", "method", Some("Shape"), false),
+                symbol("helper", "function", None, false),
+                symbol("units", "module", None, true),
+                symbol("MM", "constant", Some("units"), true),
+                symbol("m", "macro", None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn typescript_symbols() {
+        let source = "export const f = () => 1;\n\
+                      const LIMIT = 2;\n\
+                      let count = 0;\n\
+                      export class Box {\n  open() { const local = 1; }\n}\n\
+                      interface Shape { area(): number }\n\
+                      export type Id = string;\n\
+                      enum Color { Red }\n";
+        assert_eq!(
+            summary(Lang::TypeScript, source),
+            [
+                symbol("f", "function", None, true),
+                symbol("LIMIT", "constant", None, false),
+                symbol("count", "variable", None, false),
+                symbol("Box", "class", None, true),
+                symbol("open", "method", Some("Box"), false),
+                symbol("Shape", "interface", None, false),
+                symbol("area", "method", Some("Shape"), false),
+                symbol("Id", "type", None, true),
+                symbol("Color", "enum", None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn python_symbols() {
+        let source = "UPPER = 1\n\
+                      _private = 2\n\
+                      @decorated\ndef run():\n    pass\n\
+                      class Job:\n    def start(self):\n        pass\n    def _stop(self):\n        pass\n";
+        assert_eq!(
+            summary(Lang::Python, source),
+            [
+                symbol("UPPER", "constant", None, true),
+                symbol("_private", "variable", None, false),
+                symbol("run", "function", None, true),
+                symbol("Job", "class", None, true),
+                symbol("start", "method", Some("Job"), true),
+                symbol("_stop", "method", Some("Job"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn outline_nests_by_declaration() {
+        let source = "mod outer {\n    pub struct A;\n    impl A {\n        fn a(&self) {}\n    }\n}\nfn after() {}\n";
+        let roots = outline(Lang::Rust, "test", source);
+        let names = |nodes: &[OutlineNode]| nodes.iter().map(|n| n.symbol.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&roots), ["outer", "after"]);
+        // The impl isn't a symbol, so its method sits beside the struct in the module
+        assert_eq!(names(&roots[0].children), ["A", "a"]);
+        assert!(roots[0].children.iter().all(|n| n.children.is_empty()));
+        assert_eq!((roots[0].symbol.start_line, roots[0].symbol.end_line), (1, 6));
+
+        let roots = outline(Lang::TypeScript, "test", "class Box {\n  open() {}\n  close() {}\n}\nfunction f() {}\n");
+        assert_eq!(names(&roots), ["Box", "f"]);
+        assert_eq!(names(&roots[0].children), ["open", "close"]);
+    }
+}
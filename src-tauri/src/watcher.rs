@@ -0,0 +1,645 @@
+// Filesystem watcher for open projects. Raw notify events are filtered through the
+// project's .gitignore files, coalesced per path and emitted as one `fs-change`
+// batch once the tree has been quiet for DEBOUNCE. Changes made while a Claude run
+// is active in the project are flagged `byClaude`. The same thread keeps the
+// project's file index (file_index.rs) and symbol index (symbol_index.rs) up to date,
+// leaving full rescans to a thread of their own so events keep flowing during them,
+// refreshes its git status (git_status.rs) after changes to the tree or to .git and, if
+// enabled, snapshots changed files into the local history (local_history.rs).
+
+use crate::error::DrodeError;
+use crate::file_index::{self, FileIndex};
+use crate::git_status;
+use crate::state::AppState;
+use crate::symbol_index;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use notify::event::{CreateKind, EventKind, ModifyKind, RemoveKind, RenameMode};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+const DEBOUNCE: Duration = Duration::from_millis(250);
+// A steady stream of events (a build, npm install ...) is still flushed this often
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(2);
+// Filesystem events can trail the end of a run slightly
+const CLAUDE_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Create,
+    Modify,
+    Delete,
+    Rename,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct FsChange {
+    pub kind: ChangeKind,
+    pub path: String,
+    /// Previous path of a rename
+    #[serde(rename = "oldPath", skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    #[serde(rename = "isDir")]
+    pub is_dir: bool,
+    #[serde(rename = "byClaude")]
+    pub by_claude: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct FsChangeBatch<'a> {
+    #[serde(rename = "projectPath")]
+    project_path: &'a str,
+    changes: &'a [FsChange],
+}
+
+// Claude runs, per project path
+
+#[derive(Debug, Default)]
+pub struct ClaudeRuns {
+    runs: HashMap<String, RunState>,
+}
+
+#[derive(Debug, Default)]
+struct RunState {
+    active: usize,
+    finished_at: Option<Instant>,
+}
+
+impl ClaudeRuns {
+    pub fn start(&mut self, project_path: &str) {
+        self.runs.entry(project_path.to_string()).or_default().active += 1;
+    }
+
+    pub fn finish(&mut self, project_path: &str) {
+        if let Some(run) = self.runs.get_mut(project_path) {
+            run.active = run.active.saturating_sub(1);
+            run.finished_at = Some(Instant::now());
+        }
+    }
+
+    /// True while a run is going in the project, and for a short grace period after it ends
+    pub fn is_active(&self, project_path: &str) -> bool {
+        self.runs.get(project_path).is_some_and(|run| {
+            run.active > 0 || run.finished_at.is_some_and(|t| t.elapsed() < CLAUDE_GRACE)
+        })
+    }
+}
+
+// Watchers
+
+/// Dropping it stops the watch; the worker thread flushes what it has and exits
+pub struct ProjectWatcher {
+    _watcher: RecommendedWatcher,
+    index: Arc<RwLock<FileIndex>>,
+}
+
+impl ProjectWatcher {
+    pub fn index(&self) -> &Arc<RwLock<FileIndex>> {
+        &self.index
+    }
+}
+
+/// Start watching `project_path` unless it is already watched
+pub fn watch(app_handle: &AppHandle, project_path: &str) -> Result<(), DrodeError> {
+    let state = app_handle.state::<AppState>();
+    let mut watchers = state.watchers.lock().unwrap();
+    if watchers.contains_key(project_path) {
+        return Ok(());
+    }
+
+    let root = PathBuf::from(project_path);
+    if !root.is_dir() {
+        return Err(DrodeError::not_found(project_path.to_string()));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })
+    .map_err(|e| DrodeError::internal(format!("Failed to create file watcher: {}", e)))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| DrodeError::internal(format!("Failed to watch {}: {}", project_path, e)))?;
+
+    let index = Arc::new(RwLock::new(FileIndex::new(&root)));
+    let handle = app_handle.clone();
+    let project = project_path.to_string();
+    let worker_index = index.clone();
+    std::thread::spawn(move || run_worker(handle, project, root, rx, worker_index));
+
+    watchers.insert(
+        project_path.to_string(),
+        ProjectWatcher {
+            _watcher: watcher,
+            index,
+        },
+    );
+    Ok(())
+}
+
+pub fn unwatch(app_handle: &AppHandle, project_path: &str) {
+    let state = app_handle.state::<AppState>();
+    state.watchers.lock().unwrap().remove(project_path);
+    // Nothing would keep it current any more
+    state.git_statuses.lock().unwrap().remove(project_path);
+}
+
+/// Watch `project_path` and stop watching everything else; used when the open project changes
+pub fn watch_only(app_handle: &AppHandle, project_path: &str) -> Result<(), DrodeError> {
+    {
+        let state = app_handle.state::<AppState>();
+        state.watchers.lock().unwrap().retain(|path, _| path == project_path);
+        state.git_statuses.lock().unwrap().retain(|path, _| path == project_path);
+    }
+    watch(app_handle, project_path)
+}
+
+/// File index of a watched project
+pub fn index(app_handle: &AppHandle, project_path: &str) -> Option<Arc<RwLock<FileIndex>>> {
+    let state = app_handle.state::<AppState>();
+    let watchers = state.watchers.lock().unwrap();
+    watchers.get(project_path).map(|w| w.index().clone())
+}
+
+pub fn watched(app_handle: &AppHandle) -> Vec<String> {
+    let state = app_handle.state::<AppState>();
+    let watchers = state.watchers.lock().unwrap();
+    let mut paths: Vec<String> = watchers.keys().cloned().collect();
+    paths.sort();
+    paths
+}
+
+fn run_worker(
+    app_handle: AppHandle,
+    project_path: String,
+    root: PathBuf,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    index: Arc<RwLock<FileIndex>>,
+) {
+    // Set while a rescan runs on its own thread; events keep being applied meanwhile and
+    // file_index::rebuild carries them over to the new index
+    let rescanning = Arc::new(AtomicBool::new(false));
+    // A folder appeared, whose files only a symbol sync picks up
+    let mut symbols_stale = false;
+    spawn_rescan(&app_handle, &project_path, &index, &rescanning, true);
+    let mut rules = IgnoreRules::new(&root);
+    let git_dir = root.join(".git");
+    let mut batch = ChangeBatch::default();
+    let mut batch_started: Option<Instant> = None;
+    // .git changed (a commit, checkout or `git add`); its events never reach the batch
+    let mut git_changed = false;
+
+    loop {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(Ok(event)) => {
+                let by_claude = {
+                    let state = app_handle.state::<AppState>();
+                    let runs = state.claude_runs.lock().unwrap();
+                    runs.is_active(&project_path)
+                };
+                let changes = classify(&event, by_claude);
+                {
+                    let mut index = index.write().unwrap();
+                    if event.need_rescan() {
+                        index.mark_stale();
+                    }
+                    for change in &changes {
+                        index.apply(change);
+                    }
+                }
+                for change in changes {
+                    if Path::new(&change.path).starts_with(&git_dir) {
+                        git_changed = true;
+                        continue;
+                    }
+                    if rules.is_ignore_file(Path::new(&change.path)) {
+                        rules.invalidate(Path::new(&change.path));
+                    }
+                    // A rename is reported if either side of it is visible
+                    let old_ignored = match change.old_path.as_deref() {
+                        Some(old) => rules.is_ignored(Path::new(old), change.is_dir),
+                        None => true,
+                    };
+                    if !(old_ignored && rules.is_ignored(Path::new(&change.path), change.is_dir)) {
+                        batch.push(change);
+                    }
+                }
+                if !batch.is_empty() {
+                    let started = *batch_started.get_or_insert_with(Instant::now);
+                    if started.elapsed() >= MAX_BATCH_DELAY {
+                        flush(&app_handle, &project_path, &mut batch, &mut symbols_stale);
+                        batch_started = None;
+                        git_changed = true;
+                    }
+                }
+            }
+            Ok(Err(e)) => eprintln!("File watcher error in {}: {}", project_path, e),
+            Err(RecvTimeoutError::Timeout) => {
+                if flush(&app_handle, &project_path, &mut batch, &mut symbols_stale) || git_changed {
+                    git_status::refresh(&app_handle, &project_path);
+                    git_changed = false;
+                }
+                batch_started = None;
+                // Requests made while a rescan runs wait for it; the flags keep them
+                if !rescanning.load(Ordering::SeqCst) {
+                    let files_stale = index.read().unwrap().is_stale();
+                    if files_stale || symbols_stale {
+                        symbols_stale = false;
+                        spawn_rescan(&app_handle, &project_path, &index, &rescanning, files_stale);
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                flush(&app_handle, &project_path, &mut batch, &mut symbols_stale);
+                break;
+            }
+        }
+    }
+}
+
+/// Rescan the file index if `files` is set, then sync the symbols, on a thread of its own
+fn spawn_rescan(
+    app_handle: &AppHandle,
+    project_path: &str,
+    index: &Arc<RwLock<FileIndex>>,
+    rescanning: &Arc<AtomicBool>,
+    files: bool,
+) {
+    rescanning.store(true, Ordering::SeqCst);
+    let app_handle = app_handle.clone();
+    let project_path = project_path.to_string();
+    let index = index.clone();
+    let rescanning = rescanning.clone();
+    std::thread::spawn(move || {
+        if files {
+            file_index::rebuild(&app_handle, &project_path, &index);
+        }
+        symbol_index::rebuild(&app_handle, &project_path, &index);
+        rescanning.store(false, Ordering::SeqCst);
+    });
+}
+
+/// Emit and record the batch; false if it was empty. Sets `symbols_stale` when a folder
+/// appeared, whose files only a symbol sync picks up.
+fn flush(app_handle: &AppHandle, project_path: &str, batch: &mut ChangeBatch, symbols_stale: &mut bool) -> bool {
+    let changes = batch.take();
+    if changes.is_empty() {
+        return false;
+    }
+    let _ = app_handle.emit(
+        "fs-change",
+        FsChangeBatch {
+            project_path,
+            changes: &changes,
+        },
+    );
+
+    let state = app_handle.state::<AppState>();
+    let db = state.db.get();
+    crate::local_history::record_changes(&db, &state.history, &changes);
+    if symbol_index::apply_changes(&db, project_path, &changes) {
+        *symbols_stale = true;
+    }
+    true
+}
+
+/// Turn a notify event into changes. Access and metadata-only events are dropped.
+pub fn classify(event: &notify::Event, by_claude: bool) -> Vec<FsChange> {
+    let change = |kind, path: &Path, old_path: Option<&Path>, is_dir| FsChange {
+        kind,
+        path: path.to_string_lossy().to_string(),
+        old_path: old_path.map(|p| p.to_string_lossy().to_string()),
+        is_dir,
+        by_claude,
+    };
+
+    match event.kind {
+        EventKind::Create(kind) => event
+            .paths
+            .iter()
+            .map(|p| change(ChangeKind::Create, p, None, kind == CreateKind::Folder || p.is_dir()))
+            .collect(),
+        EventKind::Remove(kind) => event
+            .paths
+            .iter()
+            .map(|p| change(ChangeKind::Delete, p, None, kind == RemoveKind::Folder))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let (from, to) = (&event.paths[0], &event.paths[1]);
+            vec![change(ChangeKind::Rename, to, Some(from), to.is_dir())]
+        }
+        // One half of a rename (or a platform that can't tell): look at what is there now
+        EventKind::Modify(ModifyKind::Name(_)) => event
+            .paths
+            .iter()
+            .map(|p| {
+                if p.exists() {
+                    change(ChangeKind::Create, p, None, p.is_dir())
+                } else {
+                    change(ChangeKind::Delete, p, None, false)
+                }
+            })
+            .collect(),
+        EventKind::Modify(ModifyKind::Metadata(_)) => Vec::new(),
+        // Directory "modified" events only mean their listing changed, which the entries report
+        EventKind::Modify(_) => event
+            .paths
+            .iter()
+            .filter(|p| !p.is_dir())
+            .map(|p| change(ChangeKind::Modify, p, None, false))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Pending changes, at most one per path, in the order they were first seen. A checkout
+/// or build can touch thousands of paths in one debounce window, so slots are found
+/// through `index` and removed ones are left empty rather than shifting the rest.
+#[derive(Debug, Default)]
+pub struct ChangeBatch {
+    changes: Vec<Option<FsChange>>,
+    index: HashMap<String, usize>,
+}
+
+impl ChangeBatch {
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn take(&mut self) -> Vec<FsChange> {
+        self.index.clear();
+        std::mem::take(&mut self.changes).into_iter().flatten().collect()
+    }
+
+    fn position(&self, path: &str) -> Option<usize> {
+        self.index.get(path).copied()
+    }
+
+    fn get_mut(&mut self, i: usize) -> &mut FsChange {
+        self.changes[i].as_mut().expect("indexed slot holds a change")
+    }
+
+    fn remove(&mut self, i: usize) -> FsChange {
+        let change = self.changes[i].take().expect("indexed slot holds a change");
+        self.index.remove(&change.path);
+        change
+    }
+
+    fn append(&mut self, change: FsChange) {
+        self.index.insert(change.path.clone(), self.changes.len());
+        self.changes.push(Some(change));
+    }
+
+    pub fn push(&mut self, change: FsChange) {
+        match change.kind {
+            ChangeKind::Rename => self.push_rename(change),
+            ChangeKind::Create => match self.position(&change.path) {
+                Some(i) => {
+                    let existing = self.get_mut(i);
+                    // Deleted and recreated (editors saving via a temp file) reads as a modification
+                    if existing.kind == ChangeKind::Delete {
+                        existing.kind = ChangeKind::Modify;
+                        existing.is_dir = change.is_dir;
+                    }
+                    existing.by_claude |= change.by_claude;
+                }
+                None => self.append(change),
+            },
+            ChangeKind::Modify => match self.position(&change.path) {
+                Some(i) => {
+                    let existing = self.get_mut(i);
+                    if existing.kind == ChangeKind::Delete {
+                        existing.kind = ChangeKind::Modify;
+                    }
+                    existing.by_claude |= change.by_claude;
+                }
+                None => self.append(change),
+            },
+            ChangeKind::Delete => match self.position(&change.path) {
+                Some(i) => {
+                    let existing = self.remove(i);
+                    match existing.kind {
+                        // Never seen by the UI, nothing to report
+                        ChangeKind::Create => {}
+                        // Renamed then deleted: what the UI knows about is the old path
+                        ChangeKind::Rename => self.push(FsChange {
+                            kind: ChangeKind::Delete,
+                            path: existing.old_path.unwrap_or(existing.path),
+                            old_path: None,
+                            is_dir: change.is_dir,
+                            by_claude: existing.by_claude || change.by_claude,
+                        }),
+                        _ => self.append(FsChange {
+                            by_claude: existing.by_claude || change.by_claude,
+                            ..change
+                        }),
+                    }
+                }
+                None => self.append(change),
+            },
+        }
+    }
+
+    fn push_rename(&mut self, change: FsChange) {
+        let old = change.old_path.clone().unwrap_or_default();
+        let mut by_claude = change.by_claude;
+        let mut created_in_batch = false;
+
+        // Platforms that also report the two halves of the rename leave a delete/create behind
+        if let Some(i) = self.position(&old) {
+            let existing = self.remove(i);
+            by_claude |= existing.by_claude;
+            created_in_batch = existing.kind == ChangeKind::Create;
+        }
+        if let Some(i) = self.position(&change.path) {
+            let existing = self.remove(i);
+            by_claude |= existing.by_claude;
+        }
+
+        self.append(if created_in_batch {
+            FsChange {
+                kind: ChangeKind::Create,
+                old_path: None,
+                by_claude,
+                ..change
+            }
+        } else {
+            FsChange { by_claude, ..change }
+        });
+    }
+}
+
+/// .gitignore rules for a project, including nested .gitignore files (loaded on demand)
+/// and .git/info/exclude. Anything inside .git itself is always ignored.
+pub struct IgnoreRules {
+    root: PathBuf,
+    /// Per-directory ignore files, later ones taking precedence
+    file_names: &'static [&'static str],
+    /// Extra gitignore-style patterns relative to the root, overriding everything else
+    excludes: Option<Gitignore>,
+    matchers: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreRules {
+    pub fn new(root: &Path) -> Self {
+        IgnoreRules {
+            root: root.to_path_buf(),
+            file_names: &[".gitignore"],
+            excludes: None,
+            matchers: HashMap::new(),
+        }
+    }
+
+    /// Also honors .ignore files (as ripgrep does) and the given patterns
+    pub fn with_excludes(root: &Path, patterns: &[String]) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                eprintln!("Skipping exclude pattern {:?}: {}", pattern, e);
+            }
+        }
+        IgnoreRules {
+            root: root.to_path_buf(),
+            file_names: &[".gitignore", ".ignore"],
+            excludes: builder.build().ok(),
+            matchers: HashMap::new(),
+        }
+    }
+
+    pub fn is_ignore_file(&self, path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| self.file_names.iter().any(|f| name == *f))
+    }
+
+    /// Forget the rules of the directory holding a changed ignore file
+    pub fn invalidate(&mut self, ignore_file: &Path) {
+        if let Some(dir) = ignore_file.parent() {
+            self.matchers.remove(dir);
+        }
+    }
+
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if rel.components().next() == Some(Component::Normal(".git".as_ref())) {
+            return true;
+        }
+
+        let mut dirs = vec![self.root.clone()];
+        if let Some(parent) = rel.parent() {
+            let mut dir = self.root.clone();
+            for component in parent.components() {
+                dir.push(component);
+                dirs.push(dir.clone());
+            }
+        }
+
+        // Deeper .gitignore files override shallower ones
+        let mut ignored = false;
+        for dir in dirs {
+            match self.matcher(&dir).map(|m| m.matched_path_or_any_parents(path, is_dir)) {
+                Some(Match::Ignore(_)) => ignored = true,
+                Some(Match::Whitelist(_)) => ignored = false,
+                _ => {}
+            }
+        }
+        match self.excludes.as_ref().map(|e| e.matched_path_or_any_parents(path, is_dir)) {
+            Some(Match::Ignore(_)) => true,
+            Some(Match::Whitelist(_)) => false,
+            _ => ignored,
+        }
+    }
+
+    fn matcher(&mut self, dir: &Path) -> Option<&Gitignore> {
+        let root = self.root.clone();
+        let file_names = self.file_names;
+        self.matchers
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let mut files: Vec<PathBuf> = file_names.iter().map(|name| dir.join(name)).collect();
+                if dir == root {
+                    files.push(root.join(".git").join("info").join("exclude"));
+                }
+                let files: Vec<PathBuf> = files.into_iter().filter(|f| f.is_file()).collect();
+                if files.is_empty() {
+                    return None;
+                }
+                let mut builder = GitignoreBuilder::new(dir);
+                for file in &files {
+                    if let Some(e) = builder.add(file) {
+                        eprintln!("Ignoring unreadable {}: {}", file.display(), e);
+                    }
+                }
+                builder.build().ok()
+            })
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(kind: ChangeKind, path: &str, old_path: Option<&str>) -> FsChange {
+        FsChange {
+            kind,
+            path: path.to_string(),
+            old_path: old_path.map(str::to_string),
+            is_dir: false,
+            by_claude: false,
+        }
+    }
+
+    fn kinds(batch: &mut ChangeBatch) -> Vec<(ChangeKind, String)> {
+        batch.take().into_iter().map(|c| (c.kind, c.path)).collect()
+    }
+
+    #[test]
+    fn batch_coalesces_changes_per_path() {
+        let mut batch = ChangeBatch::default();
+        // Created and deleted within the window: never reported
+        batch.push(change(ChangeKind::Create, "/p/tmp", None));
+        batch.push(change(ChangeKind::Delete, "/p/tmp", None));
+        // Saved through a temp file: delete then create reads as a modification
+        batch.push(change(ChangeKind::Delete, "/p/a", None));
+        batch.push(change(ChangeKind::Create, "/p/a", None));
+        batch.push(change(ChangeKind::Modify, "/p/a", None));
+        batch.push(change(ChangeKind::Modify, "/p/b", None));
+        assert_eq!(
+            kinds(&mut batch),
+            vec![(ChangeKind::Modify, "/p/a".to_string()), (ChangeKind::Modify, "/p/b".to_string())]
+        );
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn batch_folds_rename_halves() {
+        let mut batch = ChangeBatch::default();
+        batch.push(change(ChangeKind::Delete, "/p/old", None));
+        batch.push(change(ChangeKind::Create, "/p/new", None));
+        batch.push(change(ChangeKind::Rename, "/p/new", Some("/p/old")));
+        let changes = batch.take();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Rename);
+        assert_eq!(changes[0].old_path.as_deref(), Some("/p/old"));
+
+        // A file created in the batch and then renamed is just a new file
+        batch.push(change(ChangeKind::Create, "/p/x", None));
+        batch.push(change(ChangeKind::Rename, "/p/y", Some("/p/x")));
+        assert_eq!(kinds(&mut batch), vec![(ChangeKind::Create, "/p/y".to_string())]);
+
+        // Renamed and then deleted: the UI only knew the old path
+        batch.push(change(ChangeKind::Rename, "/p/y", Some("/p/x")));
+        batch.push(change(ChangeKind::Delete, "/p/y", None));
+        assert_eq!(kinds(&mut batch), vec![(ChangeKind::Delete, "/p/x".to_string())]);
+    }
+}
@@ -17,6 +17,7 @@ export const ConversationList = memo(function ConversationList() {
   const projectPath = useProjectStore((s) => s.currentProject)
   const conversations = useConversationStore((s) => s.conversations)
   const activeConversationId = useConversationStore((s) => s.activeConversationId)
+  const conversationsError = useConversationStore((s) => s.conversationsError)
   const selectConversation = useConversationStore((s) => s.selectConversation)
   const newConversation = useConversationStore((s) => s.newConversation)
   const deleteConversation = useConversationStore((s) => s.deleteConversation)
@@ -113,7 +114,12 @@ export const ConversationList = memo(function ConversationList() {
       {/* List */}
       {isExpanded && (
         <div className="max-h-48 overflow-y-auto">
-          {conversations.length === 0 ? (
+          {conversationsError && (
+            <div className="px-4 py-2 text-xs text-claude-error">
+              Couldn't load conversations: {conversationsError.message}
+            </div>
+          )}
+          {conversationsError && conversations.length === 0 ? null : conversations.length === 0 ? (
             <div className="px-4 py-3 text-xs text-claude-text-secondary text-center">
               No conversations yet.
               <button
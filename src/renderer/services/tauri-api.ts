@@ -1,29 +1,35 @@
 import { invoke } from '@tauri-apps/api/core'
 import { listen, UnlistenFn } from '@tauri-apps/api/event'
-import { open } from '@tauri-apps/plugin-dialog'
-import { FileEntry, ConversationMessage, ClaudeOutput, FileChange, PermissionRequest, ToolResult, Conversation, ConversationSummary, TerminalOutput } from '../types'
+import { FileEntry, ConversationMessage, ClaudeOutput, FileChange, FileChangeBatch, PermissionRequest, ToolResult, Conversation, ConversationSummary, ConversationPage, TerminalOutput, DrodeErrorCode, FileVersion } from '../types'
+import { toDrodeError } from '../utils/errors'
 
 interface OperationResult {
   success: boolean
   error?: string
+  code?: DrodeErrorCode
   content?: string
 }
 
+// Commands resolve with their value and reject with a DrodeError; fold that into
+// the OperationResult shape the components expect
+async function runOperation(command: string, args?: Record<string, unknown>): Promise<OperationResult> {
+  try {
+    const value = await invoke<unknown>(command, args)
+    const content = value == null ? undefined : typeof value === 'object' ? JSON.stringify(value) : String(value)
+    return { success: true, content }
+  } catch (e) {
+    const error = toDrodeError(e)
+    console.error(`${command} error:`, error)
+    return { success: false, error: error.message, code: error.code }
+  }
+}
+
 // Project management
+// The backend opens the folder dialog and switches to the chosen folder itself: a
+// folder the webview names can only be opened once it is a registered project
 async function selectFolder(): Promise<string | null> {
-  console.log('selectFolder called')
   try {
-    console.log('Opening dialog...')
-    const result = await open({
-      directory: true,
-      multiple: false,
-    })
-    console.log('Dialog result:', result)
-    if (result) {
-      // After selecting folder, set it as current project
-      await invoke('set_current_project', { projectPath: result })
-    }
-    return result as string | null
+    return await invoke<string | null>('open_project_folder')
   } catch (e) {
     console.error('selectFolder error:', e)
     return null
@@ -50,7 +56,8 @@ async function getCurrentProject(): Promise<string | null> {
 
 async function setCurrentProject(path: string): Promise<boolean> {
   try {
-    return await invoke('set_current_project', { projectPath: path })
+    await invoke('set_current_project', { projectPath: path })
+    return true
   } catch (e) {
     console.error('setCurrentProject error:', e)
     return false
@@ -67,66 +74,52 @@ async function removeRecentProject(path: string): Promise<string[]> {
 }
 
 // File system
+// Rejects with a DrodeError so the file tree can show why a folder couldn't be read
 async function readDirectory(path: string): Promise<FileEntry[]> {
   try {
     return await invoke('read_directory', { dirPath: path })
   } catch (e) {
-    console.error('readDirectory error:', e)
-    return []
+    throw toDrodeError(e)
   }
 }
 
-async function readFile(path: string): Promise<OperationResult> {
+// `version` is what was read; pass it back to writeFile as `expected`
+async function readFile(path: string): Promise<OperationResult & { version?: FileVersion }> {
   try {
-    return await invoke('read_file', { filePath: path })
+    const file = await invoke<{ content: string; version: FileVersion }>('read_file', { filePath: path })
+    return { success: true, content: file.content, version: file.version }
   } catch (e) {
-    return { success: false, error: String(e) }
+    const error = toDrodeError(e)
+    console.error('read_file error:', error)
+    return { success: false, error: error.message, code: error.code }
   }
 }
 
-async function writeFile(path: string, content: string): Promise<OperationResult> {
-  try {
-    return await invoke('write_file', { filePath: path, content })
-  } catch (e) {
-    console.error('writeFile error:', e)
-    return { success: false, error: String(e) }
-  }
+// With `expected` the save is refused with a 'conflict' if the file changed on disk since
+// that version; on success `content` is the new FileVersion as JSON
+async function writeFile(path: string, content: string, expected?: Partial<FileVersion>): Promise<OperationResult> {
+  return runOperation('write_file', {
+    filePath: path,
+    content,
+    expectedMtime: expected?.mtime,
+    expectedHash: expected?.hash,
+  })
 }
 
 async function createFile(path: string): Promise<OperationResult> {
-  try {
-    return await invoke('create_file', { filePath: path })
-  } catch (e) {
-    console.error('createFile error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('create_file', { filePath: path })
 }
 
 async function createDirectory(path: string): Promise<OperationResult> {
-  try {
-    return await invoke('create_directory', { dirPath: path })
-  } catch (e) {
-    console.error('createDirectory error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('create_directory', { dirPath: path })
 }
 
 async function deleteFile(path: string): Promise<OperationResult> {
-  try {
-    return await invoke('delete_file', { filePath: path })
-  } catch (e) {
-    console.error('deleteFile error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('delete_file', { filePath: path })
 }
 
 async function renameFile(oldPath: string, newPath: string): Promise<OperationResult> {
-  try {
-    return await invoke('rename_file', { oldPath, newPath })
-  } catch (e) {
-    console.error('renameFile error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('rename_file', { oldPath, newPath })
 }
 
 async function fileExists(path: string): Promise<boolean> {
@@ -140,30 +133,15 @@ async function fileExists(path: string): Promise<boolean> {
 
 // Claude CLI
 async function startClaudeCli(projectPath: string): Promise<OperationResult> {
-  try {
-    return await invoke('start_claude_cli', { projectPath })
-  } catch (e) {
-    console.error('startClaudeCli error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('start_claude_cli', { projectPath })
 }
 
 async function sendToClaude(message: string, sessionId?: string): Promise<OperationResult> {
-  try {
-    return await invoke('send_to_claude', { message, sessionId: sessionId || null })
-  } catch (e) {
-    console.error('sendToClaude error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('send_to_claude', { message, sessionId: sessionId || null })
 }
 
 async function stopClaudeCli(): Promise<OperationResult> {
-  try {
-    return await invoke('stop_claude_cli')
-  } catch (e) {
-    console.error('stopClaudeCli error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('stop_claude_cli')
 }
 
 async function isClaudeRunning(): Promise<boolean> {
@@ -185,21 +163,11 @@ async function getDangerousMode(): Promise<boolean> {
 }
 
 async function setDangerousMode(enabled: boolean): Promise<OperationResult> {
-  try {
-    return await invoke('set_dangerous_mode', { enabled })
-  } catch (e) {
-    console.error('setDangerousMode error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('set_dangerous_mode', { enabled })
 }
 
 async function respondToTool(toolUseId: string, result: string, isError: boolean): Promise<OperationResult> {
-  try {
-    return await invoke('respond_to_tool', { toolUseId, result, isError })
-  } catch (e) {
-    console.error('respondToTool error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('respond_to_tool', { toolUseId, result, isError })
 }
 
 // Event listeners
@@ -252,8 +220,8 @@ function onClaudeError(callback: (data: { error: string }) => void): () => void
 function onFileChange(callback: (data: FileChange) => void): () => void {
   let unlisten: UnlistenFn | null = null
 
-  listen<FileChange>('file-change', (event) => {
-    callback(event.payload)
+  listen<FileChangeBatch>('fs-change', (event) => {
+    event.payload.changes.forEach(callback)
   }).then((fn) => {
     unlisten = fn
   }).catch((e) => {
@@ -299,12 +267,7 @@ function onToolResult(callback: (data: ToolResult) => void): () => void {
 
 // Conversation persistence
 async function saveConversation(projectPath: string, messages: ConversationMessage[]): Promise<OperationResult> {
-  try {
-    return await invoke('save_conversation', { projectPath, messages })
-  } catch (e) {
-    console.error('saveConversation error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('save_conversation', { projectPath, messages })
 }
 
 async function loadConversation(projectPath: string): Promise<ConversationMessage[]> {
@@ -317,21 +280,17 @@ async function loadConversation(projectPath: string): Promise<ConversationMessag
 }
 
 async function clearConversation(projectPath: string): Promise<OperationResult> {
-  try {
-    return await invoke('clear_conversation', { projectPath })
-  } catch (e) {
-    console.error('clearConversation error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('clear_conversation', { projectPath })
 }
 
 // Multi-conversation APIs
+// Rejects with a DrodeError: an empty list would read as "no conversations yet"
 async function listConversations(projectPath: string): Promise<ConversationSummary[]> {
   try {
-    return await invoke('list_conversations', { projectPath })
+    const page = await invoke<ConversationPage>('list_conversations', { projectPath })
+    return page.conversations
   } catch (e) {
-    console.error('listConversations error:', e)
-    return []
+    throw toDrodeError(e)
   }
 }
 
@@ -358,21 +317,11 @@ async function saveConversationMessages(
   conversationId: string,
   messages: ConversationMessage[]
 ): Promise<OperationResult> {
-  try {
-    return await invoke('save_conversation_messages', { projectPath, conversationId, messages })
-  } catch (e) {
-    console.error('saveConversationMessages error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('save_conversation_messages', { projectPath, conversationId, messages })
 }
 
 async function deleteConversation(projectPath: string, conversationId: string): Promise<OperationResult> {
-  try {
-    return await invoke('delete_conversation', { projectPath, conversationId })
-  } catch (e) {
-    console.error('deleteConversation error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('delete_conversation', { projectPath, conversationId })
 }
 
 async function renameConversation(
@@ -380,49 +329,29 @@ async function renameConversation(
   conversationId: string,
   newName: string
 ): Promise<OperationResult> {
-  try {
-    return await invoke('rename_conversation', { projectPath, conversationId, newName })
-  } catch (e) {
-    console.error('renameConversation error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('rename_conversation', { projectPath, conversationId, newName })
 }
 
+// Rejects with a DrodeError: null would read as "no active conversation"
 async function getActiveConversation(projectPath: string): Promise<string | null> {
   try {
     return await invoke('get_active_conversation', { projectPath })
   } catch (e) {
-    console.error('getActiveConversation error:', e)
-    return null
+    throw toDrodeError(e)
   }
 }
 
 async function setActiveConversation(projectPath: string, conversationId: string): Promise<OperationResult> {
-  try {
-    return await invoke('set_active_conversation', { projectPath, conversationId })
-  } catch (e) {
-    console.error('setActiveConversation error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('set_active_conversation', { projectPath, conversationId })
 }
 
 // Terminal APIs
 async function runTerminalCommand(command: string, terminalId: string): Promise<OperationResult> {
-  try {
-    return await invoke('run_terminal_command', { command, terminalId })
-  } catch (e) {
-    console.error('runTerminalCommand error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('run_terminal_command', { command, terminalId })
 }
 
 async function killTerminalProcess(terminalId: string): Promise<OperationResult> {
-  try {
-    return await invoke('kill_terminal_process', { terminalId })
-  } catch (e) {
-    console.error('killTerminalProcess error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('kill_terminal_process', { terminalId })
 }
 
 function onTerminalOutput(callback: (data: TerminalOutput) => void): () => void {
@@ -460,12 +389,7 @@ async function listPorts(): Promise<PortInfo[]> {
 }
 
 async function killPort(port: number): Promise<OperationResult> {
-  try {
-    return await invoke('kill_port', { port })
-  } catch (e) {
-    console.error('killPort error:', e)
-    return { success: false, error: String(e) }
-  }
+  return runOperation('kill_port', { port })
 }
 
 // Create the API object that matches the Electron API interface
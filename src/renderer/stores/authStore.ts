@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/core'
 import { listen, UnlistenFn } from '@tauri-apps/api/event'
+import { needsReauth } from '../utils/errors'
 
 interface ProviderState {
   connected: boolean
@@ -20,6 +21,8 @@ interface AuthActions {
   connect: (provider: string) => Promise<void>
   disconnect: (provider: string) => Promise<void>
   loadStatus: () => Promise<void>
+  // Call from a failed provider request; drops the connection when the credentials are gone or rejected
+  handleApiError: (provider: keyof AuthState, error: unknown) => void
 }
 
 const defaultProvider: ProviderState = { connected: false }
@@ -101,4 +104,10 @@ export const useAuthStore = create<AuthState & AuthActions>((set, get) => ({
       console.error('disconnect error:', e)
     }
   },
+
+  handleApiError: (provider, error) => {
+    if (needsReauth(error)) {
+      set({ [provider]: { connected: false } } as any)
+    }
+  },
 }))
@@ -1,9 +1,11 @@
 import { create } from 'zustand'
 import { subscribeWithSelector } from 'zustand/middleware'
-import { ConversationMessage, ConversationSummary } from '../types'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+import { ConversationMessage, ConversationSummary, DrodeError } from '../types'
 import { getClaudeBridge, ClaudeCodeBridge } from '../services/claudeCodeBridge'
 import { useProjectStore } from './projectStore'
 import { v4 as uuidv4 } from 'uuid'
+import { toDrodeError } from '../utils/errors'
 
 type ClaudeStatus = 'running' | 'stopped' | 'error' | 'starting'
 
@@ -16,6 +18,8 @@ interface ConversationState {
   rawOutput: string
   conversations: ConversationSummary[]
   activeConversationId: string | null
+  // Why the conversation list couldn't be loaded
+  conversationsError: DrodeError | null
 }
 
 interface ConversationActions {
@@ -40,12 +44,21 @@ async function loadConversationsList() {
   const projectPath = useProjectStore.getState().currentProject
   if (!projectPath) return
 
-  const list = await window.electronAPI.listConversations(projectPath)
+  let list: ConversationSummary[]
+  let activeId: string | null
+  try {
+    list = await window.electronAPI.listConversations(projectPath)
+    activeId = await window.electronAPI.getActiveConversation(projectPath)
+  } catch (e) {
+    // Keep what is shown, and don't start a new conversation over ones that failed to load
+    useConversationStore.setState({ conversationsError: toDrodeError(e) })
+    return
+  }
+  useConversationStore.setState({ conversationsError: null })
   const sorted = list.sort((a, b) => b.updatedAt - a.updatedAt)
 
   const store = useConversationStore.getState()
 
-  const activeId = await window.electronAPI.getActiveConversation(projectPath)
   if (activeId && sorted.find((c) => c.id === activeId)) {
     useConversationStore.setState({
       conversations: sorted,
@@ -113,6 +126,7 @@ export const useConversationStore = create<ConversationState & ConversationActio
   rawOutput: '',
   conversations: [],
   activeConversationId: null,
+  conversationsError: null,
 
   init: () => {
     bridge = getClaudeBridge()
@@ -146,6 +160,17 @@ export const useConversationStore = create<ConversationState & ConversationActio
       }
     })
 
+    // Titles and summaries generated in the background (commands/summaries.rs)
+    let unlistenSummary: UnlistenFn | null = null
+    listen<{ conversationId: string; name: string; summary: string }>('conversation-summary-updated', (event) => {
+      const { conversationId, name, summary } = event.payload
+      set((s) => ({
+        conversations: s.conversations.map((c) =>
+          c.id === conversationId ? { ...c, name, summary } : c
+        ),
+      }))
+    }).then((fn) => { unlistenSummary = fn })
+
     // Subscribe to project changes
     const unsubProject = useProjectStore.subscribe(
       (state) => state.currentProject,
@@ -156,6 +181,7 @@ export const useConversationStore = create<ConversationState & ConversationActio
           set({
             conversations: [],
             activeConversationId: null,
+            conversationsError: null,
             messages: [],
             rawOutput: '',
           })
@@ -188,6 +214,7 @@ export const useConversationStore = create<ConversationState & ConversationActio
       cleanupStatus()
       unsubProject()
       unsubConversation()
+      if (unlistenSummary) unlistenSummary()
       if (loadingTimeout) { clearTimeout(loadingTimeout); loadingTimeout = null }
       if (saveTimeout) { clearTimeout(saveTimeout); saveTimeout = null }
     }
@@ -347,7 +374,7 @@ export const useConversationStore = create<ConversationState & ConversationActio
     await window.electronAPI.renameConversation(projectPath, conversationId, newName)
     set((s) => ({
       conversations: s.conversations.map((c) =>
-        c.id === conversationId ? { ...c, name: newName } : c
+        c.id === conversationId ? { ...c, name: newName, titleSource: 'user' } : c
       ),
     }))
   },
@@ -1,6 +1,7 @@
 import { create } from 'zustand'
-import { FileEntry, FileChange } from '../types'
+import { FileEntry, FileChange, FileVersion } from '../types'
 import { useProjectStore } from './projectStore'
+import { errorMessage } from '../utils/errors'
 
 const FILE_CACHE_TTL = 5 * 60 * 1000 // 5 minutes
 const DIR_CACHE_TTL = 30 * 1000 // 30 seconds
@@ -10,6 +11,10 @@ const DEBOUNCE_MS = 100
 const fileCache = new Map<string, { content: string; timestamp: number }>()
 const dirCache = new Map<string, { entries: FileEntry[]; timestamp: number }>()
 const pendingPreloads = new Set<string>()
+// Hash of each file as last read or saved, sent with the next save so it is refused if
+// the file changed on disk in between. Kept when the watcher reports a change, since the
+// open editor still holds what was read.
+const fileVersions = new Map<string, string>()
 let cleanupFn: (() => void) | null = null
 let refreshTimeout: ReturnType<typeof setTimeout> | null = null
 
@@ -18,6 +23,8 @@ interface FileSystemState {
   isLoading: boolean
   error: string | null
   changedFiles: Set<string>
+  // Subset of changedFiles last changed during a Claude run, for the "edited by Claude" badge
+  claudeChangedFiles: Set<string>
 }
 
 interface FileSystemActions {
@@ -25,12 +32,14 @@ interface FileSystemActions {
   loadDirectory: (dirPath: string) => Promise<void>
   loadSubdirectory: (dirPath: string) => Promise<FileEntry[]>
   readFile: (filePath: string) => Promise<string | null>
+  // false if the save failed or the file changed on disk since it was read (`error` says which)
   writeFile: (filePath: string, content: string) => Promise<boolean>
   createFile: (filePath: string) => Promise<boolean>
   createDirectory: (dirPath: string) => Promise<boolean>
   deleteFile: (filePath: string) => Promise<boolean>
   renameFile: (oldPath: string, newPath: string) => Promise<boolean>
   isFileChanged: (filePath: string) => boolean
+  isChangedByClaude: (filePath: string) => boolean
   isFileCached: (filePath: string) => boolean
   preloadFile: (filePath: string) => Promise<void>
   refresh: () => void
@@ -42,19 +51,32 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
   isLoading: false,
   error: null,
   changedFiles: new Set(),
+  claudeChangedFiles: new Set(),
 
   init: () => {
     const handleFileChange = (change: FileChange) => {
       fileCache.delete(change.path)
+      if (change.oldPath) fileCache.delete(change.oldPath)
 
       set((s) => {
-        const next = new Set(s.changedFiles)
-        if (change.type === 'add' || change.type === 'change') {
-          next.add(change.path)
-        } else if (change.type === 'unlink') {
-          next.delete(change.path)
+        const changed = new Set(s.changedFiles)
+        const byClaude = new Set(s.claudeChangedFiles)
+        if (change.oldPath) {
+          changed.delete(change.oldPath)
+          byClaude.delete(change.oldPath)
+        }
+        if (change.kind === 'delete') {
+          changed.delete(change.path)
+          byClaude.delete(change.path)
+        } else if (!change.isDir) {
+          changed.add(change.path)
+          if (change.byClaude) {
+            byClaude.add(change.path)
+          } else {
+            byClaude.delete(change.path)
+          }
         }
-        return { changedFiles: next }
+        return { changedFiles: changed, claudeChangedFiles: byClaude }
       })
 
       if (refreshTimeout) clearTimeout(refreshTimeout)
@@ -110,7 +132,7 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
       dirCache.set(dirPath, { entries: mappedEntries, timestamp: Date.now() })
       set({ files: mappedEntries, isLoading: false })
     } catch (err) {
-      set({ error: String(err), isLoading: false })
+      set({ error: errorMessage(err), isLoading: false })
     }
   },
 
@@ -135,7 +157,9 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
       set((s) => {
         const next = new Set(s.changedFiles)
         next.delete(filePath)
-        return { changedFiles: next }
+        const byClaude = new Set(s.claudeChangedFiles)
+        byClaude.delete(filePath)
+        return { changedFiles: next, claudeChangedFiles: byClaude }
       })
       return cached.content
     }
@@ -144,13 +168,16 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
       const result = await window.electronAPI.readFile(filePath)
       if (result.success) {
         const content = result.content || ''
+        if (result.version) fileVersions.set(filePath, result.version.hash)
         if (content.length < 5 * 1024 * 1024) {
           fileCache.set(filePath, { content, timestamp: Date.now() })
         }
         set((s) => {
           const next = new Set(s.changedFiles)
           next.delete(filePath)
-          return { changedFiles: next }
+          const byClaude = new Set(s.claudeChangedFiles)
+          byClaude.delete(filePath)
+          return { changedFiles: next, claudeChangedFiles: byClaude }
         })
         return content
       }
@@ -161,9 +188,13 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
   },
 
   writeFile: async (filePath, content) => {
-    const result = await window.electronAPI.writeFile(filePath, content)
+    const hash = fileVersions.get(filePath)
+    const result = await window.electronAPI.writeFile(filePath, content, hash ? { hash } : undefined)
     if (result.success) {
       fileCache.set(filePath, { content, timestamp: Date.now() })
+      if (result.content) fileVersions.set(filePath, (JSON.parse(result.content) as FileVersion).hash)
+    } else if (result.code === 'conflict') {
+      set({ error: `${filePath} changed on disk since it was opened` })
     }
     return result.success
   },
@@ -196,6 +227,7 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
     const result = await window.electronAPI.deleteFile(filePath)
     if (result.success) {
       fileCache.delete(filePath)
+      fileVersions.delete(filePath)
       const projectPath = useProjectStore.getState().currentProject
       if (projectPath) {
         dirCache.delete(projectPath)
@@ -213,6 +245,11 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
         fileCache.delete(oldPath)
         fileCache.set(newPath, cached)
       }
+      const hash = fileVersions.get(oldPath)
+      if (hash) {
+        fileVersions.delete(oldPath)
+        fileVersions.set(newPath, hash)
+      }
       const projectPath = useProjectStore.getState().currentProject
       if (projectPath) {
         dirCache.delete(projectPath)
@@ -224,6 +261,8 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
 
   isFileChanged: (filePath) => get().changedFiles.has(filePath),
 
+  isChangedByClaude: (filePath) => get().claudeChangedFiles.has(filePath),
+
   isFileCached: (filePath) => {
     const cached = fileCache.get(filePath)
     return cached !== undefined && Date.now() - cached.timestamp < FILE_CACHE_TTL
@@ -238,6 +277,7 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
       if (result.success && result.content) {
         if (result.content.length < 5 * 1024 * 1024) {
           fileCache.set(filePath, { content: result.content, timestamp: Date.now() })
+          if (result.version) fileVersions.set(filePath, result.version.hash)
         }
       }
     } catch {
@@ -258,5 +298,6 @@ export const useFileSystemStore = create<FileSystemState & FileSystemActions>((s
   clearCache: () => {
     fileCache.clear()
     dirCache.clear()
+    fileVersions.clear()
   },
 }))
@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/core'
 import { logActivity } from '../services/activityBus'
+import { useAuthStore } from './authStore'
 
 export interface GithubRepo {
   id: number
@@ -70,6 +71,11 @@ interface GithubActions {
   createPullRequest: (title: string, body: string, head: string, base: string) => Promise<GithubPullRequest | null>
 }
 
+function reportError(action: string, e: unknown) {
+  console.error(`${action} error:`, e)
+  useAuthStore.getState().handleApiError('github', e)
+}
+
 export const useGithubStore = create<GithubState & GithubActions>((set, get) => ({
   repos: [],
   selectedRepo: null,
@@ -86,7 +92,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
       const repos = await invoke<GithubRepo[]>('github_list_repos', { page })
       set({ repos, isLoading: false })
     } catch (e) {
-      console.error('loadRepos error:', e)
+      reportError('loadRepos', e)
       set({ isLoading: false })
     }
   },
@@ -117,7 +123,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
       })
       set({ tree, isLoading: false })
     } catch (e) {
-      console.error('loadTree error:', e)
+      reportError('loadTree', e)
       set({ isLoading: false })
     }
   },
@@ -133,7 +139,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
       })
       set({ branches })
     } catch (e) {
-      console.error('loadBranches error:', e)
+      reportError('loadBranches', e)
     }
   },
 
@@ -154,7 +160,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
         path,
       })
     } catch (e) {
-      console.error('readFile error:', e)
+      reportError('readFile', e)
       return null
     }
   },
@@ -176,7 +182,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
       logActivity('github', 'file_committed', `Committed changes to ${path} on ${currentBranch}`)
       return true
     } catch (e) {
-      console.error('updateFile error:', e)
+      reportError('updateFile', e)
       return false
     }
   },
@@ -196,7 +202,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
       await get().loadBranches()
       return true
     } catch (e) {
-      console.error('createBranch error:', e)
+      reportError('createBranch', e)
       return false
     }
   },
@@ -213,7 +219,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
       })
       set({ pullRequests })
     } catch (e) {
-      console.error('loadPullRequests error:', e)
+      reportError('loadPullRequests', e)
     }
   },
 
@@ -234,7 +240,7 @@ export const useGithubStore = create<GithubState & GithubActions>((set, get) =>
       await get().loadPullRequests()
       return pr
     } catch (e) {
-      console.error('createPullRequest error:', e)
+      reportError('createPullRequest', e)
       return null
     }
   },
@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/core'
 import { logActivity } from '../services/activityBus'
+import { useAuthStore } from './authStore'
 
 export interface SupabaseProject {
   id: string
@@ -60,6 +61,11 @@ interface SupabaseActions {
   setSqlQuery: (query: string) => void
 }
 
+function reportError(action: string, e: unknown) {
+  console.error(`${action} error:`, e)
+  useAuthStore.getState().handleApiError('supabase', e)
+}
+
 export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, get) => ({
   projects: [],
   selectedProject: null,
@@ -78,7 +84,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       const projects = await invoke<SupabaseProject[]>('supabase_list_projects')
       set({ projects, isLoading: false })
     } catch (e) {
-      console.error('loadProjects error:', e)
+      reportError('loadProjects', e)
       set({ isLoading: false })
     }
   },
@@ -105,7 +111,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       })
       set({ tables, isLoading: false })
     } catch (e) {
-      console.error('loadTables error:', e)
+      reportError('loadTables', e)
       set({ isLoading: false })
     }
   },
@@ -132,7 +138,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       })
       set({ tableData, isLoading: false })
     } catch (e) {
-      console.error('loadTableData error:', e)
+      reportError('loadTableData', e)
       set({ isLoading: false })
     }
   },
@@ -150,7 +156,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       set({ sqlResult, isLoading: false })
       logActivity('supabase', 'query_executed', `Ran SQL query on ${selectedProject.name}`)
     } catch (e) {
-      console.error('runSql error:', e)
+      reportError('runSql', e)
       set({ isLoading: false })
     }
   },
@@ -169,7 +175,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       await get().loadTableData()
       return true
     } catch (e) {
-      console.error('insertRow error:', e)
+      reportError('insertRow', e)
       return false
     }
   },
@@ -189,7 +195,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       await get().loadTableData()
       return true
     } catch (e) {
-      console.error('updateRow error:', e)
+      reportError('updateRow', e)
       return false
     }
   },
@@ -208,7 +214,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       await get().loadTableData()
       return true
     } catch (e) {
-      console.error('deleteRow error:', e)
+      reportError('deleteRow', e)
       return false
     }
   },
@@ -227,7 +233,7 @@ export const useSupabaseStore = create<SupabaseState & SupabaseActions>((set, ge
       await get().loadTables()
       return true
     } catch (e) {
-      console.error('runMigration error:', e)
+      reportError('runMigration', e)
       return false
     }
   },
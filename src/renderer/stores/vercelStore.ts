@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/core'
 import { logActivity } from '../services/activityBus'
+import { useAuthStore } from './authStore'
 
 export interface VercelProject {
   id: string
@@ -62,6 +63,11 @@ interface VercelActions {
   stopPolling: () => void
 }
 
+function reportError(action: string, e: unknown) {
+  console.error(`${action} error:`, e)
+  useAuthStore.getState().handleApiError('vercel', e)
+}
+
 export const useVercelStore = create<VercelState & VercelActions>((set, get) => ({
   projects: [],
   selectedProject: null,
@@ -78,7 +84,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       const projects = await invoke<VercelProject[]>('vercel_list_projects')
       set({ projects, isLoading: false })
     } catch (e) {
-      console.error('loadProjects error:', e)
+      reportError('loadProjects', e)
       set({ isLoading: false })
     }
   },
@@ -111,7 +117,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
         get().startPolling()
       }
     } catch (e) {
-      console.error('loadDeployments error:', e)
+      reportError('loadDeployments', e)
       set({ isLoading: false })
     }
   },
@@ -128,7 +134,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       })
       set({ deploymentLogs })
     } catch (e) {
-      console.error('loadDeploymentLogs error:', e)
+      reportError('loadDeploymentLogs', e)
     }
   },
 
@@ -146,7 +152,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       get().startPolling()
       return deployment
     } catch (e) {
-      console.error('triggerDeploy error:', e)
+      reportError('triggerDeploy', e)
       return null
     }
   },
@@ -161,7 +167,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       })
       set({ domains })
     } catch (e) {
-      console.error('loadDomains error:', e)
+      reportError('loadDomains', e)
     }
   },
 
@@ -178,7 +184,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       await get().loadDomains()
       return true
     } catch (e) {
-      console.error('addDomain error:', e)
+      reportError('addDomain', e)
       return false
     }
   },
@@ -195,7 +201,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       await get().loadDomains()
       return true
     } catch (e) {
-      console.error('removeDomain error:', e)
+      reportError('removeDomain', e)
       return false
     }
   },
@@ -206,7 +212,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       await get().loadDeployments()
       return true
     } catch (e) {
-      console.error('promoteDeploy error:', e)
+      reportError('promoteDeploy', e)
       return false
     }
   },
@@ -224,7 +230,7 @@ export const useVercelStore = create<VercelState & VercelActions>((set, get) =>
       await get().loadDeployments()
       return true
     } catch (e) {
-      console.error('rollbackDeploy error:', e)
+      reportError('rollbackDeploy', e)
       return false
     }
   },
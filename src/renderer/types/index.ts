@@ -4,10 +4,83 @@ export interface FileEntry {
   isDirectory: boolean
   size: number
   modified: string
+  isHidden: boolean
+  // Gitignored; dimmed in the tree unless the project hides these
+  isIgnored: boolean
+  isSymlink: boolean
   children?: FileEntry[]
   isExpanded?: boolean
 }
 
+export interface VisibilityRules {
+  showHidden: boolean
+  // Gitignore-style, relative to the project root; `!.github` re-includes a hidden entry
+  excludes: string[]
+  hideIgnored: boolean
+}
+
+export type ConflictPolicy = 'skip' | 'overwrite' | 'rename'
+
+// `file-op-progress` event payload
+export interface FileOpProgress {
+  operationId: string
+  processed: number
+  total: number
+  path: string
+}
+
+// `file-op-done` event payload
+export interface FileOpSummary {
+  operationId: string
+  done: { from: string; to: string }[]
+  skipped: string[]
+  failed: { path: string; error: DrodeError }[]
+  fileCount: number
+}
+
+export interface DeletedItem {
+  path: string
+  // Seconds since the epoch
+  deletedAt: number
+}
+
+export type GitFileKind =
+  | 'modified'
+  | 'added'
+  | 'deleted'
+  | 'renamed'
+  | 'copied'
+  | 'typechange'
+  | 'untracked'
+  | 'conflicted'
+
+export interface GitPathStatus {
+  kind: GitFileKind
+  staged: boolean
+  unstaged: boolean
+  oldPath?: string
+}
+
+export interface GitDirStatus {
+  kind: 'conflicted' | 'modified' | 'untracked'
+  // Changed files below the folder
+  count: number
+}
+
+// Also the `git-status-changed` event payload, with `projectPath`
+export interface GitStatus {
+  isRepo: boolean
+  branch: string | null
+  head: string | null
+  upstream: string | null
+  ahead: number
+  behind: number
+  // Keyed by the same absolute paths as FileEntry.path
+  files: Record<string, GitPathStatus>
+  directories: Record<string, GitDirStatus>
+  updatedAt: number
+}
+
 export interface MessageMetadata {
   durationMs?: number
   durationApiMs?: number
@@ -29,9 +102,215 @@ export interface ConversationMessage {
   toolResults?: Record<string, ToolResult>
 }
 
+// One entry of an `fs-change` batch from the project watcher (watcher.rs)
 export interface FileChange {
-  type: 'add' | 'change' | 'unlink' | 'addDir' | 'unlinkDir'
+  kind: 'create' | 'modify' | 'delete' | 'rename'
+  path: string
+  oldPath?: string
+  isDir: boolean
+  // Happened while a Claude run was active in the project
+  byClaude: boolean
+}
+
+export interface FileChangeBatch {
+  projectPath: string
+  changes: FileChange[]
+}
+
+// On-disk version of a file (get_file_version / write_file), for conflict-checked saves
+export interface FileVersion {
+  mtime: number
+  hash: string
+  size: number
+}
+
+// details of a 'conflict' error from a conditional write_file. currentContent and
+// currentVersion are null when the file was deleted.
+export interface FileConflictDetails {
+  paths: string[]
+  currentContent: string | null
+  currentVersion: FileVersion | null
+}
+
+// read_file_range / get_file_info (file_read.rs)
+export type FileEncoding = 'utf-8' | 'utf-16le' | 'utf-16be' | 'latin-1'
+export type LineEnding = 'lf' | 'crlf' | 'cr' | 'mixed' | 'none'
+
+export interface ReadRange {
+  // 1-based
+  startLine?: number
+  lineCount?: number
+  // Bytes into the file
+  offset?: number
+  length?: number
+  hex?: boolean
+}
+
+export interface FileChunk {
+  size: number
+  binary: boolean
+  encoding: FileEncoding | null
+  bom: boolean
+  lineEnding: LineEnding
+  // Decoded text, or hexdump -C style rows when hex is set
+  content: string
+  hex: boolean
+  // Byte range of the file that content covers
+  start: number
+  end: number
+  startLine: number | null
+  totalLines: number | null
+  eof: boolean
+}
+
+export interface FileInfo {
+  size: number
+  binary: boolean
+  encoding: FileEncoding | null
+  bom: boolean
+  lineEnding: LineEnding
+  lineCount: number | null
+}
+
+// Quick-open result from find_files (file_index.rs)
+export interface FileMatch {
+  path: string
+  relativePath: string
+  score: number
+  // Matched characters of relativePath, as code point offsets
+  positions: number[]
+}
+
+// Content search (search_in_files in file_search.rs)
+export interface SearchInFilesOptions {
+  query: string
+  regex?: boolean
+  caseSensitive?: boolean
+  include?: string[]
+  exclude?: string[]
+  includeIgnored?: boolean
+  contextLines?: number
+  maxResults?: number
+  maxFileSize?: number
+}
+
+export interface FileSearchMatch {
+  path: string
+  relativePath: string
+  line: number
+  // 1-based, UTF-16 units of the full line
+  column: number
+  // The line, possibly shortened around the match
+  text: string
+  // UTF-16 offsets into text
+  ranges: { start: number; end: number }[]
+  before: string[]
+  after: string[]
+}
+
+export interface FileSearchResults {
+  searchId: string
+  matches: FileSearchMatch[]
+}
+
+export interface FileSearchDone {
+  searchId: string
+  matchCount: number
+  fileCount: number
+  searchedFiles: number
+  skippedFiles: number
+  truncated: boolean
+  cancelled: boolean
+  durationMs: number
+}
+
+// Find and replace (commands/replace.rs). Edits are picked by id when applying a plan.
+export interface ReplaceEdit {
+  id: number
+  line: number
+  column: number
+  before: string
+  after: string
+  replacement: string
+}
+
+export interface ReplacePlan {
+  id: string
+  projectPath: string
+  query: string
+  replacement: string
+  files: { path: string; relativePath: string; edits: ReplaceEdit[] }[]
+  editCount: number
+  skippedFiles: number
+  truncated: boolean
+  createdAt: number
+}
+
+export interface ReplaceOutcome {
+  undoId: string
+  filesChanged: number
+  editsApplied: number
+}
+
+export interface ReplaceUndo {
+  id: string
+  projectPath: string
+  query: string
+  replacement: string
+  fileCount: number
+  editCount: number
+  createdAt: number
+}
+
+// Local history (commands/history.rs)
+export interface HistoryEntry {
+  id: number
+  path: string
+  oldPath?: string
+  source: 'save' | 'external' | 'rename' | 'delete' | 'restore'
+  hash: string
+  size: number
+  createdAt: number
+}
+
+export interface HistorySettings {
+  enabled: boolean
+  trackExternal: boolean
+  // 0 keeps entries regardless of age / size
+  maxAgeDays: number
+  maxSizeMb: number
+}
+
+export type SymbolKind =
+  | 'function'
+  | 'method'
+  | 'class'
+  | 'interface'
+  | 'type'
+  | 'enum'
+  | 'struct'
+  | 'trait'
+  | 'module'
+  | 'constant'
+  | 'variable'
+  | 'macro'
+
+export interface CodeSymbol {
+  name: string
+  kind: SymbolKind
+  // Class, impl or module the symbol is declared in
+  container?: string
+  exported: boolean
   path: string
+  // Position of the name; 1-based, column in UTF-16 code units
+  line: number
+  column: number
+  startLine: number
+  endLine: number
+}
+
+export interface OutlineNode extends CodeSymbol {
+  children: OutlineNode[]
 }
 
 export interface ClaudeOutput {
@@ -73,6 +352,7 @@ export interface Conversation {
   name: string
   createdAt: number
   updatedAt: number
+  sessionId?: string
   messages: ConversationMessage[]
 }
 
@@ -82,6 +362,69 @@ export interface ConversationSummary {
   createdAt: number
   updatedAt: number
   messageCount: number
+  sessionId?: string
+  pinned: boolean
+  archived: boolean
+  tags: string[]
+  deletedAt?: number
+  summary?: string
+  // Unset while the name is still a placeholder
+  titleSource?: 'user' | 'auto'
+}
+
+export interface SummarySettings {
+  enabled: boolean
+  model: string
+  refreshEvery: number
+}
+
+export type BookmarkColor = 'yellow' | 'green' | 'blue' | 'red' | 'purple'
+
+export interface Bookmark {
+  id: number
+  conversationId: string
+  conversationName: string
+  messageId: string
+  messageRole?: 'user' | 'assistant' | 'system'
+  messageTimestamp?: number
+  note: string
+  color: BookmarkColor
+  // UTF-16 offsets into the message content; absent when the whole message is bookmarked
+  range?: { start: number; end: number }
+  quote?: string
+  excerpt: string
+  createdAt: number
+  updatedAt: number
+}
+
+export interface ConversationPage {
+  conversations: ConversationSummary[]
+  nextCursor: string | null
+}
+
+// Edit/Write/MultiEdit tool uses indexed per file (db/file_edits.rs)
+export interface FileEdit {
+  id: number
+  conversationId: string
+  conversationName: string
+  projectPath: string
+  messageId: string
+  toolUseId: string
+  tool: 'Edit' | 'Write' | 'MultiEdit'
+  filePath: string
+  oldString: string | null
+  // Replacement text, or the whole file for Write
+  newString: string | null
+  replaceAll: boolean
+  status: ToolStatus | null
+  timestamp: number
+}
+
+export interface EditedFile {
+  filePath: string
+  editCount: number
+  conversationCount: number
+  lastEditedAt: number
 }
 
 // Terminal types
@@ -92,6 +435,28 @@ export interface TerminalOutput {
   code?: number
 }
 
+// Error returned by every backend command (serialized from DrodeError in error.rs)
+export type DrodeErrorCode =
+  | 'database'
+  | 'io'
+  | 'not_found'
+  | 'spawn_failed'
+  | 'http'
+  | 'network'
+  | 'auth_missing'
+  | 'unauthorized'
+  | 'auth_locked'
+  | 'path_denied'
+  | 'conflict'
+  | 'validation'
+  | 'internal'
+
+export interface DrodeError {
+  code: DrodeErrorCode
+  message: string
+  details?: Record<string, unknown>
+}
+
 // Port information types
 export interface PortInfo {
   port: number
@@ -113,8 +478,8 @@ declare global {
 
       // File system
       readDirectory: (path: string) => Promise<FileEntry[]>
-      readFile: (path: string) => Promise<{ success: boolean; content?: string; error?: string }>
-      writeFile: (path: string, content: string) => Promise<{ success: boolean; error?: string }>
+      readFile: (path: string) => Promise<{ success: boolean; content?: string; version?: FileVersion; error?: string; code?: DrodeErrorCode }>
+      writeFile: (path: string, content: string, expected?: Partial<FileVersion>) => Promise<{ success: boolean; content?: string; error?: string; code?: DrodeErrorCode }>
       createFile: (path: string) => Promise<{ success: boolean; error?: string }>
       createDirectory: (path: string) => Promise<{ success: boolean; error?: string }>
       deleteFile: (path: string) => Promise<{ success: boolean; error?: string }>
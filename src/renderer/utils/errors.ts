@@ -0,0 +1,22 @@
+import { DrodeError } from '../types'
+
+export function isDrodeError(e: unknown): e is DrodeError {
+  return typeof e === 'object' && e !== null && 'code' in e && 'message' in e
+}
+
+// Normalise anything a rejected invoke() can throw into a DrodeError
+export function toDrodeError(e: unknown): DrodeError {
+  if (isDrodeError(e)) return e
+  if (e instanceof Error) return { code: 'internal', message: e.message }
+  return { code: 'internal', message: String(e) }
+}
+
+export function errorMessage(e: unknown): string {
+  return toDrodeError(e).message
+}
+
+// The stored credentials are missing or were rejected; the user has to reconnect
+export function needsReauth(e: unknown): boolean {
+  const { code } = toDrodeError(e)
+  return code === 'unauthorized' || code === 'auth_missing'
+}